@@ -14,16 +14,39 @@
 // limitations under the License.
 
 use std::env;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // The bindings for the gzip streams require zlib support in libprotobuf.
+    if env::var("DEP_PROTOBUF_SRC_ZLIB")? != "1" {
+        return Err("protobuf-native requires libprotobuf to be built with zlib support".into());
+    }
 
-fn main() {
     cxx_build::bridges([
         "src/compiler.rs",
+        "src/compiler/plugin.rs",
         "src/internal.rs",
         "src/io.rs",
+        "src/json.rs",
         "src/lib.rs",
+        "src/log.rs",
+        "src/text_format.rs",
+        "src/util.rs",
     ])
     .flag("-std=c++14")
-    .files(["src/compiler.cc", "src/io.cc", "src/lib.cc"])
+    .files([
+        "src/compiler.cc",
+        "src/compiler/plugin.cc",
+        "src/io.cc",
+        "src/json.cc",
+        "src/lib.cc",
+        "src/log.cc",
+        "src/text_format.cc",
+        "src/util.cc",
+    ])
     .warnings_into_errors(cfg!(deny_warnings))
     .compile("protobuf_native");
 
@@ -31,8 +54,46 @@ fn main() {
     // we'll switch to depending on protobuf-sys instead of protobuf-src,
     // and let protobuf-sys drive the linking.
     println!(
-        "cargo:rustc-link-search=native={}/lib",
-        env::var("DEP_PROTOBUF_SRC_ROOT").unwrap()
+        "cargo:rustc-link-search=native={}",
+        env::var("DEP_PROTOBUF_SRC_LIB_DIR").unwrap()
     );
-    println!("cargo:rustc-link-lib=static=protobuf");
+    let link_kind = env::var("DEP_PROTOBUF_SRC_LINK_KIND").unwrap();
+    println!("cargo:rustc-link-lib={}=protoc", link_kind);
+    println!("cargo:rustc-link-lib={}=protobuf", link_kind);
+    // A shared libprotobuf links zlib itself.
+    if link_kind == "static" {
+        println!("cargo:rustc-link-lib=z");
+    }
+
+    embed_well_known_types()
+}
+
+/// Generates a list of the .proto files for the well-known types that are
+/// installed alongside libprotobuf, for embedding via `include_bytes!`.
+fn embed_well_known_types() -> Result<(), Box<dyn Error>> {
+    let include_dir = PathBuf::from(env::var("DEP_PROTOBUF_SRC_INCLUDE_DIR")?);
+    let mut paths = vec![];
+    for entry in fs::read_dir(include_dir.join("google/protobuf"))? {
+        let path = entry?.path();
+        if path.extension() == Some("proto".as_ref()) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut out = String::from("[\n");
+    for path in paths {
+        let file_name = path.file_name().and_then(|f| f.to_str());
+        let file_name = file_name.ok_or("non-UTF-8 path")?;
+        let path = path.to_str().ok_or("non-UTF-8 path")?;
+        writeln!(
+            out,
+            "    (\"google/protobuf/{}\", include_bytes!({:?})),",
+            file_name, path
+        )?;
+    }
+    out.push(']');
+    let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+    fs::write(out_dir.join("well_known_types.rs"), out)?;
+    Ok(())
 }
@@ -0,0 +1,258 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License in the LICENSE file at the
+// root of this repository, or online at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Schema compatibility checking.
+//!
+//! This module detects changes between two versions of a schema that would
+//! break existing readers or writers of messages, such as removing a field or
+//! changing its type. It is intended for gating schema changes in CI.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::{DescriptorProto, FieldDescriptorProto, FieldLabel, FieldType, FileDescriptorSet};
+
+/// Checks whether the messages in `new` are compatible with the messages in
+/// `old`.
+///
+/// Messages are matched by their fully-qualified names. Fields are matched by
+/// name, or, if a field has been renamed, by number. Renaming a field is not
+/// considered a breaking change, as it does not affect the binary encoding.
+///
+/// The following changes are reported:
+///
+///   * A message in `old` does not exist in `new`.
+///   * A field in `old` has no field with the same name or number in `new`.
+///   * A field's number has changed.
+///   * A field's type has changed.
+///   * A field's label has changed, e.g., from optional to repeated.
+///
+/// Incompatibilities are returned in the order in which the messages and
+/// fields appear in `old`.
+pub fn check_compatibility(
+    old: &FileDescriptorSet,
+    new: &FileDescriptorSet,
+) -> Vec<Incompatibility> {
+    let new_messages: HashMap<_, _> = collect_messages(new).into_iter().collect();
+    let mut incompatibilities = vec![];
+    for (name, old_message) in collect_messages(old) {
+        match new_messages.get(&name) {
+            None => incompatibilities.push(Incompatibility {
+                message: name,
+                field: None,
+                kind: IncompatibilityKind::MessageRemoved,
+            }),
+            Some(new_message) => {
+                check_message(&name, old_message, new_message, &mut incompatibilities)
+            }
+        }
+    }
+    incompatibilities
+}
+
+/// A change between two schemas that breaks compatibility.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Incompatibility {
+    /// The fully-qualified name of the affected message, without a leading
+    /// `.`.
+    pub message: String,
+    /// The name of the affected field in the old schema, if the change
+    /// concerns a field.
+    pub field: Option<String>,
+    /// The kind of change.
+    pub kind: IncompatibilityKind,
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.field {
+            None => write!(f, "message {}", self.message)?,
+            Some(field) => write!(f, "field {}.{}", self.message, field)?,
+        }
+        match &self.kind {
+            IncompatibilityKind::MessageRemoved | IncompatibilityKind::FieldRemoved => {
+                write!(f, " was removed")
+            }
+            IncompatibilityKind::FieldNumberChanged { old, new } => {
+                write!(f, " changed number from {} to {}", old, new)
+            }
+            IncompatibilityKind::FieldTypeChanged { old, new } => {
+                write!(f, " changed type from {} to {}", old, new)
+            }
+            IncompatibilityKind::FieldLabelChanged { old, new } => write!(
+                f,
+                " changed label from {} to {}",
+                label_keyword(*old),
+                label_keyword(*new)
+            ),
+        }
+    }
+}
+
+/// The kind of an [`Incompatibility`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IncompatibilityKind {
+    /// The message was removed.
+    MessageRemoved,
+    /// The field was removed.
+    FieldRemoved,
+    /// The field's number changed.
+    FieldNumberChanged {
+        /// The number in the old schema.
+        old: i32,
+        /// The number in the new schema.
+        new: i32,
+    },
+    /// The field's type changed.
+    ///
+    /// Types are described by their .proto keyword, like `int32`, or, for
+    /// message and enum fields, by the name of the referenced type.
+    FieldTypeChanged {
+        /// The type in the old schema.
+        old: String,
+        /// The type in the new schema.
+        new: String,
+    },
+    /// The field's label changed.
+    FieldLabelChanged {
+        /// The label in the old schema.
+        old: FieldLabel,
+        /// The label in the new schema.
+        new: FieldLabel,
+    },
+}
+
+/// Returns every message in `set`, including nested messages, along with its
+/// fully-qualified name.
+fn collect_messages(set: &FileDescriptorSet) -> Vec<(String, &DescriptorProto)> {
+    fn visit<'a>(
+        scope: &str,
+        message: &'a DescriptorProto,
+        out: &mut Vec<(String, &'a DescriptorProto)>,
+    ) {
+        let name = qualify(scope, message.name());
+        out.push((name.clone(), message));
+        for i in 0..message.nested_type_size() {
+            visit(&name, message.nested_type(i), out);
+        }
+    }
+
+    let mut out = vec![];
+    for file in set {
+        let package = String::from_utf8_lossy(file.package());
+        for i in 0..file.message_type_size() {
+            visit(&package, file.message_type(i), &mut out);
+        }
+    }
+    out
+}
+
+fn check_message(
+    name: &str,
+    old: &DescriptorProto,
+    new: &DescriptorProto,
+    incompatibilities: &mut Vec<Incompatibility>,
+) {
+    let new_fields: Vec<_> = (0..new.field_size()).map(|i| new.field(i)).collect();
+    let new_by_name: HashMap<_, _> = new_fields.iter().map(|f| (f.name(), *f)).collect();
+    let new_by_number: HashMap<_, _> = new_fields.iter().map(|f| (f.number(), *f)).collect();
+    for i in 0..old.field_size() {
+        let old_field = old.field(i);
+        let mut push = |kind| {
+            incompatibilities.push(Incompatibility {
+                message: name.into(),
+                field: Some(String::from_utf8_lossy(old_field.name()).into_owned()),
+                kind,
+            })
+        };
+        let new_field = match new_by_name.get(old_field.name()) {
+            Some(new_field) if new_field.number() != old_field.number() => {
+                push(IncompatibilityKind::FieldNumberChanged {
+                    old: old_field.number(),
+                    new: new_field.number(),
+                });
+                continue;
+            }
+            Some(new_field) => new_field,
+            None => match new_by_number.get(&old_field.number()) {
+                Some(new_field) => new_field,
+                None => {
+                    push(IncompatibilityKind::FieldRemoved);
+                    continue;
+                }
+            },
+        };
+        let (old_type, new_type) = (type_description(old_field), type_description(new_field));
+        if old_type != new_type {
+            push(IncompatibilityKind::FieldTypeChanged {
+                old: old_type,
+                new: new_type,
+            });
+        }
+        if old_field.label() != new_field.label() {
+            push(IncompatibilityKind::FieldLabelChanged {
+                old: old_field.label(),
+                new: new_field.label(),
+            });
+        }
+    }
+}
+
+fn qualify(scope: &str, name: &[u8]) -> String {
+    let name = String::from_utf8_lossy(name);
+    if scope.is_empty() {
+        name.into_owned()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+fn type_description(field: &FieldDescriptorProto) -> String {
+    let type_name = field.type_name();
+    if !type_name.is_empty() {
+        let type_name = type_name.strip_prefix(b".").unwrap_or(type_name);
+        return String::from_utf8_lossy(type_name).into_owned();
+    }
+    let keyword = match field.type_() {
+        None => "<unknown>",
+        Some(FieldType::Double) => "double",
+        Some(FieldType::Float) => "float",
+        Some(FieldType::Int64) => "int64",
+        Some(FieldType::Uint64) => "uint64",
+        Some(FieldType::Int32) => "int32",
+        Some(FieldType::Fixed64) => "fixed64",
+        Some(FieldType::Fixed32) => "fixed32",
+        Some(FieldType::Bool) => "bool",
+        Some(FieldType::String) => "string",
+        Some(FieldType::Group) => "group",
+        Some(FieldType::Message) => "message",
+        Some(FieldType::Bytes) => "bytes",
+        Some(FieldType::Uint32) => "uint32",
+        Some(FieldType::Enum) => "enum",
+        Some(FieldType::Sfixed32) => "sfixed32",
+        Some(FieldType::Sfixed64) => "sfixed64",
+        Some(FieldType::Sint32) => "sint32",
+        Some(FieldType::Sint64) => "sint64",
+    };
+    keyword.into()
+}
+
+fn label_keyword(label: FieldLabel) -> &'static str {
+    match label {
+        FieldLabel::Optional => "optional",
+        FieldLabel::Required => "required",
+        FieldLabel::Repeated => "repeated",
+    }
+}
@@ -15,6 +15,20 @@
 
 #include "protobuf-native/src/compiler.h"
 
+#include <google/protobuf/compiler/cpp/cpp_generator.h>
+#include <google/protobuf/compiler/csharp/csharp_generator.h>
+#include <google/protobuf/compiler/java/java_generator.h>
+#include <google/protobuf/compiler/java/java_kotlin_generator.h>
+#include <google/protobuf/compiler/objectivec/objectivec_generator.h>
+#include <google/protobuf/compiler/php/php_generator.h>
+#include <google/protobuf/compiler/python/python_generator.h>
+#include <google/protobuf/compiler/ruby/ruby_generator.h>
+
+#include <algorithm>
+#include <string>
+#include <utility>
+#include <vector>
+
 #include "protobuf-native/src/compiler.rs.h"
 #include "protobuf-native/src/internal.rs.h"
 
@@ -44,10 +58,43 @@ void SimpleErrorCollector::AddErrorOrWarning(const std::string& filename, int li
 
 std::vector<FileLoadError>& SimpleErrorCollector::Errors() { return errors_; }
 
+bool SimpleErrorCollector::HasErrors() const {
+    return std::any_of(errors_.begin(), errors_.end(),
+                       [](const FileLoadError& error) { return !error.warning; });
+}
+
 SimpleErrorCollector* NewSimpleErrorCollector() { return new SimpleErrorCollector(); }
 
 void DeleteSimpleErrorCollector(SimpleErrorCollector* collector) { delete collector; }
 
+FnErrorCollector::FnErrorCollector(rust::Box<ErrorCollectorAdaptor> adaptor)
+    : adaptor_(std::move(adaptor)) {}
+
+void FnErrorCollector::AddError(const std::string& filename, int line, int column,
+                                const std::string& message) {
+    AddErrorOrWarning(filename, line, column, message, false);
+}
+
+void FnErrorCollector::AddWarning(const std::string& filename, int line, int column,
+                                  const std::string& message) {
+    AddErrorOrWarning(filename, line, column, message, true);
+}
+
+void FnErrorCollector::AddErrorOrWarning(const std::string& filename, int line, int column,
+                                         const std::string& message, bool warning) {
+    adaptor_->add_error(FileLoadError{.filename = filename,
+                                      .line = line,
+                                      .column = column,
+                                      .message = message,
+                                      .warning = warning});
+}
+
+FnErrorCollector* NewFnErrorCollector(rust::Box<ErrorCollectorAdaptor> adaptor) {
+    return new FnErrorCollector(std::move(adaptor));
+}
+
+void DeleteFnErrorCollector(FnErrorCollector* collector) { delete collector; }
+
 rust::String SourceTreeGetLastErrorMessage(SourceTree& source_tree) {
     return rust::String::lossy(source_tree.GetLastErrorMessage());
 }
@@ -57,7 +104,23 @@ VirtualSourceTree* NewVirtualSourceTree() { return new VirtualSourceTree(); }
 void DeleteVirtualSourceTree(VirtualSourceTree* tree) { delete tree; }
 
 void VirtualSourceTree::AddFile(const std::string& name, rust::Vec<rust::u8> contents) {
-    files_[name] = contents;
+    files_[name] = std::move(contents);
+}
+
+bool VirtualSourceTree::RemoveFile(const std::string& name) { return files_.erase(name) > 0; }
+
+bool VirtualSourceTree::Contains(const std::string& name) const {
+    return files_.find(name) != files_.end();
+}
+
+std::unique_ptr<std::vector<std::string>> VirtualSourceTree::FileNames() const {
+    std::unique_ptr<std::vector<std::string>> output(new std::vector<std::string>());
+    output->reserve(files_.size());
+    for (const auto& entry : files_) {
+        output->push_back(entry.first);
+    }
+    std::sort(output->begin(), output->end());
+    return output;
 }
 
 io::ZeroCopyInputStream* VirtualSourceTree::Open(const std::string& filename) {
@@ -71,9 +134,105 @@ io::ZeroCopyInputStream* VirtualSourceTree::Open(const std::string& filename) {
 
 std::string VirtualSourceTree::GetLastErrorMessage() { return "File not found."; }
 
+CustomSourceTree::CustomSourceTree(rust::Box<SourceTreeAdaptor> adaptor)
+    : adaptor_(std::move(adaptor)) {}
+
+io::ZeroCopyInputStream* CustomSourceTree::Open(const std::string& filename) {
+    return adaptor_->open(rust::Slice<const uint8_t>(
+        reinterpret_cast<const uint8_t*>(filename.data()), filename.size()));
+}
+
+std::string CustomSourceTree::GetLastErrorMessage() {
+    return std::string(adaptor_->last_error_message());
+}
+
+CustomSourceTree* NewCustomSourceTree(rust::Box<SourceTreeAdaptor> adaptor) {
+    return new CustomSourceTree(std::move(adaptor));
+}
+
+void DeleteCustomSourceTree(CustomSourceTree* tree) { delete tree; }
+
+void DiskSourceTree::AddEmbeddedFile(const std::string& filename,
+                                     rust::Slice<const rust::u8> contents) {
+    embedded_files_.emplace(filename, contents);
+}
+
+io::ZeroCopyInputStream* DiskSourceTree::Open(const std::string& filename) {
+    io::ZeroCopyInputStream* stream = google::protobuf::compiler::DiskSourceTree::Open(filename);
+    if (stream != nullptr) {
+        return stream;
+    }
+    auto entry = embedded_files_.find(filename);
+    if (entry == embedded_files_.end()) {
+        return nullptr;
+    }
+    auto& file = entry->second;
+    return new io::ArrayInputStream(file.data(), file.size());
+}
+
 DiskSourceTree* NewDiskSourceTree() { return new DiskSourceTree(); }
 void DeleteDiskSourceTree(DiskSourceTree* tree) { delete tree; }
 
+bool DiskSourceTreeVirtualFileToDiskFile(DiskSourceTree& tree, const std::string& virtual_file,
+                                         std::string& disk_file) {
+    return tree.VirtualFileToDiskFile(virtual_file, &disk_file);
+}
+
+bool DiskSourceTreeDiskFileToVirtualFile(DiskSourceTree& tree, const std::string& disk_file,
+                                         std::string& virtual_file) {
+    std::string shadowing_disk_file;
+    return tree.DiskFileToVirtualFile(disk_file, &virtual_file, &shadowing_disk_file) ==
+           DiskSourceTree::SUCCESS;
+}
+
+SourceTreeDescriptorDatabase::SourceTreeDescriptorDatabase(SourceTree* source_tree)
+    : google::protobuf::compiler::SourceTreeDescriptorDatabase(source_tree) {
+    google::protobuf::compiler::SourceTreeDescriptorDatabase::RecordErrorsTo(&error_collector_);
+}
+
+void SourceTreeDescriptorDatabase::RecordErrorsTo(MultiFileErrorCollector* error_collector) {
+    error_collector_.inner_ = error_collector;
+}
+
+void SourceTreeDescriptorDatabase::SetWarningsAsErrors(bool enabled) {
+    warnings_as_errors_ = enabled;
+}
+
+void SourceTreeDescriptorDatabase::AddError(const std::string& filename,
+                                            const std::string& message) {
+    error_collector_.AddError(filename, -1, 0, message);
+}
+
+void SourceTreeDescriptorDatabase::SetRetainSourceCodeInfo(bool enabled) {
+    retain_source_code_info_ = enabled;
+}
+
+bool SourceTreeDescriptorDatabase::FindFileByName(const std::string& filename,
+                                                  FileDescriptorProto* output) {
+    error_collector_.had_warnings_ = false;
+    bool ok =
+        google::protobuf::compiler::SourceTreeDescriptorDatabase::FindFileByName(filename, output);
+    if (ok && !retain_source_code_info_) {
+        output->clear_source_code_info();
+    }
+    return ok && !(warnings_as_errors_ && error_collector_.had_warnings_);
+}
+
+void SourceTreeDescriptorDatabase::WarningTrackingErrorCollector::AddError(
+    const std::string& filename, int line, int column, const std::string& message) {
+    if (inner_ != nullptr) {
+        inner_->AddError(filename, line, column, message);
+    }
+}
+
+void SourceTreeDescriptorDatabase::WarningTrackingErrorCollector::AddWarning(
+    const std::string& filename, int line, int column, const std::string& message) {
+    had_warnings_ = true;
+    if (inner_ != nullptr) {
+        inner_->AddWarning(filename, line, column, message);
+    }
+}
+
 SourceTreeDescriptorDatabase* NewSourceTreeDescriptorDatabase(SourceTree* source_tree) {
     return new SourceTreeDescriptorDatabase(source_tree);
 }
@@ -82,5 +241,74 @@ void DeleteSourceTreeDescriptorDatabase(SourceTreeDescriptorDatabase* source_tre
     delete source_tree;
 }
 
+Importer* NewImporter(SourceTree* source_tree, MultiFileErrorCollector* error_collector) {
+    return new Importer(source_tree, error_collector);
+}
+
+void DeleteImporter(Importer* importer) { delete importer; }
+
+void Parser::SetRetainSourceCodeInfo(bool enabled) { retain_source_code_info_ = enabled; }
+
+bool Parser::Parse(io::Tokenizer& input, FileDescriptorProto* file) {
+    parser_.RecordErrorsTo(input.GetErrorCollector());
+    bool ok = parser_.Parse(&input.Inner(), file);
+    if (!retain_source_code_info_) {
+        file->clear_source_code_info();
+    }
+    return ok;
+}
+
+Parser* NewParser() { return new Parser(); }
+
+void DeleteParser(Parser* parser) { delete parser; }
+
+CommandLineInterface::CommandLineInterface() {
+    AllowPlugins("protoc-");
+    RegisterOwnedGenerator("--cpp_out", "--cpp_opt", new cpp::CppGenerator(),
+                           "Generate C++ header and source.");
+    RegisterOwnedGenerator("--java_out", "--java_opt", new java::JavaGenerator(),
+                           "Generate Java source file.");
+    RegisterOwnedGenerator("--kotlin_out", "--kotlin_opt", new java::KotlinGenerator(),
+                           "Generate Kotlin file.");
+    RegisterOwnedGenerator("--python_out", "--python_opt",
+                           new google::protobuf::compiler::python::Generator(),
+                           "Generate Python source file.");
+    RegisterOwnedGenerator("--php_out", "--php_opt", new php::Generator(),
+                           "Generate PHP source file.");
+    RegisterOwnedGenerator("--ruby_out", "--ruby_opt", new ruby::Generator(),
+                           "Generate Ruby source file.");
+    RegisterOwnedGenerator("--csharp_out", "--csharp_opt", new csharp::Generator(),
+                           "Generate C# source file.");
+    RegisterOwnedGenerator("--objc_out", "--objc_opt", new objectivec::ObjectiveCGenerator(),
+                           "Generate Objective-C header and source.");
+    // The JavaScript generator is omitted, as its installed header depends on
+    // headers that libprotobuf does not install.
+}
+
+void CommandLineInterface::RegisterOwnedGenerator(const std::string& flag_name,
+                                                  const std::string& option_flag_name,
+                                                  CodeGenerator* generator,
+                                                  const std::string& help_text) {
+    generators_.emplace_back(generator);
+    RegisterGenerator(flag_name, option_flag_name, generator, help_text);
+}
+
+CommandLineInterface* NewCommandLineInterface() { return new CommandLineInterface(); }
+
+void DeleteCommandLineInterface(CommandLineInterface* cli) { delete cli; }
+
+internal::CInt CommandLineInterfaceRun(CommandLineInterface& cli,
+                                       const rust::Vec<rust::String>& args) {
+    std::vector<std::string> owned_args{"protoc"};
+    for (const rust::String& arg : args) {
+        owned_args.push_back(std::string(arg));
+    }
+    std::vector<const char*> argv;
+    for (const std::string& arg : owned_args) {
+        argv.push_back(arg.c_str());
+    }
+    return cli.Run(argv.size(), argv.data());
+}
+
 }  // namespace compiler
 }  // namespace protobuf_native
@@ -15,10 +15,19 @@
 
 #pragma once
 
+#include <google/protobuf/compiler/code_generator.h>
+#include <google/protobuf/compiler/command_line_interface.h>
 #include <google/protobuf/compiler/importer.h>
+#include <google/protobuf/compiler/parser.h>
+
+#include <memory>
+#include <vector>
 
 #include "rust/cxx.h"
 
+#include "protobuf-native/src/internal.h"
+#include "protobuf-native/src/io.h"
+
 namespace protobuf_native {
 namespace compiler {
 
@@ -26,6 +35,8 @@ using namespace google::protobuf;
 using namespace google::protobuf::compiler;
 
 struct FileLoadError;
+struct SourceTreeAdaptor;
+struct ErrorCollectorAdaptor;
 
 class SimpleErrorCollector : public MultiFileErrorCollector {
    public:
@@ -34,6 +45,7 @@ class SimpleErrorCollector : public MultiFileErrorCollector {
     void AddWarning(const std::string& filename, int line, int column,
                     const std::string& message) override;
     std::vector<FileLoadError>& Errors();
+    bool HasErrors() const;
 
    private:
     void AddErrorOrWarning(const std::string& filename, int line, int column,
@@ -44,11 +56,31 @@ class SimpleErrorCollector : public MultiFileErrorCollector {
 SimpleErrorCollector* NewSimpleErrorCollector();
 void DeleteSimpleErrorCollector(SimpleErrorCollector*);
 
+class FnErrorCollector : public MultiFileErrorCollector {
+   public:
+    FnErrorCollector(rust::Box<ErrorCollectorAdaptor> adaptor);
+    void AddError(const std::string& filename, int line, int column,
+                  const std::string& message) override;
+    void AddWarning(const std::string& filename, int line, int column,
+                    const std::string& message) override;
+
+   private:
+    void AddErrorOrWarning(const std::string& filename, int line, int column,
+                           const std::string& message, bool warning);
+    rust::Box<ErrorCollectorAdaptor> adaptor_;
+};
+
+FnErrorCollector* NewFnErrorCollector(rust::Box<ErrorCollectorAdaptor> adaptor);
+void DeleteFnErrorCollector(FnErrorCollector*);
+
 rust::String SourceTreeGetLastErrorMessage(SourceTree&);
 
 class VirtualSourceTree : public SourceTree {
    public:
     void AddFile(const std::string& name, rust::Vec<rust::u8> contents);
+    bool RemoveFile(const std::string& name);
+    bool Contains(const std::string& name) const;
+    std::unique_ptr<std::vector<std::string>> FileNames() const;
     io::ZeroCopyInputStream* Open(const std::string& filename);
     std::string GetLastErrorMessage();
 
@@ -60,13 +92,111 @@ VirtualSourceTree* NewVirtualSourceTree();
 
 void DeleteVirtualSourceTree(VirtualSourceTree*);
 
+class CustomSourceTree : public SourceTree {
+   public:
+    CustomSourceTree(rust::Box<SourceTreeAdaptor> adaptor);
+    io::ZeroCopyInputStream* Open(const std::string& filename) override;
+    std::string GetLastErrorMessage() override;
+
+   private:
+    rust::Box<SourceTreeAdaptor> adaptor_;
+};
+
+CustomSourceTree* NewCustomSourceTree(rust::Box<SourceTreeAdaptor> adaptor);
+
+void DeleteCustomSourceTree(CustomSourceTree*);
+
+class DiskSourceTree : public google::protobuf::compiler::DiskSourceTree {
+   public:
+    void AddEmbeddedFile(const std::string& filename, rust::Slice<const rust::u8> contents);
+    io::ZeroCopyInputStream* Open(const std::string& filename) override;
+
+   private:
+    std::unordered_map<std::string, rust::Slice<const rust::u8>> embedded_files_;
+};
+
 DiskSourceTree* NewDiskSourceTree();
 
 void DeleteDiskSourceTree(DiskSourceTree*);
 
+bool DiskSourceTreeVirtualFileToDiskFile(DiskSourceTree& tree, const std::string& virtual_file,
+                                         std::string& disk_file);
+
+bool DiskSourceTreeDiskFileToVirtualFile(DiskSourceTree& tree, const std::string& disk_file,
+                                         std::string& virtual_file);
+
+class SourceTreeDescriptorDatabase
+    : public google::protobuf::compiler::SourceTreeDescriptorDatabase {
+   public:
+    SourceTreeDescriptorDatabase(SourceTree* source_tree);
+    void RecordErrorsTo(MultiFileErrorCollector* error_collector);
+    void SetWarningsAsErrors(bool enabled);
+    void SetRetainSourceCodeInfo(bool enabled);
+    void AddError(const std::string& filename, const std::string& message);
+    bool FindFileByName(const std::string& filename, FileDescriptorProto* output) override;
+
+   private:
+    // Forwards errors and warnings to the user's error collector, if any,
+    // while noting whether any warnings were reported.
+    class WarningTrackingErrorCollector : public MultiFileErrorCollector {
+       public:
+        void AddError(const std::string& filename, int line, int column,
+                      const std::string& message) override;
+        void AddWarning(const std::string& filename, int line, int column,
+                        const std::string& message) override;
+
+        MultiFileErrorCollector* inner_ = nullptr;
+        bool had_warnings_ = false;
+    };
+
+    WarningTrackingErrorCollector error_collector_;
+    bool warnings_as_errors_ = false;
+    bool retain_source_code_info_ = true;
+};
+
 SourceTreeDescriptorDatabase* NewSourceTreeDescriptorDatabase(SourceTree* source_tree);
 
 void DeleteSourceTreeDescriptorDatabase(SourceTreeDescriptorDatabase* source_tree);
 
+Importer* NewImporter(SourceTree* source_tree, MultiFileErrorCollector* error_collector);
+
+void DeleteImporter(Importer* importer);
+
+// Wraps `google::protobuf::compiler::Parser`, optionally discarding the source
+// code info that it always records.
+class Parser {
+   public:
+    void SetRetainSourceCodeInfo(bool enabled);
+    bool Parse(io::Tokenizer& input, FileDescriptorProto* file);
+
+   private:
+    google::protobuf::compiler::Parser parser_;
+    bool retain_source_code_info_ = true;
+};
+
+Parser* NewParser();
+
+void DeleteParser(Parser* parser);
+
+// A command-line interface with the same generators and plugin support as
+// protoc.
+class CommandLineInterface : public google::protobuf::compiler::CommandLineInterface {
+   public:
+    CommandLineInterface();
+
+   private:
+    void RegisterOwnedGenerator(const std::string& flag_name, const std::string& option_flag_name,
+                                CodeGenerator* generator, const std::string& help_text);
+
+    std::vector<std::unique_ptr<CodeGenerator>> generators_;
+};
+
+CommandLineInterface* NewCommandLineInterface();
+
+void DeleteCommandLineInterface(CommandLineInterface* cli);
+
+internal::CInt CommandLineInterfaceRun(CommandLineInterface& cli,
+                                       const rust::Vec<rust::String>& args);
+
 }  // namespace compiler
 }  // namespace protobuf_native
@@ -22,17 +22,26 @@
 use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
+use std::io::{self, Read};
+use std::iter;
 use std::marker::PhantomData;
 use std::marker::PhantomPinned;
 use std::mem;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
 
 use cxx::let_cxx_string;
 
-use crate::internal::{unsafe_ffi_conversions, CInt, ProtobufPath};
-use crate::io::DynZeroCopyInputStream;
-use crate::{DescriptorDatabase, FileDescriptorProto, FileDescriptorSet, OperationFailedError};
+use crate::internal::{
+    unsafe_ffi_conversions, BoolExt, CInt, ErrorCollectorAdaptor, ProtobufPath, SourceTreeAdaptor,
+};
+use crate::io::{DynZeroCopyInputStream, Tokenizer};
+use crate::{
+    DescriptorDatabase, DescriptorPool, FileDescriptor, FileDescriptorProto, FileDescriptorSet,
+    OperationFailedError,
+};
+
+pub mod plugin;
 
 #[cxx::bridge(namespace = "protobuf_native::compiler")]
 pub(crate) mod ffi {
@@ -45,6 +54,15 @@ pub(crate) mod ffi {
         warning: bool,
     }
 
+    extern "Rust" {
+        type SourceTreeAdaptor<'a>;
+        fn open(self: &mut SourceTreeAdaptor<'_>, filename: &[u8]) -> *mut ReaderStream;
+        fn last_error_message(self: &SourceTreeAdaptor<'_>) -> String;
+
+        type ErrorCollectorAdaptor<'a>;
+        fn add_error(self: &mut ErrorCollectorAdaptor<'_>, error: FileLoadError);
+    }
+
     unsafe extern "C++" {
         include!("protobuf-native/src/compiler.h");
         include!("protobuf-native/src/internal.h");
@@ -55,13 +73,27 @@ pub(crate) mod ffi {
         #[namespace = "google::protobuf"]
         type FileDescriptorProto = crate::ffi::FileDescriptorProto;
 
+        #[namespace = "google::protobuf"]
+        type FileDescriptor = crate::ffi::FileDescriptor;
+
+        #[namespace = "google::protobuf"]
+        type DescriptorPool = crate::ffi::DescriptorPool;
+
         #[namespace = "google::protobuf::io"]
         type ZeroCopyInputStream = crate::io::ffi::ZeroCopyInputStream;
 
+        #[namespace = "protobuf_native::io"]
+        type ReaderStream = crate::io::ffi::ReaderStream;
+
         type SimpleErrorCollector;
         fn NewSimpleErrorCollector() -> *mut SimpleErrorCollector;
         unsafe fn DeleteSimpleErrorCollector(collector: *mut SimpleErrorCollector);
         fn Errors(self: Pin<&mut SimpleErrorCollector>) -> Pin<&mut CxxVector<FileLoadError>>;
+        fn HasErrors(self: &SimpleErrorCollector) -> bool;
+
+        type FnErrorCollector;
+        fn NewFnErrorCollector(adaptor: Box<ErrorCollectorAdaptor<'_>>) -> *mut FnErrorCollector;
+        unsafe fn DeleteFnErrorCollector(collector: *mut FnErrorCollector);
 
         #[namespace = "google::protobuf::compiler"]
         type MultiFileErrorCollector;
@@ -85,7 +117,6 @@ pub(crate) mod ffi {
         fn Open(self: Pin<&mut SourceTree>, filename: &CxxString) -> *mut ZeroCopyInputStream;
         fn SourceTreeGetLastErrorMessage(source_tree: Pin<&mut SourceTree>) -> String;
 
-        #[namespace = "google::protobuf::compiler"]
         type SourceTreeDescriptorDatabase;
         unsafe fn NewSourceTreeDescriptorDatabase(
             source_tree: *mut SourceTree,
@@ -102,23 +133,220 @@ pub(crate) mod ffi {
             self: Pin<&mut SourceTreeDescriptorDatabase>,
             error_collector: *mut MultiFileErrorCollector,
         );
+        fn SetWarningsAsErrors(self: Pin<&mut SourceTreeDescriptorDatabase>, enabled: bool);
+        fn SetRetainSourceCodeInfo(self: Pin<&mut SourceTreeDescriptorDatabase>, enabled: bool);
+        fn AddError(
+            self: Pin<&mut SourceTreeDescriptorDatabase>,
+            filename: &CxxString,
+            message: &CxxString,
+        );
+
+        #[namespace = "google::protobuf::compiler"]
+        type Importer;
+        unsafe fn NewImporter(
+            source_tree: *mut SourceTree,
+            error_collector: *mut MultiFileErrorCollector,
+        ) -> *mut Importer;
+        unsafe fn DeleteImporter(importer: *mut Importer);
+        fn Import(self: Pin<&mut Importer>, filename: &CxxString) -> *const FileDescriptor;
+        fn pool(self: &Importer) -> *const DescriptorPool;
+
+        #[namespace = "protobuf_native::io"]
+        type Tokenizer = crate::io::ffi::Tokenizer;
+
+        type Parser;
+        fn NewParser() -> *mut Parser;
+        unsafe fn DeleteParser(parser: *mut Parser);
+        fn SetRetainSourceCodeInfo(self: Pin<&mut Parser>, enabled: bool);
+        unsafe fn Parse(
+            self: Pin<&mut Parser>,
+            input: Pin<&mut Tokenizer>,
+            file: *mut FileDescriptorProto,
+        ) -> bool;
 
         type VirtualSourceTree;
         fn NewVirtualSourceTree() -> *mut VirtualSourceTree;
         unsafe fn DeleteVirtualSourceTree(tree: *mut VirtualSourceTree);
         fn AddFile(self: Pin<&mut VirtualSourceTree>, filename: &CxxString, contents: Vec<u8>);
+        fn RemoveFile(self: Pin<&mut VirtualSourceTree>, filename: &CxxString) -> bool;
+        fn Contains(self: &VirtualSourceTree, filename: &CxxString) -> bool;
+        fn FileNames(self: &VirtualSourceTree) -> UniquePtr<CxxVector<CxxString>>;
+
+        type CustomSourceTree;
+        fn NewCustomSourceTree(adaptor: Box<SourceTreeAdaptor<'_>>) -> *mut CustomSourceTree;
+        unsafe fn DeleteCustomSourceTree(tree: *mut CustomSourceTree);
 
-        #[namespace = "google::protobuf::compiler"]
         type DiskSourceTree;
         fn NewDiskSourceTree() -> *mut DiskSourceTree;
         unsafe fn DeleteDiskSourceTree(tree: *mut DiskSourceTree);
         fn MapPath(self: Pin<&mut DiskSourceTree>, virtual_path: &CxxString, disk_path: &CxxString);
+        fn AddEmbeddedFile(
+            self: Pin<&mut DiskSourceTree>,
+            filename: &CxxString,
+            contents: &'static [u8],
+        );
+        fn DiskSourceTreeVirtualFileToDiskFile(
+            tree: Pin<&mut DiskSourceTree>,
+            virtual_file: &CxxString,
+            disk_file: Pin<&mut CxxString>,
+        ) -> bool;
+        fn DiskSourceTreeDiskFileToVirtualFile(
+            tree: Pin<&mut DiskSourceTree>,
+            disk_file: &CxxString,
+            virtual_file: Pin<&mut CxxString>,
+        ) -> bool;
+
+        type CommandLineInterface;
+        fn NewCommandLineInterface() -> *mut CommandLineInterface;
+        unsafe fn DeleteCommandLineInterface(cli: *mut CommandLineInterface);
+        fn CommandLineInterfaceRun(cli: Pin<&mut CommandLineInterface>, args: &Vec<String>)
+            -> CInt;
+    }
+}
+
+/// The .proto files for protobuf's well-known types, keyed by their path
+/// relative to protobuf's include directory.
+const WELL_KNOWN_TYPES: &[(&str, &[u8])] =
+    &include!(concat!(env!("OUT_DIR"), "/well_known_types.rs"));
+
+/// Returns the source of the .proto file for one of protobuf's well-known
+/// types.
+///
+/// The `name` is the path to the file relative to protobuf's include
+/// directory, as it would appear in an import statement, e.g.
+/// `google/protobuf/timestamp.proto`. Returns `None` if `name` does not refer
+/// to a well-known type.
+///
+/// The sources are embedded from the version of protobuf that this library
+/// is built against.
+///
+/// ```
+/// use protobuf_native::compiler::well_known_type_source;
+///
+/// let source = well_known_type_source("google/protobuf/duration.proto").unwrap();
+/// assert!(source.starts_with(b"// Protocol Buffers"));
+/// assert!(well_known_type_source("google/protobuf/nope.proto").is_none());
+/// ```
+pub fn well_known_type_source(name: &str) -> Option<&'static [u8]> {
+    WELL_KNOWN_TYPES
+        .iter()
+        .find(|(filename, _)| *filename == name)
+        .map(|(_, contents)| *contents)
+}
+
+/// Parses the given .proto files and their dependencies into a file descriptor
+/// set, like `protoc --include_imports --descriptor_set_out`.
+///
+/// Imports are resolved against the `includes` directories, in order, as with
+/// protoc's `--proto_path` flag. If `includes` is empty, the current
+/// directory is used. The .proto files for protobuf's well-known types are
+/// always available; see [`DiskSourceTree::map_well_known_types`].
+///
+/// Each input may be either a path on disk within one of the include
+/// directories or a path relative to the include directories. The files in the
+/// returned set are ordered as described in
+/// [`SourceTreeDescriptorDatabase::build_file_descriptor_set`].
+///
+/// If any file fails to load, returns all errors and warnings that were
+/// reported while loading the files, in the order in which they were reported.
+pub fn compile<I, P>(
+    includes: &[I],
+    inputs: &[P],
+) -> Result<Pin<Box<FileDescriptorSet>>, Vec<FileLoadError>>
+where
+    I: AsRef<Path>,
+    P: AsRef<Path>,
+{
+    let mut source_tree = DiskSourceTree::new();
+    if includes.is_empty() {
+        source_tree.as_mut().map_path(Path::new(""), Path::new("."));
+    }
+    for include in includes {
+        source_tree
+            .as_mut()
+            .map_path(Path::new(""), include.as_ref());
+    }
+    source_tree.as_mut().map_well_known_types();
+    let inputs: Vec<_> = inputs
+        .iter()
+        .map(|input| {
+            let input = input.as_ref();
+            source_tree
+                .as_mut()
+                .disk_file_to_virtual_file(input)
+                .unwrap_or_else(|| input.to_path_buf())
+        })
+        .collect();
+
+    let mut error_collector = SimpleErrorCollector::new();
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    db.as_mut().record_errors_to(error_collector.as_mut());
+    let res = db.as_mut().build_file_descriptor_set(&inputs);
+    drop(db);
+    res.map_err(|_| drain_errors(error_collector.as_mut()))
+}
+
+/// Parses a single .proto file from memory into a file descriptor proto.
+///
+/// The file is parsed under the name `filename`, which is reported in any
+/// errors. The file may import protobuf's well-known types, but importing any
+/// other file is an error. Note that the returned proto is not validated beyond
+/// parsing; for example, references to undefined types are not reported. Build
+/// it in a [`DescriptorPool`] to validate it.
+///
+/// If the file fails to parse, returns all errors and warnings that were
+/// reported while parsing it, in the order in which they were reported.
+///
+/// ```
+/// use protobuf_native::compiler;
+///
+/// let file = compiler::parse_file("foo.proto", b"syntax = \"proto3\"; message Foo {}")
+///     .unwrap();
+/// assert_eq!(file.message_type(0).name(), b"Foo");
+///
+/// let errors = compiler::parse_file("bar.proto", b"message Bar {").unwrap_err();
+/// assert!(!errors.is_empty());
+/// ```
+pub fn parse_file(
+    filename: &str,
+    contents: &[u8],
+) -> Result<Pin<Box<FileDescriptorProto>>, Vec<FileLoadError>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().map_well_known_types();
+    source_tree
+        .as_mut()
+        .add_file(Path::new(filename), contents.to_vec());
+
+    let mut error_collector = SimpleErrorCollector::new();
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    db.as_mut().record_errors_to(error_collector.as_mut());
+    // Building a file descriptor set, rather than only looking up the file,
+    // verifies that the file's imports can be loaded.
+    let res = db.as_mut().build_file_descriptor_set(&[filename]);
+    drop(db);
+    match res {
+        // The file itself follows its dependencies.
+        Ok(fds) => Ok(fds.file(fds.file_size() - 1).clone()),
+        Err(_) => Err(drain_errors(error_collector.as_mut())),
     }
 }
 
+/// Removes all errors and warnings from the collector, in the order in which
+/// they were reported.
+fn drain_errors(error_collector: Pin<&mut SimpleErrorCollector>) -> Vec<FileLoadError> {
+    // The collector yields errors in the reverse of the order in which they
+    // were reported.
+    let mut errors: Vec<_> = error_collector.collect();
+    errors.reverse();
+    errors
+}
+
 /// If the importer encounters problems while trying to import the proto files,
 /// it reports them to a `MultiFileErrorCollector`.
-pub trait MultiFileErrorCollector: multi_file_error_collector::Sealed {
+///
+/// Error collectors are `Send`, as they may be sent to another thread along
+/// with the database or importer that reports errors to them.
+pub trait MultiFileErrorCollector: multi_file_error_collector::Sealed + Send {
     /// Adds an error message to the error collector at the specified position.
     ///
     /// Line and column numbers are zero-based. A line number of -1 indicates
@@ -185,6 +413,31 @@ impl SimpleErrorCollector {
         unsafe { Self::from_ffi_owned(collector) }
     }
 
+    /// Reports whether the collector holds any problems with
+    /// [`Severity::Error`].
+    ///
+    /// Unlike iterating over the collector, this does not remove any problems
+    /// from the collector.
+    pub fn has_errors(&self) -> bool {
+        self.as_ffi().HasErrors()
+    }
+
+    /// Returns an iterator over the problems with [`Severity::Error`].
+    ///
+    /// Like iterating over the collector directly, this removes problems from
+    /// the collector as they are yielded. Any warnings that are encountered
+    /// along the way are discarded.
+    pub fn errors_only(self: Pin<&mut Self>) -> impl Iterator<Item = FileLoadError> + '_ {
+        self.filter(|e| e.severity == Severity::Error)
+    }
+
+    /// Returns an iterator over the problems with [`Severity::Warning`].
+    ///
+    /// See [`SimpleErrorCollector::errors_only`] for details.
+    pub fn warnings_only(self: Pin<&mut Self>) -> impl Iterator<Item = FileLoadError> + '_ {
+        self.filter(|e| e.severity == Severity::Warning)
+    }
+
     unsafe_ffi_conversions!(ffi::SimpleErrorCollector);
 }
 
@@ -208,11 +461,78 @@ impl multi_file_error_collector::Sealed for SimpleErrorCollector {
     }
 }
 
+/// An implementation of [`MultiFileErrorCollector`] that reports each error to
+/// a Rust closure as it occurs.
+///
+/// This is useful for streaming errors to a logger rather than buffering them
+/// in memory as [`SimpleErrorCollector`] does.
+pub struct FnErrorCollector<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> Drop for FnErrorCollector<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteFnErrorCollector(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl<'a> FnErrorCollector<'a> {
+    /// Creates a new error collector that calls `f` with each error and
+    /// warning.
+    ///
+    /// The closure must be `Send`, as the error collector may be sent to
+    /// another thread along with the database or importer that reports errors
+    /// to it.
+    pub fn new<F>(f: F) -> Pin<Box<FnErrorCollector<'a>>>
+    where
+        F: FnMut(&FileLoadError) + Send + 'a,
+    {
+        let collector = ffi::NewFnErrorCollector(Box::new(ErrorCollectorAdaptor(Box::new(f))));
+        unsafe { Self::from_ffi_owned(collector) }
+    }
+
+    unsafe_ffi_conversions!(ffi::FnErrorCollector);
+}
+
+impl<'a> MultiFileErrorCollector for FnErrorCollector<'a> {}
+
+impl<'a> multi_file_error_collector::Sealed for FnErrorCollector<'a> {
+    fn upcast(&self) -> &ffi::MultiFileErrorCollector {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MultiFileErrorCollector> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
 /// An implementation of `DescriptorDatabase` which loads files from a
 /// `SourceTree` and parses them.
 ///
 /// Note: This class does not implement `FindFileContainingSymbol` or
 /// `FindFileContainingExtension`; these will always return false.
+///
+/// # Lifetimes
+///
+/// The underlying C++ object holds pointers to the source tree and to any
+/// error collector passed to [`record_errors_to`], so both remain mutably
+/// borrowed for the lifetime `'a` of the database. Dropping the source tree
+/// while the database is alive is a compile error:
+///
+/// ```compile_fail,E0505
+/// use std::path::Path;
+///
+/// use protobuf_native::compiler::{SourceTreeDescriptorDatabase, VirtualSourceTree};
+/// use protobuf_native::DescriptorDatabase;
+///
+/// let mut source_tree = VirtualSourceTree::new();
+/// let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+/// drop(source_tree);
+/// let _ = db.as_mut().find_file_by_name(Path::new("foo.proto"));
+/// ```
+///
+/// [`record_errors_to`]: SourceTreeDescriptorDatabase::record_errors_to
 pub struct SourceTreeDescriptorDatabase<'a> {
     _opaque: PhantomPinned,
     _lifetime: PhantomData<&'a ()>,
@@ -247,8 +567,42 @@ impl<'a> SourceTreeDescriptorDatabase<'a> {
         }
     }
 
+    /// Configures whether warnings are treated as errors.
+    ///
+    /// When enabled, [`find_file_by_name`] returns an error if parsing the
+    /// file produced any warnings, such as style warnings about the names of
+    /// messages. The warnings are still reported as warnings to the error
+    /// collector configured with
+    /// [`record_errors_to`](SourceTreeDescriptorDatabase::record_errors_to).
+    ///
+    /// [`find_file_by_name`]: DescriptorDatabase::find_file_by_name
+    pub fn set_warnings_as_errors(self: Pin<&mut Self>, enabled: bool) {
+        self.as_ffi_mut().SetWarningsAsErrors(enabled)
+    }
+
+    /// Configures whether parsed files retain their source code info.
+    ///
+    /// The source code info, available via
+    /// [`FileDescriptorProto::source_code_info`], records the location of each
+    /// element in the .proto file along with any comments attached to it. It
+    /// is retained by default. Disabling it produces smaller file descriptor
+    /// protos, like protoc does when `--include_source_info` is not specified.
+    pub fn set_retain_source_code_info(self: Pin<&mut Self>, enabled: bool) {
+        self.as_ffi_mut().SetRetainSourceCodeInfo(enabled)
+    }
+
     /// Builds a file descriptor set containing all file descriptor protos
     /// reachable from the specified roots.
+    ///
+    /// The files are topologically sorted, so that every file appears after
+    /// all of its dependencies, as protoc does. The order is deterministic:
+    /// dependencies are visited in the order in which they are imported, and
+    /// roots are visited in the order in which they are specified.
+    ///
+    /// Returns an error if any of the files cannot be loaded or if the files
+    /// contain an import cycle. Import cycles are reported to the error
+    /// collector configured with
+    /// [`record_errors_to`](SourceTreeDescriptorDatabase::record_errors_to).
     pub fn build_file_descriptor_set<P>(
         mut self: Pin<&mut Self>,
         roots: &[P],
@@ -258,28 +612,62 @@ impl<'a> SourceTreeDescriptorDatabase<'a> {
     {
         let mut out = FileDescriptorSet::new();
         let mut seen = HashSet::new();
-        let mut stack = vec![];
+        let mut importers = vec![];
         for root in roots {
-            let root = root.as_ref();
-            stack.push(self.as_mut().find_file_by_name(root)?);
-            seen.insert(ProtobufPath::from(root).as_ref().to_vec());
-        }
-        while let Some(file) = stack.pop() {
-            out.as_mut().add_file().copy_from(&file);
-            for i in 0..file.dependency_size() {
-                let dep_path = ProtobufPath::from(file.dependency(i));
-                if !seen.contains(dep_path.as_ref()) {
-                    let dep = self
-                        .as_mut()
-                        .find_file_by_name(dep_path.as_path().as_ref())?;
-                    stack.push(dep);
-                    seen.insert(dep_path.as_ref().to_vec());
-                }
-            }
+            let root = ProtobufPath::from(root.as_ref());
+            self.as_mut().add_file_and_dependencies(
+                root.as_ref(),
+                out.as_mut(),
+                &mut seen,
+                &mut importers,
+            )?;
         }
         Ok(out)
     }
 
+    /// Adds the named file to `out` after its transitive dependencies,
+    /// skipping any files in `seen`.
+    ///
+    /// `importers` is the chain of files whose imports led to this file, which
+    /// is used to detect import cycles.
+    fn add_file_and_dependencies(
+        mut self: Pin<&mut Self>,
+        name: &[u8],
+        mut out: Pin<&mut FileDescriptorSet>,
+        seen: &mut HashSet<Vec<u8>>,
+        importers: &mut Vec<Vec<u8>>,
+    ) -> Result<(), OperationFailedError> {
+        if let Some(i) = importers.iter().position(|importer| importer == name) {
+            let cycle = importers[i..]
+                .iter()
+                .map(|importer| String::from_utf8_lossy(importer))
+                .chain(iter::once(String::from_utf8_lossy(name)))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let_cxx_string!(filename = &importers[importers.len() - 1]);
+            let_cxx_string!(message = format!("File recursively imports itself: {}", cycle));
+            self.as_ffi_mut().AddError(&filename, &message);
+            return Err(OperationFailedError::new());
+        }
+        if !seen.insert(name.to_vec()) {
+            return Ok(());
+        }
+        let path = ProtobufPath::from(name);
+        let file = self.as_mut().find_file_by_name(path.as_path().as_ref())?;
+        importers.push(name.to_vec());
+        for i in 0..file.dependency_size() {
+            self.as_mut().add_file_and_dependencies(
+                file.dependency(i),
+                out.as_mut(),
+                seen,
+                importers,
+            )?;
+        }
+        importers.pop();
+        out.add_file().copy_from(&file);
+        Ok(())
+    }
+
     unsafe_ffi_conversions!(ffi::SourceTreeDescriptorDatabase);
 }
 
@@ -296,11 +684,160 @@ impl<'a> DescriptorDatabase for SourceTreeDescriptorDatabase<'a> {
         } {
             Ok(fd)
         } else {
-            Err(OperationFailedError)
+            Err(OperationFailedError::new())
         }
     }
 }
 
+/// Parses .proto files and builds them into [`FileDescriptor`]s.
+///
+/// Unlike [`SourceTreeDescriptorDatabase`], which produces unlinked
+/// [`FileDescriptorProto`]s, an importer resolves all cross-references between
+/// files and returns fully built descriptors suitable for reflection.
+///
+/// Files are built into a [`DescriptorPool`] owned by the importer. Imported
+/// files and their dependencies are cached in the pool, so importing several
+/// files that share dependencies only parses each dependency once.
+///
+/// # Thread safety
+///
+/// The importer's pool loads files from the source tree lazily, on whichever
+/// thread performs a lookup, so the source tree and error collector must be
+/// `Send`. A source tree that captures state which is not `Send` is rejected
+/// at compile time:
+///
+/// ```compile_fail,E0277
+/// use std::io::{self, Read};
+/// use std::path::Path;
+/// use std::rc::Rc;
+///
+/// use protobuf_native::compiler::{CustomSourceTree, Importer, SimpleErrorCollector};
+///
+/// let contents = Rc::new(b"syntax = \"proto3\";".to_vec());
+/// let mut source_tree = CustomSourceTree::new(move |_: &Path| {
+///     Ok(Box::new(io::Cursor::new(contents.to_vec())) as Box<dyn Read>)
+/// });
+/// let mut error_collector = SimpleErrorCollector::new();
+/// let _ = Importer::new(source_tree.as_mut(), error_collector.as_mut());
+/// ```
+pub struct Importer<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> Drop for Importer<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteImporter(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl<'a> Importer<'a> {
+    /// Constructs a new importer that loads files from the provided source
+    /// tree and reports any errors to the provided error collector.
+    pub fn new(
+        source_tree: Pin<&'a mut dyn SourceTree>,
+        error_collector: Pin<&'a mut dyn MultiFileErrorCollector>,
+    ) -> Pin<Box<Importer<'a>>> {
+        let importer = unsafe {
+            ffi::NewImporter(
+                source_tree.upcast_mut_ptr(),
+                error_collector.upcast_mut_ptr(),
+            )
+        };
+        unsafe { Self::from_ffi_owned(importer) }
+    }
+
+    /// Imports the given file and builds it, along with all of its
+    /// dependencies, into the importer's descriptor pool.
+    ///
+    /// If the file has already been imported, the existing descriptor is
+    /// returned. If the file cannot be parsed or built, the errors are reported
+    /// to the importer's error collector and an error is returned.
+    pub fn import(
+        self: Pin<&mut Self>,
+        filename: &Path,
+    ) -> Result<&FileDescriptor, OperationFailedError> {
+        let_cxx_string!(filename = ProtobufPath::from(filename));
+        let file = self.as_ffi_mut().Import(&filename);
+        (!file.is_null())
+            .then(|| unsafe { FileDescriptor::from_ffi_ptr(file) })
+            .ok_or_else(OperationFailedError::new)
+    }
+
+    /// Returns the descriptor pool into which files are imported.
+    pub fn pool(&self) -> &DescriptorPool<'a> {
+        unsafe { DescriptorPool::from_ffi_ptr(self.as_ffi().pool()) }
+    }
+
+    unsafe_ffi_conversions!(ffi::Importer);
+}
+
+/// Parses a single .proto file into a [`FileDescriptorProto`].
+///
+/// The parser operates on the tokens produced by a [`Tokenizer`] and does not
+/// resolve imports or cross-references; see [`Importer`] for that. Errors are
+/// reported to the error collector with which the tokenizer was constructed.
+///
+/// # Examples
+///
+/// ```
+/// use protobuf_native::compiler::{Parser, SimpleErrorCollector};
+/// use protobuf_native::io::{SliceInputStream, Tokenizer};
+///
+/// let mut input = SliceInputStream::new(b"syntax = \"proto3\"; message Foo {}");
+/// let mut errors = SimpleErrorCollector::new();
+/// let mut tokenizer = Tokenizer::new(input.as_mut(), "foo.proto", errors.as_mut());
+/// let file = Parser::new().as_mut().parse(tokenizer.as_mut())?;
+/// assert_eq!(file.message_type(0).name(), b"Foo");
+/// # Ok::<(), protobuf_native::OperationFailedError>(())
+/// ```
+pub struct Parser {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for Parser {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteParser(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl Parser {
+    /// Creates a new parser.
+    pub fn new() -> Pin<Box<Parser>> {
+        let parser = ffi::NewParser();
+        unsafe { Self::from_ffi_owned(parser) }
+    }
+
+    /// Sets whether to retain the source code info, which records the location
+    /// of each element in the source file, in parsed files.
+    ///
+    /// Defaults to true.
+    pub fn set_retain_source_code_info(self: Pin<&mut Self>, enabled: bool) {
+        self.as_ffi_mut().SetRetainSourceCodeInfo(enabled)
+    }
+
+    /// Parses the tokens produced by `input` into a new file descriptor proto.
+    ///
+    /// Parsing begins at the tokenizer's current token, or at the first token
+    /// if [`Tokenizer::next`] has not yet been called, and continues to the end
+    /// of the input. The parser does not set the name of the returned file.
+    ///
+    /// If any errors are reported, returns an error.
+    pub fn parse(
+        self: Pin<&mut Self>,
+        input: Pin<&mut Tokenizer>,
+    ) -> Result<Pin<Box<FileDescriptorProto>>, OperationFailedError> {
+        let mut fd = FileDescriptorProto::new();
+        let ok = unsafe {
+            self.as_ffi_mut()
+                .Parse(input.as_ffi_mut(), fd.as_mut().as_ffi_mut_ptr())
+        };
+        ok.as_result().map(|()| fd)
+    }
+
+    unsafe_ffi_conversions!(ffi::Parser);
+}
+
 /// Abstract interface which represents a directory tree containing .proto
 /// files.
 ///
@@ -308,8 +845,11 @@ impl<'a> DescriptorDatabase for SourceTreeDescriptorDatabase<'a> {
 /// statements. Most users will probably want to use the `DiskSourceTree`
 /// implementation.
 ///
+/// Source trees are `Send`, as they may be sent to another thread along with
+/// the database or importer that loads files from them.
+///
 /// This trait is sealed and cannot be implemented outside of this crate.
-pub trait SourceTree: source_tree::Sealed {
+pub trait SourceTree: source_tree::Sealed + Send {
     /// Opens the given file and return a stream that reads it.
     ///
     /// The filename must be a path relative to the root of the source tree and
@@ -364,11 +904,57 @@ impl VirtualSourceTree {
     }
 
     /// Adds a file to the source tree with the specified name and contents.
+    ///
+    /// If the source tree already contains a file with the same name, its
+    /// contents are replaced.
+    ///
+    /// The source tree takes ownership of `contents` without copying them. The
+    /// tree cannot borrow contents instead, as files may be opened at any
+    /// point during its lifetime, so files in a borrowed buffer must be copied
+    /// into a `Vec` with `to_vec`.
     pub fn add_file(self: Pin<&mut Self>, filename: &Path, contents: Vec<u8>) {
         let_cxx_string!(filename = ProtobufPath::from(filename));
         self.as_ffi_mut().AddFile(&filename, contents)
     }
 
+    /// Removes the file with the specified name from the source tree.
+    ///
+    /// Returns whether the source tree contained the file.
+    pub fn remove_file(self: Pin<&mut Self>, filename: &Path) -> bool {
+        let_cxx_string!(filename = ProtobufPath::from(filename));
+        self.as_ffi_mut().RemoveFile(&filename)
+    }
+
+    /// Reports whether the source tree contains a file with the specified
+    /// name.
+    pub fn contains(&self, filename: &Path) -> bool {
+        let_cxx_string!(filename = ProtobufPath::from(filename));
+        self.as_ffi().Contains(&filename)
+    }
+
+    /// Returns the names of the files in the source tree, in sorted order.
+    pub fn file_names(&self) -> Vec<PathBuf> {
+        self.as_ffi()
+            .FileNames()
+            .iter()
+            .map(|name| {
+                ProtobufPath::from(name.as_bytes())
+                    .as_path()
+                    .as_ref()
+                    .to_path_buf()
+            })
+            .collect()
+    }
+
+    /// Adds the .proto files for protobuf's well-known types, like
+    /// `google/protobuf/timestamp.proto`, to the source tree.
+    pub fn map_well_known_types(mut self: Pin<&mut Self>) {
+        for (filename, contents) in WELL_KNOWN_TYPES {
+            self.as_mut()
+                .add_file(Path::new(filename), contents.to_vec());
+        }
+    }
+
     unsafe_ffi_conversions!(ffi::VirtualSourceTree);
 }
 
@@ -384,6 +970,78 @@ impl source_tree::Sealed for VirtualSourceTree {
     }
 }
 
+/// An implementation of `SourceTree` which opens files by calling a Rust
+/// closure.
+///
+/// This allows .proto files to be loaded from arbitrary locations, like an
+/// embedded asset bundle or a remote object store, without first writing them
+/// to disk.
+///
+/// ```
+/// use std::io::{self, Cursor, Read};
+/// use std::path::Path;
+/// use protobuf_native::compiler::{CustomSourceTree, SourceTree};
+///
+/// let mut source_tree = CustomSourceTree::new(|filename| {
+///     if filename == Path::new("test.proto") {
+///         let reader: Box<dyn Read> = Box::new(Cursor::new(b"syntax = \"proto3\";"));
+///         Ok(reader)
+///     } else {
+///         Err(io::Error::new(io::ErrorKind::NotFound, "file not found"))
+///     }
+/// });
+/// assert!(source_tree.as_mut().open(Path::new("test.proto")).is_ok());
+/// assert!(source_tree.as_mut().open(Path::new("missing.proto")).is_err());
+/// ```
+pub struct CustomSourceTree<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> Drop for CustomSourceTree<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteCustomSourceTree(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl<'a> CustomSourceTree<'a> {
+    /// Creates a new custom source tree that opens files with the provided
+    /// closure.
+    ///
+    /// The closure is called with the path of the file to open, relative to
+    /// the root of the source tree. If the closure returns an error, the
+    /// error's message is reported as the reason the file could not be opened.
+    ///
+    /// The closure must be `Send`, as the source tree may be sent to another
+    /// thread, e.g., as part of a [`DescriptorPool`] that loads files lazily.
+    /// The returned reader is only used for the duration of the call that
+    /// opened it, and so need not be `Send`.
+    pub fn new<F>(open: F) -> Pin<Box<CustomSourceTree<'a>>>
+    where
+        F: FnMut(&Path) -> io::Result<Box<dyn Read>> + Send + 'a,
+    {
+        let tree = ffi::NewCustomSourceTree(Box::new(SourceTreeAdaptor {
+            opener: Box::new(open),
+            last_error_message: String::new(),
+        }));
+        unsafe { Self::from_ffi_owned(tree) }
+    }
+
+    unsafe_ffi_conversions!(ffi::CustomSourceTree);
+}
+
+impl<'a> SourceTree for CustomSourceTree<'a> {}
+
+impl<'a> source_tree::Sealed for CustomSourceTree<'a> {
+    fn upcast(&self) -> &ffi::SourceTree {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::SourceTree> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
 /// An implementation of `SourceTree` which loads files from locations on disk.
 ///
 /// Multiple mappings can be set up to map locations in the `DiskSourceTree` to
@@ -447,6 +1105,65 @@ impl DiskSourceTree {
         self.as_ffi_mut().MapPath(&virtual_path, &disk_path)
     }
 
+    /// Makes the .proto files for protobuf's well-known types, like
+    /// `google/protobuf/timestamp.proto`, available in this source tree.
+    ///
+    /// The files are embedded in this library, so they can be imported without
+    /// mapping protobuf's include directory. They are only consulted when none
+    /// of the mappings added with [`DiskSourceTree::map_path`] provide a file
+    /// with the same name.
+    pub fn map_well_known_types(mut self: Pin<&mut Self>) {
+        for (filename, contents) in WELL_KNOWN_TYPES {
+            let_cxx_string!(filename = filename);
+            self.as_mut()
+                .as_ffi_mut()
+                .AddEmbeddedFile(&filename, contents);
+        }
+    }
+
+    /// Returns the path on disk to which the given virtual path maps.
+    ///
+    /// Returns `None` if the virtual path does not map to a file that can be
+    /// opened.
+    pub fn virtual_file_to_disk_file(self: Pin<&mut Self>, virtual_path: &Path) -> Option<PathBuf> {
+        let_cxx_string!(virtual_path = ProtobufPath::from(virtual_path));
+        let_cxx_string!(disk_path = "");
+        ffi::DiskSourceTreeVirtualFileToDiskFile(
+            self.as_ffi_mut(),
+            &virtual_path,
+            disk_path.as_mut(),
+        )
+        .then(|| {
+            ProtobufPath::from(disk_path.as_bytes())
+                .as_path()
+                .as_ref()
+                .to_path_buf()
+        })
+    }
+
+    /// Returns the virtual path to which the given path on disk maps.
+    ///
+    /// This is the inverse of [`DiskSourceTree::virtual_file_to_disk_file`].
+    /// Returns `None` if no mapping applies to the path, if the file cannot be
+    /// opened, or if the resulting virtual path is shadowed by a different file
+    /// mapped earlier, in which case opening the virtual path would not open
+    /// `disk_path`.
+    pub fn disk_file_to_virtual_file(self: Pin<&mut Self>, disk_path: &Path) -> Option<PathBuf> {
+        let_cxx_string!(disk_path = ProtobufPath::from(disk_path));
+        let_cxx_string!(virtual_path = "");
+        ffi::DiskSourceTreeDiskFileToVirtualFile(
+            self.as_ffi_mut(),
+            &disk_path,
+            virtual_path.as_mut(),
+        )
+        .then(|| {
+            ProtobufPath::from(virtual_path.as_bytes())
+                .as_path()
+                .as_ref()
+                .to_path_buf()
+        })
+    }
+
     unsafe_ffi_conversions!(ffi::DiskSourceTree);
 }
 
@@ -545,3 +1262,175 @@ impl fmt::Display for FileLoadError {
         write!(f, " {}: {}", self.severity, self.message)
     }
 }
+
+#[cfg(feature = "miette")]
+impl FileLoadError {
+    /// Attaches the contents of the file that failed to load to this error,
+    /// producing a [`miette::Diagnostic`] that can render the error alongside
+    /// an annotated excerpt of the source.
+    ///
+    /// The `source` should be the same bytes that were loaded from the
+    /// [`SourceTree`] under [`FileLoadError::filename`].
+    pub fn with_source<S>(self, source: S) -> FileLoadDiagnostic
+    where
+        S: Into<Vec<u8>>,
+    {
+        let source = source.into();
+        let span = self
+            .location
+            .as_ref()
+            .map(|location| location.span(&source));
+        FileLoadDiagnostic {
+            source: miette::NamedSource::new(
+                &self.filename,
+                std::borrow::Cow::<'static, [u8]>::Owned(source),
+            ),
+            span,
+            error: self,
+        }
+    }
+}
+
+#[cfg(feature = "miette")]
+impl Location {
+    /// Computes the byte span in `source` to which this location refers.
+    ///
+    /// libprotobuf's tokenizer advances the column to the next multiple of
+    /// eight when it encounters a tab, so tabs are accounted for here in the
+    /// same way.
+    fn span(&self, source: &[u8]) -> miette::SourceSpan {
+        let mut offset = 0;
+        let mut line = 1;
+        while line < self.line && offset < source.len() {
+            if source[offset] == b'\n' {
+                line += 1;
+            }
+            offset += 1;
+        }
+        let mut column = 1;
+        while column < self.column && offset < source.len() && source[offset] != b'\n' {
+            if source[offset] == b'\t' {
+                column += 8 - (column - 1) % 8;
+            } else {
+                column += 1;
+            }
+            offset += 1;
+        }
+        let len = match source.get(offset) {
+            None | Some(b'\n') => 0,
+            Some(_) => 1,
+        };
+        (offset, len).into()
+    }
+}
+
+/// A [`FileLoadError`] paired with the source of the file that failed to
+/// load.
+///
+/// Constructed via [`FileLoadError::with_source`].
+#[cfg(feature = "miette")]
+#[derive(Debug)]
+pub struct FileLoadDiagnostic {
+    error: FileLoadError,
+    source: miette::NamedSource,
+    span: Option<miette::SourceSpan>,
+}
+
+#[cfg(feature = "miette")]
+impl FileLoadDiagnostic {
+    /// Returns the underlying error.
+    pub fn error(&self) -> &FileLoadError {
+        &self.error
+    }
+
+    /// Consumes the diagnostic, returning the underlying error.
+    pub fn into_error(self) -> FileLoadError {
+        self.error
+    }
+}
+
+#[cfg(feature = "miette")]
+impl fmt::Display for FileLoadDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+#[cfg(feature = "miette")]
+impl Error for FileLoadDiagnostic {}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for FileLoadDiagnostic {
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(match self.error.severity {
+            Severity::Error => miette::Severity::Error,
+            Severity::Warning => miette::Severity::Warning,
+        })
+    }
+
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        Some(&self.source)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let span = self.span?;
+        let label = miette::LabeledSpan::new_with_span(Some(self.error.message.clone()), span);
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+/// An in-process equivalent of the `protoc` command-line tool.
+///
+/// The interface supports the same flags as the `protoc` binary built from
+/// the version of `libprotobuf` that this library is built against, including
+/// the built-in code generators (`--cpp_out`, `--java_out`, etc.) and plugins.
+/// The one exception is the JavaScript generator (`--js_out`), which is not
+/// available.
+/// Plugins named `protoc-gen-NAME` are located via the `PATH` environment
+/// variable, or via an explicit `--plugin` flag, just as with `protoc`.
+///
+/// Like `protoc`, the interface writes its diagnostics to the process's
+/// standard error and, for flags like `--decode` and `--version`, its output to
+/// the process's standard output. It may also run plugins as subprocesses. To
+/// parse .proto files without these side effects, use [`compile`] or an
+/// [`Importer`] instead.
+pub struct CommandLineInterface {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for CommandLineInterface {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteCommandLineInterface(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl CommandLineInterface {
+    /// Creates a new command-line interface.
+    pub fn new() -> Pin<Box<CommandLineInterface>> {
+        let cli = ffi::NewCommandLineInterface();
+        unsafe { Self::from_ffi_owned(cli) }
+    }
+
+    /// Runs the command-line interface with the given arguments and returns
+    /// the exit code that `protoc` would exit with.
+    ///
+    /// The arguments must not include the program name. For example:
+    ///
+    /// ```no_run
+    /// use protobuf_native::compiler::CommandLineInterface;
+    ///
+    /// let code = CommandLineInterface::new()
+    ///     .as_mut()
+    ///     .run(&["-Iproto", "--cpp_out=out", "proto/example.proto"]);
+    /// assert_eq!(code, 0);
+    /// ```
+    pub fn run<S>(self: Pin<&mut Self>, args: &[S]) -> i32
+    where
+        S: AsRef<str>,
+    {
+        let args = args.iter().map(|arg| arg.as_ref().to_owned()).collect();
+        ffi::CommandLineInterfaceRun(self.as_ffi_mut(), &args).0
+    }
+
+    unsafe_ffi_conversions!(ffi::CommandLineInterface);
+}
@@ -0,0 +1,38 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License in the LICENSE file at the
+// root of this repository, or online at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#pragma once
+
+#include <google/protobuf/compiler/plugin.pb.h>
+
+namespace protobuf_native {
+namespace compiler {
+namespace plugin {
+
+using CodeGeneratorRequest = google::protobuf::compiler::CodeGeneratorRequest;
+using CodeGeneratorResponse = google::protobuf::compiler::CodeGeneratorResponse;
+using CodeGeneratorResponse_File = google::protobuf::compiler::CodeGeneratorResponse_File;
+
+CodeGeneratorRequest* NewCodeGeneratorRequest();
+void DeleteCodeGeneratorRequest(CodeGeneratorRequest*);
+
+CodeGeneratorResponse* NewCodeGeneratorResponse();
+void DeleteCodeGeneratorResponse(CodeGeneratorResponse*);
+
+void DeleteCodeGeneratorResponseFile(CodeGeneratorResponse_File*);
+
+}  // namespace plugin
+}  // namespace compiler
+}  // namespace protobuf_native
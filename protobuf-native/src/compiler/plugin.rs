@@ -0,0 +1,442 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License in the LICENSE file at the
+// root of this repository, or online at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Support for writing `protoc` plugins.
+//!
+//! A `protoc` plugin is an executable that `protoc` runs to generate code for
+//! a set of .proto files. The plugin reads a [`CodeGeneratorRequest`]
+//! describing the files from its standard input and writes a
+//! [`CodeGeneratorResponse`] containing the generated files to its standard
+//! output. See [`run_plugin`] for an implementation of this protocol.
+//!
+//! ```no_run
+//! use protobuf_native::compiler::plugin::{self, CodeGeneratorResponse};
+//!
+//! fn main() -> Result<(), std::io::Error> {
+//!     plugin::run_plugin(|request| {
+//!         let mut response = CodeGeneratorResponse::new();
+//!         for i in 0..request.file_to_generate_size() {
+//!             let name = String::from_utf8_lossy(request.file_to_generate(i));
+//!             let mut file = response.as_mut().add_file();
+//!             file.as_mut().set_name(&format!("{}.txt", name));
+//!             file.as_mut().set_content("generated\n");
+//!         }
+//!         response
+//!     })
+//! }
+//! ```
+
+use std::io::{self, Write};
+use std::marker::PhantomPinned;
+use std::mem;
+use std::pin::Pin;
+
+use cxx::let_cxx_string;
+
+use crate::internal::{unsafe_ffi_conversions, CInt};
+use crate::{impl_message_traits, private, FileDescriptorProto, Message, MessageLite};
+
+#[cxx::bridge(namespace = "protobuf_native::compiler::plugin")]
+pub(crate) mod ffi {
+    unsafe extern "C++" {
+        include!("protobuf-native/src/compiler/plugin.h");
+        include!("protobuf-native/src/internal.h");
+
+        #[namespace = "protobuf_native::internal"]
+        type CInt = crate::internal::CInt;
+
+        #[namespace = "google::protobuf"]
+        type FileDescriptorProto = crate::ffi::FileDescriptorProto;
+
+        type CodeGeneratorRequest;
+        fn NewCodeGeneratorRequest() -> *mut CodeGeneratorRequest;
+        unsafe fn DeleteCodeGeneratorRequest(request: *mut CodeGeneratorRequest);
+        fn file_to_generate_size(self: &CodeGeneratorRequest) -> CInt;
+        fn file_to_generate(self: &CodeGeneratorRequest, i: CInt) -> &CxxString;
+        fn add_file_to_generate(self: Pin<&mut CodeGeneratorRequest>, value: &CxxString);
+        fn parameter(self: &CodeGeneratorRequest) -> &CxxString;
+        fn set_parameter(self: Pin<&mut CodeGeneratorRequest>, value: &CxxString);
+        fn proto_file_size(self: &CodeGeneratorRequest) -> CInt;
+        fn proto_file(self: &CodeGeneratorRequest, i: CInt) -> &FileDescriptorProto;
+        fn add_proto_file(self: Pin<&mut CodeGeneratorRequest>) -> *mut FileDescriptorProto;
+
+        type CodeGeneratorResponse;
+        fn NewCodeGeneratorResponse() -> *mut CodeGeneratorResponse;
+        unsafe fn DeleteCodeGeneratorResponse(response: *mut CodeGeneratorResponse);
+        fn error(self: &CodeGeneratorResponse) -> &CxxString;
+        fn set_error(self: Pin<&mut CodeGeneratorResponse>, value: &CxxString);
+        fn supported_features(self: &CodeGeneratorResponse) -> u64;
+        fn set_supported_features(self: Pin<&mut CodeGeneratorResponse>, value: u64);
+        fn file_size(self: &CodeGeneratorResponse) -> CInt;
+        fn file(self: &CodeGeneratorResponse, i: CInt) -> &CodeGeneratorResponse_File;
+        fn add_file(self: Pin<&mut CodeGeneratorResponse>) -> *mut CodeGeneratorResponse_File;
+
+        type CodeGeneratorResponse_File;
+        unsafe fn DeleteCodeGeneratorResponseFile(file: *mut CodeGeneratorResponse_File);
+        fn name(self: &CodeGeneratorResponse_File) -> &CxxString;
+        fn set_name(self: Pin<&mut CodeGeneratorResponse_File>, value: &CxxString);
+        fn insertion_point(self: &CodeGeneratorResponse_File) -> &CxxString;
+        fn set_insertion_point(self: Pin<&mut CodeGeneratorResponse_File>, value: &CxxString);
+        fn content(self: &CodeGeneratorResponse_File) -> &CxxString;
+        fn set_content(self: Pin<&mut CodeGeneratorResponse_File>, value: &CxxString);
+    }
+}
+
+/// Runs a `protoc` plugin.
+///
+/// Reads a [`CodeGeneratorRequest`] from standard input, passes it to `gen`,
+/// and writes the [`CodeGeneratorResponse`] that `gen` returns to standard
+/// output. This function is typically called from the plugin's `main`
+/// function.
+///
+/// Errors in the input .proto files should be reported to `protoc` by
+/// setting [`CodeGeneratorResponse::set_error`], not by returning an error
+/// from this function. An error is returned only if the request cannot be read
+/// or the response cannot be written, in which case the plugin should exit
+/// with a nonzero status.
+pub fn run_plugin<F>(gen: F) -> Result<(), io::Error>
+where
+    F: FnOnce(&CodeGeneratorRequest) -> Pin<Box<CodeGeneratorResponse>>,
+{
+    let mut request = CodeGeneratorRequest::new();
+    request
+        .as_mut()
+        .parse_from_reader(&mut io::stdin().lock())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let response = gen(&request)
+        .serialize()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(&response)?;
+    stdout.flush()
+}
+
+/// The request that `protoc` sends to a plugin.
+pub struct CodeGeneratorRequest {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for CodeGeneratorRequest {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteCodeGeneratorRequest(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl CodeGeneratorRequest {
+    /// Creates a new, empty request.
+    pub fn new() -> Pin<Box<CodeGeneratorRequest>> {
+        let request = ffi::NewCodeGeneratorRequest();
+        unsafe { Self::from_ffi_owned(request) }
+    }
+
+    /// Returns the number of entries in the `file_to_generate` field.
+    pub fn file_to_generate_size(&self) -> usize {
+        self.as_ffi().file_to_generate_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `file_to_generate` field.
+    ///
+    /// These are the names of the .proto files that were explicitly listed on
+    /// the command line. The plugin should generate code for exactly these
+    /// files. Their descriptors are included in [`proto_file`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    ///
+    /// [`proto_file`]: CodeGeneratorRequest::proto_file
+    pub fn file_to_generate(&self, i: usize) -> &[u8] {
+        if i >= self.file_to_generate_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.file_to_generate_size(),
+                i
+            );
+        }
+        self.as_ffi()
+            .file_to_generate(CInt::expect_from(i))
+            .as_bytes()
+    }
+
+    /// Adds an entry to the `file_to_generate` field.
+    pub fn add_file_to_generate(self: Pin<&mut Self>, value: &str) {
+        let_cxx_string!(value = value);
+        self.as_ffi_mut().add_file_to_generate(&value)
+    }
+
+    /// Returns the generator parameter passed on the command line, if any.
+    ///
+    /// For example, `protoc --foo_out=bar:out_dir` passes the parameter `bar`
+    /// to the `protoc-gen-foo` plugin.
+    pub fn parameter(&self) -> &[u8] {
+        self.as_ffi().parameter().as_bytes()
+    }
+
+    /// Sets the generator parameter.
+    pub fn set_parameter(self: Pin<&mut Self>, value: &str) {
+        let_cxx_string!(value = value);
+        self.as_ffi_mut().set_parameter(&value)
+    }
+
+    /// Returns the number of entries in the `proto_file` field.
+    pub fn proto_file_size(&self) -> usize {
+        self.as_ffi().proto_file_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `proto_file` field.
+    ///
+    /// These are the descriptors for the files in
+    /// [`file_to_generate`](CodeGeneratorRequest::file_to_generate) and
+    /// everything they import, ordered such that every file appears after all
+    /// of its dependencies.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn proto_file(&self, i: usize) -> &FileDescriptorProto {
+        if i >= self.proto_file_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.proto_file_size(),
+                i
+            );
+        }
+        FileDescriptorProto::from_ffi_ref(self.as_ffi().proto_file(CInt::expect_from(i)))
+    }
+
+    /// Adds a new empty entry to the `proto_file` field and returns a mutable
+    /// reference to it.
+    pub fn add_proto_file(self: Pin<&mut Self>) -> Pin<&mut FileDescriptorProto> {
+        let file = self.as_ffi_mut().add_proto_file();
+        unsafe { FileDescriptorProto::from_ffi_mut(file) }
+    }
+
+    unsafe_ffi_conversions!(ffi::CodeGeneratorRequest);
+}
+
+impl MessageLite for CodeGeneratorRequest {}
+
+impl private::MessageLite for CodeGeneratorRequest {
+    fn upcast(&self) -> &crate::ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut crate::ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for CodeGeneratorRequest {}
+
+impl_message_traits!(CodeGeneratorRequest);
+
+impl private::Message for CodeGeneratorRequest {
+    fn upcast_message(&self) -> &crate::ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut crate::ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// The response that a plugin sends to `protoc`.
+pub struct CodeGeneratorResponse {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for CodeGeneratorResponse {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteCodeGeneratorResponse(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl CodeGeneratorResponse {
+    /// The feature flag indicating that the plugin supports proto3 optional
+    /// fields.
+    ///
+    /// See [`CodeGeneratorResponse::set_supported_features`].
+    pub const FEATURE_PROTO3_OPTIONAL: u64 = 1;
+
+    /// Creates a new, empty response.
+    pub fn new() -> Pin<Box<CodeGeneratorResponse>> {
+        let response = ffi::NewCodeGeneratorResponse();
+        unsafe { Self::from_ffi_owned(response) }
+    }
+
+    /// Returns the error message, if any.
+    pub fn error(&self) -> &[u8] {
+        self.as_ffi().error().as_bytes()
+    }
+
+    /// Sets the error message.
+    ///
+    /// The error message indicates that the input .proto files were invalid
+    /// for this plugin, e.g., because they use an option that the plugin does
+    /// not support. `protoc` reports the message to the user and fails.
+    pub fn set_error(self: Pin<&mut Self>, value: &str) {
+        let_cxx_string!(value = value);
+        self.as_ffi_mut().set_error(&value)
+    }
+
+    /// Returns the bitwise OR of the features that the plugin supports.
+    pub fn supported_features(&self) -> u64 {
+        self.as_ffi().supported_features()
+    }
+
+    /// Sets the features that the plugin supports, as the bitwise OR of
+    /// constants like [`CodeGeneratorResponse::FEATURE_PROTO3_OPTIONAL`].
+    pub fn set_supported_features(self: Pin<&mut Self>, value: u64) {
+        self.as_ffi_mut().set_supported_features(value)
+    }
+
+    /// Returns the number of entries in the `file` field.
+    pub fn file_size(&self) -> usize {
+        self.as_ffi().file_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `file` field.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn file(&self, i: usize) -> &CodeGeneratorResponseFile {
+        if i >= self.file_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.file_size(),
+                i
+            );
+        }
+        CodeGeneratorResponseFile::from_ffi_ref(self.as_ffi().file(CInt::expect_from(i)))
+    }
+
+    /// Adds a new empty entry to the `file` field and returns a mutable
+    /// reference to it.
+    pub fn add_file(self: Pin<&mut Self>) -> Pin<&mut CodeGeneratorResponseFile> {
+        let file = self.as_ffi_mut().add_file();
+        unsafe { CodeGeneratorResponseFile::from_ffi_mut(file) }
+    }
+
+    unsafe_ffi_conversions!(ffi::CodeGeneratorResponse);
+}
+
+impl MessageLite for CodeGeneratorResponse {}
+
+impl private::MessageLite for CodeGeneratorResponse {
+    fn upcast(&self) -> &crate::ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut crate::ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for CodeGeneratorResponse {}
+
+impl_message_traits!(CodeGeneratorResponse);
+
+impl private::Message for CodeGeneratorResponse {
+    fn upcast_message(&self) -> &crate::ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut crate::ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// A file generated by a plugin, as part of a [`CodeGeneratorResponse`].
+pub struct CodeGeneratorResponseFile {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for CodeGeneratorResponseFile {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteCodeGeneratorResponseFile(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl CodeGeneratorResponseFile {
+    /// Returns the name of the file, relative to the output directory.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Sets the name of the file, relative to the output directory.
+    ///
+    /// The name must not contain `.` or `..` components and must be relative,
+    /// not absolute. The name should use `/` as the path separator, regardless
+    /// of the platform.
+    pub fn set_name(self: Pin<&mut Self>, value: &str) {
+        let_cxx_string!(value = value);
+        self.as_ffi_mut().set_name(&value)
+    }
+
+    /// Returns the insertion point, if any.
+    pub fn insertion_point(&self) -> &[u8] {
+        self.as_ffi().insertion_point().as_bytes()
+    }
+
+    /// Sets the insertion point.
+    ///
+    /// If set, the content of this file is inserted into the file of the same
+    /// name, which must have been generated by another plugin, immediately
+    /// above the line containing `@@protoc_insertion_point(NAME)`.
+    pub fn set_insertion_point(self: Pin<&mut Self>, value: &str) {
+        let_cxx_string!(value = value);
+        self.as_ffi_mut().set_insertion_point(&value)
+    }
+
+    /// Returns the content of the file.
+    pub fn content(&self) -> &[u8] {
+        self.as_ffi().content().as_bytes()
+    }
+
+    /// Sets the content of the file.
+    pub fn set_content(self: Pin<&mut Self>, value: &str) {
+        let_cxx_string!(value = value);
+        self.as_ffi_mut().set_content(&value)
+    }
+
+    unsafe_ffi_conversions!(ffi::CodeGeneratorResponse_File);
+}
+
+impl MessageLite for CodeGeneratorResponseFile {}
+
+impl private::MessageLite for CodeGeneratorResponseFile {
+    fn upcast(&self) -> &crate::ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut crate::ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for CodeGeneratorResponseFile {}
+
+impl_message_traits!(CodeGeneratorResponseFile);
+
+impl private::Message for CodeGeneratorResponseFile {
+    fn upcast_message(&self) -> &crate::ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut crate::ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
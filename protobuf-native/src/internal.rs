@@ -16,18 +16,22 @@
 #[cfg(unix)]
 use std::ffi::OsStr;
 use std::fmt;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
 #[cfg(windows)]
 use std::marker::PhantomData;
+use std::mem::MaybeUninit;
 use std::os::raw::{c_int, c_void};
 #[cfg(unix)]
 use std::os::unix::prelude::OsStrExt;
 use std::path::Path;
+use std::pin::Pin;
+use std::ptr;
 
 use cxx::kind::Trivial;
-use cxx::{type_id, ExternType};
+use cxx::{type_id, CxxString, ExternType};
 
-use crate::OperationFailedError;
+use crate::compiler::FileLoadError;
+use crate::{DescriptorDatabase, OperationFailedError};
 
 // Pollyfill C++ APIs that aren't yet in cxx.
 // See: https://github.com/dtolnay/cxx/pull/984
@@ -52,7 +56,7 @@ pub struct CInt(pub c_int);
 
 impl CInt {
     pub fn to_usize(self) -> Result<usize, OperationFailedError> {
-        usize::try_from(self.0).map_err(|_| OperationFailedError)
+        usize::try_from(self.0).map_err(|_| OperationFailedError::new())
     }
 
     pub fn expect_usize(self) -> usize {
@@ -95,7 +99,7 @@ unsafe impl ExternType for CVoid {
 
 // `Read` and `Write` adaptors for C++.
 
-pub struct ReadAdaptor<'a>(pub &'a mut dyn Read);
+pub struct ReadAdaptor<'a>(pub Box<dyn Read + 'a>);
 
 impl ReadAdaptor<'_> {
     pub fn read(&mut self, buf: &mut [u8]) -> isize {
@@ -114,6 +118,153 @@ impl WriteAdaptor<'_> {
     }
 }
 
+// `ZeroCopyInputStream` adaptor for C++.
+
+/// The operations of a `ZeroCopyInputStream` implemented in Rust.
+///
+/// See the C++ `ZeroCopyInputStream` class for the contract of each method.
+pub trait ZeroCopyInputSource {
+    /// Returns the next chunk of input, or an empty slice if there is no more
+    /// input. The chunk must be no longer than `c_int::MAX` bytes.
+    fn next(&mut self) -> &[u8];
+    fn back_up(&mut self, count: usize) -> Result<(), &'static str>;
+    fn skip(&mut self, count: usize) -> bool;
+    fn byte_count(&self) -> i64;
+}
+
+pub struct InputStreamAdaptor<'a>(pub Box<dyn ZeroCopyInputSource + Send + 'a>);
+
+impl InputStreamAdaptor<'_> {
+    pub fn next(&mut self) -> &[u8] {
+        self.0.next()
+    }
+
+    pub fn back_up(&mut self, count: usize) -> Result<(), &'static str> {
+        self.0.back_up(count)
+    }
+
+    pub fn skip(&mut self, count: usize) -> bool {
+        self.0.skip(count)
+    }
+
+    pub fn byte_count(&self) -> i64 {
+        self.0.byte_count()
+    }
+}
+
+// `ZeroCopyOutputStream` adaptor for C++.
+
+/// The operations of a `ZeroCopyOutputStream` implemented in Rust.
+///
+/// See the C++ `ZeroCopyOutputStream` class for the contract of each method.
+pub trait ZeroCopyOutputSink {
+    /// Returns the next buffer to write into, or an empty slice if no more
+    /// output can be written. The buffer must be no longer than `c_int::MAX`
+    /// bytes.
+    fn next(&mut self) -> &mut [MaybeUninit<u8>];
+    fn back_up(&mut self, count: usize) -> Result<(), &'static str>;
+    fn byte_count(&self) -> i64;
+}
+
+pub struct OutputStreamAdaptor<'a>(pub Box<dyn ZeroCopyOutputSink + Send + 'a>);
+
+impl OutputStreamAdaptor<'_> {
+    pub fn next(&mut self) -> crate::io::ffi::OutputBuffer {
+        let buf = self.0.next();
+        crate::io::ffi::OutputBuffer {
+            data: buf.as_mut_ptr().cast(),
+            size: buf.len(),
+        }
+    }
+
+    pub fn back_up(&mut self, count: usize) -> Result<(), &'static str> {
+        self.0.back_up(count)
+    }
+
+    pub fn byte_count(&self) -> i64 {
+        self.0.byte_count()
+    }
+}
+
+// `DescriptorDatabase` adaptor for C++.
+
+pub struct DescriptorDatabaseAdaptor<'a>(pub Pin<&'a mut dyn DescriptorDatabase>);
+
+impl DescriptorDatabaseAdaptor<'_> {
+    pub fn find_file_by_name(
+        &mut self,
+        filename: &[u8],
+        output: Pin<&mut crate::ffi::FileDescriptorProto>,
+    ) -> bool {
+        let filename = ProtobufPath::from(filename);
+        match self
+            .0
+            .as_mut()
+            .find_file_by_name(filename.as_path().as_ref())
+        {
+            Ok(proto) => {
+                output.CopyFrom(proto.as_ffi());
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    pub fn find_file_containing_symbol(
+        &mut self,
+        symbol_name: &CxxString,
+        output: Pin<&mut crate::ffi::FileDescriptorProto>,
+    ) -> bool {
+        let symbol_name = match symbol_name.to_str() {
+            Ok(symbol_name) => symbol_name,
+            Err(_) => return false,
+        };
+        match self.0.as_mut().find_file_containing_symbol(symbol_name) {
+            Ok(proto) => {
+                output.CopyFrom(proto.as_ffi());
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+// `SourceTree` adaptor for C++.
+
+type SourceTreeOpenFn<'a> = dyn FnMut(&Path) -> io::Result<Box<dyn Read>> + Send + 'a;
+
+pub struct SourceTreeAdaptor<'a> {
+    pub opener: Box<SourceTreeOpenFn<'a>>,
+    pub last_error_message: String,
+}
+
+impl SourceTreeAdaptor<'_> {
+    pub fn open(&mut self, filename: &[u8]) -> *mut crate::io::ffi::ReaderStream {
+        let filename = ProtobufPath::from(filename);
+        match (self.opener)(filename.as_path().as_ref()) {
+            Ok(reader) => crate::io::ffi::NewReaderStream(Box::new(ReadAdaptor(reader)), CInt(-1)),
+            Err(e) => {
+                self.last_error_message = e.to_string();
+                ptr::null_mut()
+            }
+        }
+    }
+
+    pub fn last_error_message(&self) -> String {
+        self.last_error_message.clone()
+    }
+}
+
+// `MultiFileErrorCollector` adaptor for C++.
+
+pub struct ErrorCollectorAdaptor<'a>(pub Box<dyn FnMut(&FileLoadError) + Send + 'a>);
+
+impl ErrorCollectorAdaptor<'_> {
+    pub fn add_error(&mut self, error: crate::compiler::ffi::FileLoadError) {
+        (self.0)(&error.into())
+    }
+}
+
 /// Extensions to [`Result`].
 pub trait ResultExt {
     /// Converts this result into a status boolean.
@@ -145,7 +296,7 @@ impl BoolExt for bool {
     fn as_result(self) -> Result<(), OperationFailedError> {
         match self {
             true => Ok(()),
-            false => Err(OperationFailedError),
+            false => Err(OperationFailedError::new()),
         }
     }
 }
@@ -15,6 +15,9 @@
 
 #include "protobuf-native/src/io.h"
 
+#include <map>
+#include <string>
+
 #include "protobuf-native/src/internal.rs.h"
 #include "protobuf-native/src/io.rs.h"
 
@@ -25,8 +28,8 @@ using namespace google::protobuf::io;
 
 void DeleteZeroCopyInputStream(ZeroCopyInputStream* stream) { delete stream; }
 
-ReaderStream::ReaderStream(rust::Box<ReadAdaptor> adaptor)
-    : CopyingInputStreamAdaptor(new CopyingReaderStream(std::move(adaptor))) {
+ReaderStream::ReaderStream(rust::Box<ReadAdaptor> adaptor, int block_size)
+    : CopyingInputStreamAdaptor(new CopyingReaderStream(std::move(adaptor)), block_size) {
     SetOwnsCopyingStream(true);
 }
 
@@ -37,20 +40,93 @@ int ReaderStream::CopyingReaderStream::Read(void* buffer, int size) {
     return adaptor_->read(rust::Slice<uint8_t>(static_cast<uint8_t*>(buffer), size));
 }
 
-ReaderStream* NewReaderStream(rust::Box<ReadAdaptor> adaptor) {
-    return new ReaderStream(std::move(adaptor));
+ReaderStream* NewReaderStream(rust::Box<ReadAdaptor> adaptor, int block_size) {
+    return new ReaderStream(std::move(adaptor), block_size);
 }
 
 void DeleteReaderStream(ReaderStream* stream) { delete stream; }
 
-ArrayInputStream* NewArrayInputStream(const uint8_t* data, int size) {
-    return new ArrayInputStream(data, size);
+RustInputStream::RustInputStream(rust::Box<InputStreamAdaptor> adaptor)
+    : adaptor_(std::move(adaptor)) {}
+
+bool RustInputStream::Next(const void** data, int* size) {
+    rust::Slice<const uint8_t> chunk = adaptor_->next();
+    if (chunk.empty()) {
+        return false;
+    }
+    *data = chunk.data();
+    *size = chunk.size();
+    return true;
+}
+
+void RustInputStream::BackUp(int count) {
+    GOOGLE_CHECK_GE(count, 0);
+    adaptor_->back_up(count);
+}
+
+bool RustInputStream::Skip(int count) {
+    GOOGLE_CHECK_GE(count, 0);
+    return adaptor_->skip(count);
 }
 
-void DeleteArrayInputStream(ArrayInputStream* stream) { delete stream; }
+int64_t RustInputStream::ByteCount() const { return adaptor_->byte_count(); }
+
+RustInputStream* NewRustInputStream(rust::Box<InputStreamAdaptor> adaptor) {
+    return new RustInputStream(std::move(adaptor));
+}
+
+void DeleteRustInputStream(RustInputStream* stream) { delete stream; }
+
+SliceInputStream::SliceInputStream(const uint8_t* data, int size)
+    : ArrayInputStream(data, size), size_(size) {}
+
+int SliceInputStream::Size() const { return size_; }
+
+SliceInputStream* NewSliceInputStream(const uint8_t* data, int size) {
+    return new SliceInputStream(data, size);
+}
+
+void DeleteSliceInputStream(SliceInputStream* stream) { delete stream; }
+
+LimitingInputStream* NewLimitingInputStream(ZeroCopyInputStream* input, int64_t limit) {
+    return new LimitingInputStream(input, limit);
+}
+
+void DeleteLimitingInputStream(LimitingInputStream* stream) { delete stream; }
+
+ConcatenatingInputStream::ConcatenatingInputStream(std::vector<ZeroCopyInputStream*> streams)
+    : streams_(std::move(streams)), inner_(streams_.data(), streams_.size()) {}
 
-WriterStream::WriterStream(rust::Box<WriteAdaptor> adaptor)
-    : CopyingOutputStreamAdaptor(new CopyingWriterStream(std::move(adaptor))) {
+bool ConcatenatingInputStream::Next(const void** data, int* size) {
+    return inner_.Next(data, size);
+}
+
+void ConcatenatingInputStream::BackUp(int count) { inner_.BackUp(count); }
+
+bool ConcatenatingInputStream::Skip(int count) { return inner_.Skip(count); }
+
+int64_t ConcatenatingInputStream::ByteCount() const { return inner_.ByteCount(); }
+
+ConcatenatingInputStream* NewConcatenatingInputStream(
+    rust::Slice<const ZeroCopyInputStreamPtr> streams) {
+    std::vector<ZeroCopyInputStream*> ptrs;
+    ptrs.reserve(streams.size());
+    for (const ZeroCopyInputStreamPtr& stream : streams) {
+        ptrs.push_back(stream.ptr);
+    }
+    return new ConcatenatingInputStream(std::move(ptrs));
+}
+
+void DeleteConcatenatingInputStream(ConcatenatingInputStream* stream) { delete stream; }
+
+GzipInputStream* NewGzipInputStream(ZeroCopyInputStream* sub_stream, int format) {
+    return new GzipInputStream(sub_stream, static_cast<GzipInputStream::Format>(format));
+}
+
+void DeleteGzipInputStream(GzipInputStream* stream) { delete stream; }
+
+WriterStream::WriterStream(rust::Box<WriteAdaptor> adaptor, int block_size)
+    : CopyingOutputStreamAdaptor(new CopyingWriterStream(std::move(adaptor)), block_size) {
     SetOwnsCopyingStream(true);
 }
 
@@ -61,14 +137,40 @@ bool WriterStream::CopyingWriterStream::Write(const void* buffer, int size) {
     return adaptor_->write(rust::Slice<const uint8_t>(static_cast<const uint8_t*>(buffer), size));
 }
 
-WriterStream* NewWriterStream(rust::Box<WriteAdaptor> adaptor) {
-    return new WriterStream(std::move(adaptor));
+WriterStream* NewWriterStream(rust::Box<WriteAdaptor> adaptor, int block_size) {
+    return new WriterStream(std::move(adaptor), block_size);
 }
 
 void DeleteWriterStream(WriterStream* stream) { delete stream; }
 
-ArrayOutputStream* NewArrayOutputStream(uint8_t* data, int size) {
-    return new ArrayOutputStream(data, size);
+RustOutputStream::RustOutputStream(rust::Box<OutputStreamAdaptor> adaptor)
+    : adaptor_(std::move(adaptor)) {}
+
+bool RustOutputStream::Next(void** data, int* size) {
+    OutputBuffer buffer = adaptor_->next();
+    if (buffer.size == 0) {
+        return false;
+    }
+    *data = buffer.data;
+    *size = buffer.size;
+    return true;
+}
+
+void RustOutputStream::BackUp(int count) {
+    GOOGLE_CHECK_GE(count, 0);
+    adaptor_->back_up(count);
+}
+
+int64_t RustOutputStream::ByteCount() const { return adaptor_->byte_count(); }
+
+RustOutputStream* NewRustOutputStream(rust::Box<OutputStreamAdaptor> adaptor) {
+    return new RustOutputStream(std::move(adaptor));
+}
+
+void DeleteRustOutputStream(RustOutputStream* stream) { delete stream; }
+
+ArrayOutputStream* NewArrayOutputStream(uint8_t* data, int size, int block_size) {
+    return new ArrayOutputStream(data, size, block_size);
 }
 
 void DeleteArrayOutputStream(ArrayOutputStream* stream) { delete stream; }
@@ -102,11 +204,113 @@ VecOutputStream* NewVecOutputStream(rust::Vec<uint8_t>& target) {
 
 void DeleteVecOutputStream(VecOutputStream* stream) { delete stream; }
 
+GzipOutputStream* NewGzipOutputStream(ZeroCopyOutputStream* sub_stream, int format,
+                                      int buffer_size, int compression_level) {
+    GzipOutputStream::Options options;
+    options.format = static_cast<GzipOutputStream::Format>(format);
+    if (buffer_size != -1) {
+        options.buffer_size = buffer_size;
+    }
+    if (compression_level != -1) {
+        options.compression_level = compression_level;
+    }
+    return new GzipOutputStream(sub_stream, options);
+}
+
+void DeleteGzipOutputStream(GzipOutputStream* stream) { delete stream; }
+
 CodedInputStream* NewCodedInputStream(ZeroCopyInputStream* input) {
     return new CodedInputStream(input);
 }
 
+CodedInputStream* NewCodedInputStreamFromArray(rust::Slice<const uint8_t> buffer) {
+    return new CodedInputStream(buffer.data(), buffer.size());
+}
+
 void DeleteCodedInputStream(CodedInputStream* stream) { delete stream; }
 
+CodedOutputStream* NewCodedOutputStream(ZeroCopyOutputStream* output) {
+    return new CodedOutputStream(output);
+}
+
+void DeleteCodedOutputStream(CodedOutputStream* stream) { delete stream; }
+
+Printer::Printer(ZeroCopyOutputStream* output, char variable_delimiter)
+    : printer_(output, variable_delimiter), variable_delimiter_(variable_delimiter) {}
+
+void Printer::Print(const std::string& text, const rust::Vec<rust::String>& names,
+                    const rust::Vec<rust::String>& values) {
+    std::map<std::string, std::string> variables;
+    for (size_t i = 0; i < names.size(); i++) {
+        variables[std::string(names[i])] = std::string(values[i]);
+    }
+    printer_.Print(variables, text.c_str());
+}
+
+void Printer::Indent() {
+    printer_.Indent();
+    indent_level_++;
+}
+
+bool Printer::Outdent() {
+    if (indent_level_ == 0) {
+        return false;
+    }
+    printer_.Outdent();
+    indent_level_--;
+    return true;
+}
+
+bool Printer::Failed() const { return printer_.failed(); }
+
+uint8_t Printer::Delimiter() const { return variable_delimiter_; }
+
+Printer* NewPrinter(ZeroCopyOutputStream* output, uint8_t variable_delimiter) {
+    return new Printer(output, variable_delimiter);
+}
+
+void DeletePrinter(Printer* printer) { delete printer; }
+
+SingleFileErrorCollector::SingleFileErrorCollector(
+    const std::string& filename, google::protobuf::compiler::MultiFileErrorCollector* multi)
+    : filename_(filename), multi_(multi) {}
+
+void SingleFileErrorCollector::AddError(int line, ColumnNumber column,
+                                        const std::string& message) {
+    multi_->AddError(filename_, line, column, message);
+}
+
+void SingleFileErrorCollector::AddWarning(int line, ColumnNumber column,
+                                          const std::string& message) {
+    multi_->AddWarning(filename_, line, column, message);
+}
+
+Tokenizer::Tokenizer(ZeroCopyInputStream* input, const std::string& filename,
+                     google::protobuf::compiler::MultiFileErrorCollector* error_collector)
+    : error_collector_(filename, error_collector), tokenizer_(input, &error_collector_) {}
+
+bool Tokenizer::Next() { return tokenizer_.Next(); }
+
+internal::CInt Tokenizer::CurrentType() const { return tokenizer_.current().type; }
+
+const std::string& Tokenizer::CurrentText() const { return tokenizer_.current().text; }
+
+internal::CInt Tokenizer::CurrentLine() const { return tokenizer_.current().line; }
+
+internal::CInt Tokenizer::CurrentColumn() const { return tokenizer_.current().column; }
+
+internal::CInt Tokenizer::CurrentEndColumn() const { return tokenizer_.current().end_column; }
+
+google::protobuf::io::Tokenizer& Tokenizer::Inner() { return tokenizer_; }
+
+ErrorCollector* Tokenizer::GetErrorCollector() { return &error_collector_; }
+
+Tokenizer* NewTokenizer(ZeroCopyInputStream* input, const std::string& filename,
+                        google::protobuf::compiler::MultiFileErrorCollector* error_collector) {
+    return new Tokenizer(input, filename, error_collector);
+}
+
+void DeleteTokenizer(Tokenizer* tokenizer) { delete tokenizer; }
+
 }  // namespace io
 }  // namespace protobuf_native
@@ -15,28 +15,38 @@
 
 #pragma once
 
+#include <google/protobuf/compiler/importer.h>
 #include <google/protobuf/io/coded_stream.h>
+#include <google/protobuf/io/gzip_stream.h>
+#include <google/protobuf/io/printer.h>
+#include <google/protobuf/io/tokenizer.h>
 #include <google/protobuf/io/zero_copy_stream.h>
 #include <google/protobuf/io/zero_copy_stream_impl.h>
 #include <google/protobuf/io/zero_copy_stream_impl_lite.h>
 
 #include <memory>
+#include <vector>
 
 #include "rust/cxx.h"
 
+#include "protobuf-native/src/internal.h"
+
 namespace protobuf_native {
 namespace io {
 
 using namespace google::protobuf::io;
 
+struct InputStreamAdaptor;
+struct OutputStreamAdaptor;
 struct ReadAdaptor;
 struct WriteAdaptor;
+struct ZeroCopyInputStreamPtr;
 
 void DeleteZeroCopyInputStream(ZeroCopyInputStream*);
 
 class ReaderStream : public CopyingInputStreamAdaptor {
    public:
-    ReaderStream(rust::Box<ReadAdaptor> adaptor);
+    ReaderStream(rust::Box<ReadAdaptor> adaptor, int block_size);
 
    private:
     class CopyingReaderStream : public CopyingInputStream {
@@ -50,17 +60,72 @@ class ReaderStream : public CopyingInputStreamAdaptor {
     };
 };
 
-ReaderStream* NewReaderStream(rust::Box<ReadAdaptor> adaptor);
+// A `block_size` of -1 selects the default.
+ReaderStream* NewReaderStream(rust::Box<ReadAdaptor> adaptor, int block_size);
 void DeleteReaderStream(ReaderStream*);
 
-ArrayInputStream* NewArrayInputStream(const uint8_t* data, int size);
-void DeleteArrayInputStream(ArrayInputStream*);
+// A `ZeroCopyInputStream` that forwards to a Rust `ZeroCopyInputSource`.
+class RustInputStream : public ZeroCopyInputStream {
+   public:
+    RustInputStream(rust::Box<InputStreamAdaptor> adaptor);
+
+    bool Next(const void** data, int* size) override;
+    void BackUp(int count) override;
+    bool Skip(int count) override;
+    int64_t ByteCount() const override;
+
+   private:
+    rust::Box<InputStreamAdaptor> adaptor_;
+};
+
+RustInputStream* NewRustInputStream(rust::Box<InputStreamAdaptor> adaptor);
+void DeleteRustInputStream(RustInputStream*);
+
+// An `ArrayInputStream` that remembers the size of its array.
+class SliceInputStream : public ArrayInputStream {
+   public:
+    SliceInputStream(const uint8_t* data, int size);
+
+    int Size() const;
+
+   private:
+    int size_;
+};
+
+SliceInputStream* NewSliceInputStream(const uint8_t* data, int size);
+void DeleteSliceInputStream(SliceInputStream*);
+
+LimitingInputStream* NewLimitingInputStream(ZeroCopyInputStream* input, int64_t limit);
+void DeleteLimitingInputStream(LimitingInputStream*);
+
+// Like `google::protobuf::io::ConcatenatingInputStream`, but owns the array of
+// stream pointers, which the upstream class requires to outlive it.
+class ConcatenatingInputStream : public ZeroCopyInputStream {
+   public:
+    ConcatenatingInputStream(std::vector<ZeroCopyInputStream*> streams);
+
+    bool Next(const void** data, int* size) override;
+    void BackUp(int count) override;
+    bool Skip(int count) override;
+    int64_t ByteCount() const override;
+
+   private:
+    std::vector<ZeroCopyInputStream*> streams_;
+    google::protobuf::io::ConcatenatingInputStream inner_;
+};
+
+ConcatenatingInputStream* NewConcatenatingInputStream(
+    rust::Slice<const ZeroCopyInputStreamPtr> streams);
+void DeleteConcatenatingInputStream(ConcatenatingInputStream*);
+
+GzipInputStream* NewGzipInputStream(ZeroCopyInputStream* sub_stream, int format);
+void DeleteGzipInputStream(GzipInputStream*);
 
 void DeleteZeroCopyOutputStream(ZeroCopyOutputStream*);
 
 class WriterStream : public CopyingOutputStreamAdaptor {
    public:
-    WriterStream(rust::Box<WriteAdaptor> adaptor);
+    WriterStream(rust::Box<WriteAdaptor> adaptor, int block_size);
 
    private:
     class CopyingWriterStream : public CopyingOutputStream {
@@ -74,10 +139,28 @@ class WriterStream : public CopyingOutputStreamAdaptor {
     };
 };
 
-WriterStream* NewWriterStream(rust::Box<WriteAdaptor> adaptor);
+// A `block_size` of -1 selects the default.
+WriterStream* NewWriterStream(rust::Box<WriteAdaptor> adaptor, int block_size);
 void DeleteWriterStream(WriterStream*);
 
-ArrayOutputStream* NewArrayOutputStream(uint8_t* data, int size);
+// A `ZeroCopyOutputStream` that forwards to a Rust `ZeroCopyOutputSink`.
+class RustOutputStream : public ZeroCopyOutputStream {
+   public:
+    RustOutputStream(rust::Box<OutputStreamAdaptor> adaptor);
+
+    bool Next(void** data, int* size) override;
+    void BackUp(int count) override;
+    int64_t ByteCount() const override;
+
+   private:
+    rust::Box<OutputStreamAdaptor> adaptor_;
+};
+
+RustOutputStream* NewRustOutputStream(rust::Box<OutputStreamAdaptor> adaptor);
+void DeleteRustOutputStream(RustOutputStream*);
+
+// A `block_size` of -1 selects the default.
+ArrayOutputStream* NewArrayOutputStream(uint8_t* data, int size, int block_size);
 void DeleteArrayOutputStream(ArrayOutputStream*);
 
 class VecOutputStream : public ZeroCopyOutputStream {
@@ -100,10 +183,76 @@ class VecOutputStream : public ZeroCopyOutputStream {
 VecOutputStream* NewVecOutputStream(rust::Vec<uint8_t>& target);
 void DeleteVecOutputStream(VecOutputStream*);
 
+// A `buffer_size` or `compression_level` of -1 selects the default.
+GzipOutputStream* NewGzipOutputStream(ZeroCopyOutputStream* sub_stream, int format,
+                                      int buffer_size, int compression_level);
+void DeleteGzipOutputStream(GzipOutputStream*);
+
 CodedInputStream* NewCodedInputStream(ZeroCopyInputStream* input);
+CodedInputStream* NewCodedInputStreamFromArray(rust::Slice<const uint8_t> buffer);
 void DeleteCodedInputStream(CodedInputStream*);
 
+CodedOutputStream* NewCodedOutputStream(ZeroCopyOutputStream* output);
 void DeleteCodedOutputStream(CodedOutputStream*);
 
+// Wraps `google::protobuf::io::Printer`, tracking the indentation level so that
+// an unbalanced `Outdent` can be detected before it reaches libprotobuf.
+class Printer {
+   public:
+    Printer(ZeroCopyOutputStream* output, char variable_delimiter);
+    void Print(const std::string& text, const rust::Vec<rust::String>& names,
+               const rust::Vec<rust::String>& values);
+    void Indent();
+    bool Outdent();
+    bool Failed() const;
+    uint8_t Delimiter() const;
+
+   private:
+    google::protobuf::io::Printer printer_;
+    char variable_delimiter_;
+    size_t indent_level_ = 0;
+};
+
+Printer* NewPrinter(ZeroCopyOutputStream* output, uint8_t variable_delimiter);
+void DeletePrinter(Printer*);
+
+// Reports errors in a single file to a `MultiFileErrorCollector`.
+class SingleFileErrorCollector : public ErrorCollector {
+   public:
+    SingleFileErrorCollector(const std::string& filename,
+                             google::protobuf::compiler::MultiFileErrorCollector* multi);
+    void AddError(int line, ColumnNumber column, const std::string& message) override;
+    void AddWarning(int line, ColumnNumber column, const std::string& message) override;
+
+   private:
+    std::string filename_;
+    google::protobuf::compiler::MultiFileErrorCollector* multi_;
+};
+
+// Wraps `google::protobuf::io::Tokenizer`, owning the error collector that
+// forwards its errors.
+class Tokenizer {
+   public:
+    Tokenizer(ZeroCopyInputStream* input, const std::string& filename,
+              google::protobuf::compiler::MultiFileErrorCollector* error_collector);
+    bool Next();
+    internal::CInt CurrentType() const;
+    const std::string& CurrentText() const;
+    internal::CInt CurrentLine() const;
+    internal::CInt CurrentColumn() const;
+    internal::CInt CurrentEndColumn() const;
+    google::protobuf::io::Tokenizer& Inner();
+    ErrorCollector* GetErrorCollector();
+
+   private:
+    SingleFileErrorCollector error_collector_;
+    // `Tokenizer::current` is not marked const, though it does not mutate.
+    mutable google::protobuf::io::Tokenizer tokenizer_;
+};
+
+Tokenizer* NewTokenizer(ZeroCopyInputStream* input, const std::string& filename,
+                        google::protobuf::compiler::MultiFileErrorCollector* error_collector);
+void DeleteTokenizer(Tokenizer*);
+
 }  // namespace io
 }  // namespace protobuf_native
@@ -103,18 +103,56 @@
 //! for practicality we set a limit at 64 bits. The maximum encoded length of a
 //! number is thus 10 bytes.
 
+use std::collections::BTreeMap;
 use std::io::{self, Read, Write};
 use std::marker::{PhantomData, PhantomPinned};
 use std::mem::{self, MaybeUninit};
+#[cfg(feature = "bytes")]
+use std::os::raw::c_int;
 use std::pin::Pin;
 use std::slice;
-
-use crate::internal::{unsafe_ffi_conversions, BoolExt, CInt, CVoid, ReadAdaptor, WriteAdaptor};
-use crate::OperationFailedError;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+#[cfg(feature = "bytes")]
+use bytes::{Buf, BufMut, BytesMut};
+use cxx::let_cxx_string;
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::compiler::MultiFileErrorCollector;
+use crate::internal::{
+    unsafe_ffi_conversions, BoolExt, CInt, CVoid, InputStreamAdaptor, OutputStreamAdaptor,
+    ReadAdaptor, WriteAdaptor,
+};
+#[cfg(feature = "bytes")]
+use crate::internal::{ZeroCopyInputSource, ZeroCopyOutputSink};
+#[cfg(feature = "tokio")]
+use crate::MessageLite;
+use crate::{log, OperationFailedError};
 
 #[cxx::bridge(namespace = "protobuf_native::io")]
 pub(crate) mod ffi {
+    struct ZeroCopyInputStreamPtr {
+        ptr: *mut ZeroCopyInputStream,
+    }
+
+    struct OutputBuffer {
+        data: *mut u8,
+        size: usize,
+    }
+
     extern "Rust" {
+        type InputStreamAdaptor<'a>;
+        unsafe fn next<'a>(self: &'a mut InputStreamAdaptor<'_>) -> &'a [u8];
+        fn back_up(self: &mut InputStreamAdaptor<'_>, count: usize) -> Result<()>;
+        fn skip(self: &mut InputStreamAdaptor<'_>, count: usize) -> bool;
+        fn byte_count(self: &InputStreamAdaptor<'_>) -> i64;
+
+        type OutputStreamAdaptor<'a>;
+        fn next(self: &mut OutputStreamAdaptor<'_>) -> OutputBuffer;
+        fn back_up(self: &mut OutputStreamAdaptor<'_>, count: usize) -> Result<()>;
+        fn byte_count(self: &OutputStreamAdaptor<'_>) -> i64;
+
         type ReadAdaptor<'a>;
         fn read(self: &mut ReadAdaptor<'_>, buf: &mut [u8]) -> isize;
 
@@ -140,17 +178,47 @@ pub(crate) mod ffi {
             size: *mut CInt,
         ) -> bool;
         fn BackUp(self: Pin<&mut ZeroCopyInputStream>, count: CInt);
+        #[cxx_name = "BackUp"]
+        fn TryBackUp(self: Pin<&mut ZeroCopyInputStream>, count: CInt) -> Result<()>;
         fn Skip(self: Pin<&mut ZeroCopyInputStream>, count: CInt) -> bool;
         fn ByteCount(self: &ZeroCopyInputStream) -> i64;
 
         type ReaderStream;
-        fn NewReaderStream(adaptor: Box<ReadAdaptor<'_>>) -> *mut ReaderStream;
+        fn NewReaderStream(adaptor: Box<ReadAdaptor<'_>>, block_size: CInt) -> *mut ReaderStream;
         unsafe fn DeleteReaderStream(stream: *mut ReaderStream);
 
+        type RustInputStream;
+        #[cfg(feature = "bytes")]
+        fn NewRustInputStream(adaptor: Box<InputStreamAdaptor<'_>>) -> *mut RustInputStream;
+        #[cfg(feature = "bytes")]
+        unsafe fn DeleteRustInputStream(stream: *mut RustInputStream);
+
+        type SliceInputStream;
+        unsafe fn NewSliceInputStream(data: *const u8, size: CInt) -> *mut SliceInputStream;
+        unsafe fn DeleteSliceInputStream(stream: *mut SliceInputStream);
+        fn Size(self: &SliceInputStream) -> CInt;
+
+        #[namespace = "google::protobuf::io"]
+        type GzipInputStream;
+        unsafe fn NewGzipInputStream(
+            sub_stream: *mut ZeroCopyInputStream,
+            format: CInt,
+        ) -> *mut GzipInputStream;
+        unsafe fn DeleteGzipInputStream(stream: *mut GzipInputStream);
+
         #[namespace = "google::protobuf::io"]
-        type ArrayInputStream;
-        unsafe fn NewArrayInputStream(data: *const u8, size: CInt) -> *mut ArrayInputStream;
-        unsafe fn DeleteArrayInputStream(stream: *mut ArrayInputStream);
+        type LimitingInputStream;
+        unsafe fn NewLimitingInputStream(
+            input: *mut ZeroCopyInputStream,
+            limit: i64,
+        ) -> *mut LimitingInputStream;
+        unsafe fn DeleteLimitingInputStream(stream: *mut LimitingInputStream);
+
+        type ConcatenatingInputStream;
+        unsafe fn NewConcatenatingInputStream(
+            streams: &[ZeroCopyInputStreamPtr],
+        ) -> *mut ConcatenatingInputStream;
+        unsafe fn DeleteConcatenatingInputStream(stream: *mut ConcatenatingInputStream);
 
         #[namespace = "google::protobuf::io"]
         type ZeroCopyOutputStream;
@@ -160,39 +228,123 @@ pub(crate) mod ffi {
             size: *mut CInt,
         ) -> bool;
         fn BackUp(self: Pin<&mut ZeroCopyOutputStream>, count: CInt);
+        #[cxx_name = "BackUp"]
+        fn TryBackUp(self: Pin<&mut ZeroCopyOutputStream>, count: CInt) -> Result<()>;
         fn ByteCount(self: &ZeroCopyOutputStream) -> i64;
 
         type WriterStream;
-        fn NewWriterStream(adaptor: Box<WriteAdaptor<'_>>) -> *mut WriterStream;
+        fn NewWriterStream(adaptor: Box<WriteAdaptor<'_>>, block_size: CInt) -> *mut WriterStream;
         unsafe fn DeleteWriterStream(stream: *mut WriterStream);
 
         #[namespace = "google::protobuf::io"]
         type ArrayOutputStream;
-        unsafe fn NewArrayOutputStream(data: *mut u8, size: CInt) -> *mut ArrayOutputStream;
+        unsafe fn NewArrayOutputStream(
+            data: *mut u8,
+            size: CInt,
+            block_size: CInt,
+        ) -> *mut ArrayOutputStream;
         unsafe fn DeleteArrayOutputStream(stream: *mut ArrayOutputStream);
 
+        type RustOutputStream;
+        #[cfg(feature = "bytes")]
+        fn NewRustOutputStream(adaptor: Box<OutputStreamAdaptor<'_>>) -> *mut RustOutputStream;
+        #[cfg(feature = "bytes")]
+        unsafe fn DeleteRustOutputStream(stream: *mut RustOutputStream);
+
         type VecOutputStream;
         fn NewVecOutputStream(target: &mut Vec<u8>) -> *mut VecOutputStream;
         unsafe fn DeleteVecOutputStream(stream: *mut VecOutputStream);
 
+        #[namespace = "google::protobuf::io"]
+        type GzipOutputStream;
+        unsafe fn NewGzipOutputStream(
+            sub_stream: *mut ZeroCopyOutputStream,
+            format: CInt,
+            buffer_size: CInt,
+            compression_level: CInt,
+        ) -> *mut GzipOutputStream;
+        unsafe fn DeleteGzipOutputStream(stream: *mut GzipOutputStream);
+        fn Flush(self: Pin<&mut GzipOutputStream>) -> bool;
+        fn Close(self: Pin<&mut GzipOutputStream>) -> bool;
+
         #[namespace = "google::protobuf::io"]
         type CodedInputStream;
         unsafe fn NewCodedInputStream(ptr: *mut ZeroCopyInputStream) -> *mut CodedInputStream;
+        fn NewCodedInputStreamFromArray(buffer: &[u8]) -> *mut CodedInputStream;
         unsafe fn DeleteCodedInputStream(stream: *mut CodedInputStream);
         fn IsFlat(self: &CodedInputStream) -> bool;
         unsafe fn ReadRaw(self: Pin<&mut CodedInputStream>, buffer: *mut CVoid, size: CInt)
             -> bool;
+        unsafe fn ReadString(
+            self: Pin<&mut CodedInputStream>,
+            buffer: *mut CxxString,
+            size: CInt,
+        ) -> bool;
+        unsafe fn ReadLittleEndian32(self: Pin<&mut CodedInputStream>, value: *mut u32) -> bool;
+        unsafe fn ReadLittleEndian64(self: Pin<&mut CodedInputStream>, value: *mut u64) -> bool;
         unsafe fn ReadVarint32(self: Pin<&mut CodedInputStream>, value: *mut u32) -> bool;
         unsafe fn ReadVarint64(self: Pin<&mut CodedInputStream>, value: *mut u64) -> bool;
         fn ReadTag(self: Pin<&mut CodedInputStream>) -> u32;
         fn ReadTagNoLastTag(self: Pin<&mut CodedInputStream>) -> u32;
         fn LastTagWas(self: Pin<&mut CodedInputStream>, expected: u32) -> bool;
         fn ConsumedEntireMessage(self: Pin<&mut CodedInputStream>) -> bool;
+        fn Skip(self: Pin<&mut CodedInputStream>, count: CInt) -> bool;
         fn CurrentPosition(self: &CodedInputStream) -> CInt;
+        fn PushLimit(self: Pin<&mut CodedInputStream>, byte_limit: CInt) -> CInt;
+        fn PopLimit(self: Pin<&mut CodedInputStream>, limit: CInt);
+        fn BytesUntilLimit(self: &CodedInputStream) -> CInt;
+        fn BytesUntilTotalBytesLimit(self: &CodedInputStream) -> CInt;
+        fn SetTotalBytesLimit(self: Pin<&mut CodedInputStream>, total_bytes_limit: CInt);
+        fn SetRecursionLimit(self: Pin<&mut CodedInputStream>, limit: CInt);
 
         #[namespace = "google::protobuf::io"]
         type CodedOutputStream;
+        unsafe fn NewCodedOutputStream(ptr: *mut ZeroCopyOutputStream) -> *mut CodedOutputStream;
         unsafe fn DeleteCodedOutputStream(stream: *mut CodedOutputStream);
+        unsafe fn WriteRaw(self: Pin<&mut CodedOutputStream>, buffer: *const CVoid, size: CInt);
+        fn WriteLittleEndian32(self: Pin<&mut CodedOutputStream>, value: u32);
+        fn WriteLittleEndian64(self: Pin<&mut CodedOutputStream>, value: u64);
+        fn WriteVarint32(self: Pin<&mut CodedOutputStream>, value: u32);
+        fn WriteVarint64(self: Pin<&mut CodedOutputStream>, value: u64);
+        fn HadError(self: Pin<&mut CodedOutputStream>) -> bool;
+        fn Trim(self: Pin<&mut CodedOutputStream>);
+        fn ByteCount(self: &CodedOutputStream) -> CInt;
+        fn SetSerializationDeterministic(self: Pin<&mut CodedOutputStream>, value: bool);
+        fn IsSerializationDeterministic(self: &CodedOutputStream) -> bool;
+
+        type Printer;
+        unsafe fn NewPrinter(
+            output: *mut ZeroCopyOutputStream,
+            variable_delimiter: u8,
+        ) -> *mut Printer;
+        unsafe fn DeletePrinter(printer: *mut Printer);
+        fn Print(
+            self: Pin<&mut Printer>,
+            text: &CxxString,
+            names: &Vec<String>,
+            values: &Vec<String>,
+        );
+        fn Indent(self: Pin<&mut Printer>);
+        fn Outdent(self: Pin<&mut Printer>) -> bool;
+        fn Failed(self: &Printer) -> bool;
+        fn Delimiter(self: &Printer) -> u8;
+
+        #[namespace = "google::protobuf::compiler"]
+        type MultiFileErrorCollector = crate::compiler::ffi::MultiFileErrorCollector;
+
+        type Tokenizer;
+        unsafe fn NewTokenizer(
+            input: *mut ZeroCopyInputStream,
+            filename: &CxxString,
+            error_collector: *mut MultiFileErrorCollector,
+        ) -> *mut Tokenizer;
+        unsafe fn DeleteTokenizer(tokenizer: *mut Tokenizer);
+        fn Next(self: Pin<&mut Tokenizer>) -> bool;
+        fn CurrentType(self: &Tokenizer) -> CInt;
+        fn CurrentText(self: &Tokenizer) -> &CxxString;
+        fn CurrentLine(self: &Tokenizer) -> CInt;
+        fn CurrentColumn(self: &Tokenizer) -> CInt;
+        fn CurrentEndColumn(self: &Tokenizer) -> CInt;
     }
 
     impl UniquePtr<ZeroCopyOutputStream> {}
@@ -229,15 +381,19 @@ pub trait ZeroCopyInputStream: zero_copy_input_stream::Sealed {
     fn next(self: Pin<&mut Self>) -> Result<&[u8], OperationFailedError> {
         let mut data = MaybeUninit::uninit();
         let mut size = MaybeUninit::uninit();
+        let mut stream = self.upcast_mut();
+        forget_last_next(&stream);
         unsafe {
             // SAFETY: `data` and `size` are non-null, as required.
-            self.upcast_mut()
+            stream
+                .as_mut()
                 .Next(data.as_mut_ptr(), size.as_mut_ptr())
                 .as_result()?;
             // SAFETY: `Next` has succeeded and so has promised to provide us
             // with a valid buffer.
             let data = data.assume_init() as *const u8;
             let size = size.assume_init().to_usize()?;
+            record_last_next(&stream, size);
             Ok(slice::from_raw_parts(data, size))
         }
     }
@@ -262,7 +418,41 @@ pub trait ZeroCopyInputStream: zero_copy_input_stream::Sealed {
         // something more graceful than panicking since `BackUp` will often
         // crash the process on too-large input.
         let count = CInt::try_from(count).expect("count did not fit in a C int");
-        self.upcast_mut().BackUp(count)
+        let stream = self.upcast_mut();
+        forget_last_next(&stream);
+        stream.BackUp(count)
+    }
+
+    /// Like [`back_up`], but returns an error rather than terminating the
+    /// process if `count` is invalid.
+    ///
+    /// Suitable for use when `count` is derived from untrusted input. Not all
+    /// streams validate `count` themselves, so `count` is checked against the
+    /// size of the buffer returned by the last call to [`next`] on this stream.
+    /// An error is returned if any other method has been called on the stream
+    /// since. Calls on other streams do not interfere, so several streams may
+    /// be read in an interleaved fashion. Any violations detected by the stream
+    /// itself are still logged by `libprotobuf` at [`LogLevel::Fatal`], but are
+    /// reported as an error instead of terminating the process.
+    ///
+    /// [`back_up`]: ZeroCopyInputStream::back_up
+    /// [`next`]: ZeroCopyInputStream::next
+    /// [`LogLevel::Fatal`]: crate::log::LogLevel::Fatal
+    fn try_back_up(self: Pin<&mut Self>, count: usize) -> Result<(), OperationFailedError> {
+        let stream = self.upcast_mut();
+        let size = last_next_size(&stream).ok_or_else(|| {
+            OperationFailedError::with_message("back_up must immediately follow a call to next")
+        })?;
+        if count > size {
+            return Err(OperationFailedError::with_message(format!(
+                "cannot back up {} bytes after reading a buffer of {} bytes",
+                count, size
+            )));
+        }
+        forget_last_next(&stream);
+        stream
+            .TryBackUp(CInt::expect_from(count))
+            .map_err(|e| OperationFailedError::with_message(e.what()))
     }
 
     /// Skips `count` bytes.
@@ -273,14 +463,34 @@ pub trait ZeroCopyInputStream: zero_copy_input_stream::Sealed {
     ///
     /// [`byte_count`]: ZeroCopyInputStream::byte_count
     fn skip(self: Pin<&mut Self>, count: usize) -> Result<(), OperationFailedError> {
-        let count = CInt::try_from(count).map_err(|_| OperationFailedError)?;
-        self.upcast_mut().Skip(count).as_result()
+        let count = CInt::try_from(count).map_err(|_| OperationFailedError::new())?;
+        let stream = self.upcast_mut();
+        forget_last_next(&stream);
+        stream.Skip(count).as_result()
     }
 
     /// Returns the total number of bytes read since this stream was created.
     fn byte_count(&self) -> i64 {
         self.upcast().ByteCount()
     }
+
+    /// Reads all remaining data in the stream, appending it to `buf`.
+    ///
+    /// Returns the number of bytes appended to `buf`. As with [`next`], the
+    /// end of the stream cannot be distinguished from an I/O error, so an I/O
+    /// error ends the read early rather than being reported.
+    ///
+    /// [`next`]: ZeroCopyInputStream::next
+    fn read_to_end(
+        mut self: Pin<&mut Self>,
+        buf: &mut Vec<u8>,
+    ) -> Result<usize, OperationFailedError> {
+        let start = buf.len();
+        while let Ok(chunk) = self.as_mut().next() {
+            buf.extend_from_slice(chunk);
+        }
+        Ok(buf.len() - start)
+    }
 }
 
 mod zero_copy_input_stream {
@@ -292,11 +502,77 @@ mod zero_copy_input_stream {
         fn upcast(&self) -> &ffi::ZeroCopyInputStream;
         fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::ZeroCopyInputStream>;
         unsafe fn upcast_mut_ptr(self: Pin<&mut Self>) -> *mut ffi::ZeroCopyInputStream {
-            self.upcast_mut().get_unchecked_mut() as *mut _
+            let stream = self.upcast_mut();
+            // The stream may be read through the pointer without our knowledge.
+            super::forget_last_next(&stream);
+            stream.get_unchecked_mut() as *mut _
         }
     }
 }
 
+/// The last successful call to [`ZeroCopyInputStream::next`] on a stream.
+#[derive(Clone, Copy)]
+struct LastNext {
+    /// The stream's byte count after the call, which guards against the
+    /// stream being advanced without our knowledge.
+    byte_count: i64,
+    /// The size of the buffer returned by the call.
+    size: usize,
+}
+
+/// The last call to `next` on each stream whose last method call was `next`,
+/// keyed by the stream's address. Streams can be sent between threads, so the
+/// map is shared by all threads. Entries are removed when their stream is
+/// dropped.
+static LAST_NEXT: Mutex<BTreeMap<usize, LastNext>> = Mutex::new(BTreeMap::new());
+
+fn last_next_map() -> MutexGuard<'static, BTreeMap<usize, LastNext>> {
+    // The map is never left in an inconsistent state, so it is safe to ignore
+    // poisoning.
+    LAST_NEXT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn record_last_next(stream: &ffi::ZeroCopyInputStream, size: usize) {
+    let last_next = LastNext {
+        byte_count: stream.ByteCount(),
+        size,
+    };
+    last_next_map().insert(stream as *const _ as usize, last_next);
+}
+
+fn forget_last_next(stream: &ffi::ZeroCopyInputStream) {
+    last_next_map().remove(&(stream as *const _ as usize));
+}
+
+/// Returns the size of the buffer returned by the last call to `next` on
+/// `stream`, if no other call on `stream` has intervened.
+fn last_next_size(stream: &ffi::ZeroCopyInputStream) -> Option<usize> {
+    match last_next_map().get(&(stream as *const _ as usize)) {
+        Some(last_next) if last_next.byte_count == stream.ByteCount() => Some(last_next.size),
+        _ => None,
+    }
+}
+
+/// Validates the `count` passed to an output stream's `try_back_up` method
+/// against the number of bytes that have passed through the stream.
+fn check_back_up_count(count: usize, byte_count: i64) -> Result<CInt, OperationFailedError> {
+    if !matches!(i64::try_from(count), Ok(count) if count <= byte_count) {
+        return Err(OperationFailedError::with_message(format!(
+            "cannot back up {} bytes in a stream of {} bytes",
+            count, byte_count
+        )));
+    }
+    CInt::try_from(count).map_err(|_| {
+        OperationFailedError::with_message(format!("cannot back up {} bytes at once", count))
+    })
+}
+
+/// Validates a user-provided block size for a stream.
+fn check_block_size(block_size: usize) -> CInt {
+    assert!(block_size > 0, "block size must be positive");
+    CInt::expect_from(block_size)
+}
+
 /// Converts an [`Read`] implementor to a [`ZeroCopyInputStream`].
 pub struct ReaderStream<'a> {
     _opaque: PhantomPinned,
@@ -305,6 +581,7 @@ pub struct ReaderStream<'a> {
 
 impl<'a> Drop for ReaderStream<'a> {
     fn drop(&mut self) {
+        forget_last_next(zero_copy_input_stream::Sealed::upcast(self));
         unsafe { ffi::DeleteReaderStream(self.as_ffi_mut_ptr_unpinned()) }
     }
 }
@@ -312,7 +589,27 @@ impl<'a> Drop for ReaderStream<'a> {
 impl<'a> ReaderStream<'a> {
     /// Creates a reader stream from the specified [`Read`] implementor.
     pub fn new(reader: &'a mut dyn Read) -> Pin<Box<ReaderStream<'a>>> {
-        let stream = ffi::NewReaderStream(Box::new(ReadAdaptor(reader)));
+        let stream = ffi::NewReaderStream(Box::new(ReadAdaptor(Box::new(reader))), CInt(-1));
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    /// Creates a reader stream from the specified [`Read`] implementor that
+    /// reads from `reader` in blocks of `block_size` bytes.
+    ///
+    /// Each call to [`next`] returns at most one block. Larger blocks reduce
+    /// the number of calls required to consume large inputs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero.
+    ///
+    /// [`next`]: ZeroCopyInputStream::next
+    pub fn with_block_size(
+        reader: &'a mut dyn Read,
+        block_size: usize,
+    ) -> Pin<Box<ReaderStream<'a>>> {
+        let block_size = check_block_size(block_size);
+        let stream = ffi::NewReaderStream(Box::new(ReadAdaptor(Box::new(reader))), block_size);
         unsafe { Self::from_ffi_owned(stream) }
     }
 
@@ -331,6 +628,114 @@ impl<'a> zero_copy_input_stream::Sealed for ReaderStream<'a> {
     }
 }
 
+/// A [`ZeroCopyInputStream`] that reads from a [`bytes::Buf`].
+///
+/// The stream returns each contiguous chunk of the buffer directly, so the
+/// buffer need not be copied into contiguous memory before parsing. The
+/// buffer is advanced past the bytes that have been consumed by the time the
+/// stream is dropped. To observe how much of a buffer was consumed, pass a
+/// mutable reference to the buffer.
+///
+/// This type is only available if the `bytes` feature is enabled.
+#[cfg(feature = "bytes")]
+pub struct BufInputStream<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+#[cfg(feature = "bytes")]
+impl<'a> Drop for BufInputStream<'a> {
+    fn drop(&mut self) {
+        forget_last_next(zero_copy_input_stream::Sealed::upcast(self));
+        unsafe { ffi::DeleteRustInputStream(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<'a> BufInputStream<'a> {
+    /// Creates a new `BufInputStream` that reads from the provided buffer.
+    ///
+    /// The buffer must be `Send`, as the stream may be sent to another thread.
+    pub fn new<B>(buf: B) -> Pin<Box<BufInputStream<'a>>>
+    where
+        B: Buf + Send + 'a,
+    {
+        let source = BufSource {
+            buf,
+            pending: 0,
+            byte_count: 0,
+        };
+        let stream = ffi::NewRustInputStream(Box::new(InputStreamAdaptor(Box::new(source))));
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    unsafe_ffi_conversions!(ffi::RustInputStream);
+}
+
+#[cfg(feature = "bytes")]
+impl<'a> ZeroCopyInputStream for BufInputStream<'a> {}
+
+#[cfg(feature = "bytes")]
+impl<'a> zero_copy_input_stream::Sealed for BufInputStream<'a> {
+    fn upcast(&self) -> &ffi::ZeroCopyInputStream {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::ZeroCopyInputStream> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+#[cfg(feature = "bytes")]
+struct BufSource<B: Buf> {
+    buf: B,
+    /// The number of bytes returned by the last call to `next` that have not
+    /// yet been advanced past.
+    pending: usize,
+    byte_count: i64,
+}
+
+#[cfg(feature = "bytes")]
+impl<B: Buf> ZeroCopyInputSource for BufSource<B> {
+    fn next(&mut self) -> &[u8] {
+        self.buf.advance(self.pending);
+        let chunk = self.buf.chunk();
+        let chunk = &chunk[..chunk.len().min(c_int::MAX as usize)];
+        self.pending = chunk.len();
+        self.byte_count += chunk.len() as i64;
+        chunk
+    }
+
+    fn back_up(&mut self, count: usize) -> Result<(), &'static str> {
+        if count > self.pending {
+            return Err("cannot back up past the last chunk");
+        }
+        self.pending -= count;
+        self.byte_count -= count as i64;
+        Ok(())
+    }
+
+    fn skip(&mut self, count: usize) -> bool {
+        self.buf.advance(self.pending);
+        self.pending = 0;
+        let n = count.min(self.buf.remaining());
+        self.buf.advance(n);
+        self.byte_count += n as i64;
+        n == count
+    }
+
+    fn byte_count(&self) -> i64 {
+        self.byte_count
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<B: Buf> Drop for BufSource<B> {
+    fn drop(&mut self) {
+        self.buf.advance(self.pending);
+    }
+}
+
 /// A [`ZeroCopyInputStream`] specialized for reading from byte slices.
 ///
 /// Using this type is more efficient than using a [`ReaderStream`] when the
@@ -342,7 +747,8 @@ pub struct SliceInputStream<'a> {
 
 impl<'a> Drop for SliceInputStream<'a> {
     fn drop(&mut self) {
-        unsafe { ffi::DeleteArrayInputStream(self.as_ffi_mut_ptr_unpinned()) }
+        forget_last_next(zero_copy_input_stream::Sealed::upcast(self));
+        unsafe { ffi::DeleteSliceInputStream(self.as_ffi_mut_ptr_unpinned()) }
     }
 }
 
@@ -350,11 +756,27 @@ impl<'a> SliceInputStream<'a> {
     /// Creates a new `SliceInputStream` from the provided byte slice.
     pub fn new(slice: &[u8]) -> Pin<Box<SliceInputStream<'a>>> {
         let size = CInt::expect_from(slice.len());
-        let stream = unsafe { ffi::NewArrayInputStream(slice.as_ptr(), size) };
+        let stream = unsafe { ffi::NewSliceInputStream(slice.as_ptr(), size) };
         unsafe { Self::from_ffi_owned(stream) }
     }
 
-    unsafe_ffi_conversions!(ffi::ArrayInputStream);
+    /// Returns the offset into the slice of the next byte to be read.
+    ///
+    /// Bytes returned by [`next`] count as read unless they are returned to
+    /// the stream via [`back_up`].
+    ///
+    /// [`next`]: ZeroCopyInputStream::next
+    /// [`back_up`]: ZeroCopyInputStream::back_up
+    pub fn current_position(&self) -> usize {
+        usize::try_from(self.byte_count()).expect("stream position not representable as usize")
+    }
+
+    /// Returns the number of bytes in the slice that remain to be read.
+    pub fn bytes_remaining(&self) -> usize {
+        self.as_ffi().Size().expect_usize() - self.current_position()
+    }
+
+    unsafe_ffi_conversions!(ffi::SliceInputStream);
 }
 
 impl<'a> ZeroCopyInputStream for SliceInputStream<'a> {}
@@ -380,6 +802,7 @@ pub struct DynZeroCopyInputStream<'a> {
 
 impl<'a> Drop for DynZeroCopyInputStream<'a> {
     fn drop(&mut self) {
+        forget_last_next(zero_copy_input_stream::Sealed::upcast(self));
         unsafe { ffi::DeleteZeroCopyInputStream(self.as_ffi_mut_ptr_unpinned()) }
     }
 }
@@ -400,6 +823,173 @@ impl zero_copy_input_stream::Sealed for DynZeroCopyInputStream<'_> {
     }
 }
 
+/// A [`ZeroCopyInputStream`] that reads at most a fixed number of bytes from
+/// another `ZeroCopyInputStream`.
+///
+/// Once the limit is reached, the stream behaves as if the end of the input
+/// has been reached. When the `LimitingInputStream` is dropped, any data that
+/// it read from the underlying stream but did not return is backed up, so the
+/// underlying stream is positioned exactly `limit` bytes past where it started
+/// (or at its end, if it contained fewer bytes).
+pub struct LimitingInputStream<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> Drop for LimitingInputStream<'a> {
+    fn drop(&mut self) {
+        forget_last_next(zero_copy_input_stream::Sealed::upcast(self));
+        unsafe { ffi::DeleteLimitingInputStream(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl<'a> LimitingInputStream<'a> {
+    /// Creates a new `LimitingInputStream` that reads at most `limit` bytes
+    /// from `input`.
+    pub fn new(
+        input: Pin<&'a mut dyn ZeroCopyInputStream>,
+        limit: i64,
+    ) -> Pin<Box<LimitingInputStream<'a>>> {
+        let stream = unsafe { ffi::NewLimitingInputStream(input.upcast_mut_ptr(), limit) };
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    unsafe_ffi_conversions!(ffi::LimitingInputStream);
+}
+
+impl<'a> ZeroCopyInputStream for LimitingInputStream<'a> {}
+
+impl<'a> zero_copy_input_stream::Sealed for LimitingInputStream<'a> {
+    fn upcast(&self) -> &ffi::ZeroCopyInputStream {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::ZeroCopyInputStream> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// A [`ZeroCopyInputStream`] which reads from several other streams in
+/// sequence.
+///
+/// This is useful for presenting a message that is split across several
+/// buffers to the parser as a single logical stream. [`byte_count`] reports
+/// the total number of bytes read across all of the streams.
+///
+/// [`byte_count`]: ZeroCopyInputStream::byte_count
+pub struct ConcatenatingInputStream<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> Drop for ConcatenatingInputStream<'a> {
+    fn drop(&mut self) {
+        forget_last_next(zero_copy_input_stream::Sealed::upcast(self));
+        unsafe { ffi::DeleteConcatenatingInputStream(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl<'a> ConcatenatingInputStream<'a> {
+    /// Creates a new `ConcatenatingInputStream` that reads from each of the
+    /// given streams in turn.
+    pub fn new<I>(streams: I) -> Pin<Box<ConcatenatingInputStream<'a>>>
+    where
+        I: IntoIterator<Item = Pin<&'a mut dyn ZeroCopyInputStream>>,
+    {
+        let streams: Vec<_> = streams
+            .into_iter()
+            .map(|stream| ffi::ZeroCopyInputStreamPtr {
+                ptr: unsafe { stream.upcast_mut_ptr() },
+            })
+            .collect();
+        let stream = unsafe { ffi::NewConcatenatingInputStream(&streams) };
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    unsafe_ffi_conversions!(ffi::ConcatenatingInputStream);
+}
+
+impl<'a> ZeroCopyInputStream for ConcatenatingInputStream<'a> {}
+
+impl<'a> zero_copy_input_stream::Sealed for ConcatenatingInputStream<'a> {
+    fn upcast(&self) -> &ffi::ZeroCopyInputStream {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::ZeroCopyInputStream> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// The compression format used by a [`GzipInputStream`] or
+/// [`GzipOutputStream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GzipFormat {
+    /// The gzip format, which includes extra header data for file attributes.
+    Gzip,
+    /// The simpler zlib stream format.
+    Zlib,
+}
+
+impl GzipFormat {
+    fn to_ffi(self) -> CInt {
+        match self {
+            GzipFormat::Gzip => CInt(1),
+            GzipFormat::Zlib => CInt(2),
+        }
+    }
+}
+
+/// A [`ZeroCopyInputStream`] that decompresses data read from another
+/// `ZeroCopyInputStream` using zlib.
+pub struct GzipInputStream<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> Drop for GzipInputStream<'a> {
+    fn drop(&mut self) {
+        forget_last_next(zero_copy_input_stream::Sealed::upcast(self));
+        unsafe { ffi::DeleteGzipInputStream(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl<'a> GzipInputStream<'a> {
+    /// Creates a new `GzipInputStream` that reads compressed data from
+    /// `input`.
+    ///
+    /// Whether the data is in the gzip or zlib format is detected
+    /// automatically.
+    pub fn new(input: Pin<&'a mut dyn ZeroCopyInputStream>) -> Pin<Box<GzipInputStream<'a>>> {
+        let stream = unsafe { ffi::NewGzipInputStream(input.upcast_mut_ptr(), CInt(0)) };
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    /// Creates a new `GzipInputStream` that reads data compressed in the
+    /// specified format from `input`.
+    pub fn with_format(
+        input: Pin<&'a mut dyn ZeroCopyInputStream>,
+        format: GzipFormat,
+    ) -> Pin<Box<GzipInputStream<'a>>> {
+        let stream = unsafe { ffi::NewGzipInputStream(input.upcast_mut_ptr(), format.to_ffi()) };
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    unsafe_ffi_conversions!(ffi::GzipInputStream);
+}
+
+impl<'a> ZeroCopyInputStream for GzipInputStream<'a> {}
+
+impl<'a> zero_copy_input_stream::Sealed for GzipInputStream<'a> {
+    fn upcast(&self) -> &ffi::ZeroCopyInputStream {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::ZeroCopyInputStream> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
 /// Abstract interface similar to an output stream but designed to minimize
 /// copying.
 ///
@@ -473,6 +1063,29 @@ pub trait ZeroCopyOutputStream: zero_copy_output_stream::Sealed {
         self.upcast_mut().BackUp(count)
     }
 
+    /// Like [`back_up`], but returns an error rather than terminating the
+    /// process if `count` is invalid.
+    ///
+    /// Unlike [`ZeroCopyInputStream::try_back_up`], this method does not track
+    /// the size of the buffer returned by the last call to [`next`]. `count`
+    /// is only checked against the total number of bytes that have passed
+    /// through the stream, as reported by [`byte_count`], so a `count` larger
+    /// than the last buffer is detected only if the stream itself validates
+    /// it. Any violations detected by the stream itself are logged by
+    /// `libprotobuf` at [`LogLevel::Fatal`], but are reported as an error
+    /// instead of terminating the process.
+    ///
+    /// [`back_up`]: ZeroCopyOutputStream::back_up
+    /// [`next`]: ZeroCopyOutputStream::next
+    /// [`byte_count`]: ZeroCopyOutputStream::byte_count
+    /// [`LogLevel::Fatal`]: crate::log::LogLevel::Fatal
+    fn try_back_up(self: Pin<&mut Self>, count: usize) -> Result<(), OperationFailedError> {
+        let count = check_back_up_count(count, self.byte_count())?;
+        self.upcast_mut()
+            .TryBackUp(count)
+            .map_err(|e| OperationFailedError::with_message(e.what()))
+    }
+
     /// Returns the total number of bytes written since this object was created.
     fn byte_count(&self) -> i64 {
         self.upcast().ByteCount()
@@ -502,7 +1115,27 @@ pub struct WriterStream<'a> {
 impl<'a> WriterStream<'a> {
     /// Creates a writer stream from the specified [`Write`] implementor.
     pub fn new(writer: &'a mut dyn Write) -> Pin<Box<WriterStream<'a>>> {
-        let stream = ffi::NewWriterStream(Box::new(WriteAdaptor(writer)));
+        let stream = ffi::NewWriterStream(Box::new(WriteAdaptor(writer)), CInt(-1));
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    /// Creates a writer stream from the specified [`Write`] implementor that
+    /// writes to `writer` in blocks of `block_size` bytes.
+    ///
+    /// Each call to [`next`] returns at most one block. Larger blocks reduce
+    /// the number of calls required to produce large outputs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero.
+    ///
+    /// [`next`]: ZeroCopyOutputStream::next
+    pub fn with_block_size(
+        writer: &'a mut dyn Write,
+        block_size: usize,
+    ) -> Pin<Box<WriterStream<'a>>> {
+        let block_size = check_block_size(block_size);
+        let stream = ffi::NewWriterStream(Box::new(WriteAdaptor(writer)), block_size);
         unsafe { Self::from_ffi_owned(stream) }
     }
 
@@ -531,6 +1164,32 @@ impl<'a> zero_copy_output_stream::Sealed for WriterStream<'a> {
 ///
 /// Using this type is more efficient than using a [`WriterStream`] when the
 /// underlying writer is a type that exposes a simple mutable byte slice.
+///
+/// The stream does not track which prefix of the slice holds valid data. Use
+/// [`byte_count`] to determine how many bytes were written, taking into account
+/// any calls to [`back_up`].
+///
+/// # Examples
+///
+/// ```
+/// use protobuf_native::io::{SliceOutputStream, ZeroCopyOutputStream};
+///
+/// let mut buffer = [0; 64];
+/// let mut output = SliceOutputStream::new(&mut buffer);
+/// let buf = unsafe { output.as_mut().next() }?;
+/// for (dst, src) in buf.iter_mut().zip(b"hello") {
+///     dst.write(*src);
+/// }
+/// let unused = buf.len() - 5;
+/// output.as_mut().back_up(unused);
+/// let written = usize::try_from(output.byte_count())?;
+/// drop(output);
+/// assert_eq!(&buffer[..written], b"hello");
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`byte_count`]: ZeroCopyOutputStream::byte_count
+/// [`back_up`]: ZeroCopyOutputStream::back_up
 pub struct SliceOutputStream<'a> {
     _opaque: PhantomPinned,
     _lifetime: PhantomData<&'a ()>,
@@ -538,9 +1197,57 @@ pub struct SliceOutputStream<'a> {
 
 impl<'a> SliceOutputStream<'a> {
     /// Creates a new `SliceOutputStream` from the provided byte slice.
-    pub fn new(slice: &mut [u8]) -> Pin<Box<SliceOutputStream<'a>>> {
+    pub fn new(slice: &'a mut [u8]) -> Pin<Box<SliceOutputStream<'a>>> {
+        let size = CInt::expect_from(slice.len());
+        let stream = unsafe { ffi::NewArrayOutputStream(slice.as_mut_ptr(), size, CInt(-1)) };
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    /// Creates a new `SliceOutputStream` from the provided byte slice that
+    /// returns the slice in blocks of `block_size` bytes.
+    ///
+    /// By default, the first call to [`next`] returns the entire slice.
+    ///
+    /// The stream borrows the slice for its entire lifetime, so it cannot
+    /// outlive the slice:
+    ///
+    /// ```compile_fail,E0597
+    /// use protobuf_native::io::{SliceOutputStream, ZeroCopyOutputStream};
+    ///
+    /// let mut output = {
+    ///     let mut buffer = [0; 64];
+    ///     SliceOutputStream::with_block_size(&mut buffer, 16)
+    /// };
+    /// let _ = unsafe { output.as_mut().next() };
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is zero.
+    ///
+    /// [`next`]: ZeroCopyOutputStream::next
+    pub fn with_block_size(
+        slice: &'a mut [u8],
+        block_size: usize,
+    ) -> Pin<Box<SliceOutputStream<'a>>> {
         let size = CInt::expect_from(slice.len());
-        let stream = unsafe { ffi::NewArrayOutputStream(slice.as_mut_ptr(), size) };
+        let block_size = check_block_size(block_size);
+        let stream = unsafe { ffi::NewArrayOutputStream(slice.as_mut_ptr(), size, block_size) };
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    /// Creates a new `SliceOutputStream` from the provided possibly
+    /// uninitialized byte slice.
+    ///
+    /// This avoids the cost of initializing a large buffer only to have it
+    /// immediately overwritten. The first [`byte_count`] bytes of the slice
+    /// are initialized once the stream is dropped.
+    ///
+    /// [`byte_count`]: ZeroCopyOutputStream::byte_count
+    pub fn new_uninit(slice: &'a mut [MaybeUninit<u8>]) -> Pin<Box<SliceOutputStream<'a>>> {
+        let size = CInt::expect_from(slice.len());
+        let stream =
+            unsafe { ffi::NewArrayOutputStream(slice.as_mut_ptr().cast(), size, CInt(-1)) };
         unsafe { Self::from_ffi_owned(stream) }
     }
 
@@ -576,11 +1283,24 @@ pub struct VecOutputStream<'a> {
 
 impl<'a> VecOutputStream<'a> {
     /// Creates a new `VecOutputStream` from the provided byte vector.
-    pub fn new(vec: &mut Vec<u8>) -> Pin<Box<VecOutputStream<'a>>> {
+    pub fn new(vec: &'a mut Vec<u8>) -> Pin<Box<VecOutputStream<'a>>> {
         let stream = ffi::NewVecOutputStream(vec);
         unsafe { Self::from_ffi_owned(stream) }
     }
 
+    /// Creates a new `VecOutputStream` from the provided byte vector, after
+    /// reserving capacity for at least `capacity` more bytes.
+    ///
+    /// When the size of the output is known in advance, e.g. via
+    /// [`MessageLite::byte_size`], reserving that capacity up front avoids
+    /// repeatedly growing the vector as output is written.
+    ///
+    /// [`MessageLite::byte_size`]: crate::MessageLite::byte_size
+    pub fn with_capacity(vec: &'a mut Vec<u8>, capacity: usize) -> Pin<Box<VecOutputStream<'a>>> {
+        vec.reserve(capacity);
+        Self::new(vec)
+    }
+
     unsafe_ffi_conversions!(ffi::VecOutputStream);
 }
 
@@ -602,41 +1322,371 @@ impl<'a> zero_copy_output_stream::Sealed for VecOutputStream<'a> {
     }
 }
 
-/// Type which reads and decodes binary data which is composed of varint-
-/// encoded integers and fixed-width pieces.
+/// A [`ZeroCopyOutputStream`] that writes to a [`bytes::BytesMut`].
 ///
-/// Wraps a [`ZeroCopyInputStream`]. Most users will not need to deal with
-/// `CodedInputStream`.
+/// The stream exposes the spare capacity of the buffer directly, reserving
+/// more capacity as necessary, so output is written into the buffer without
+/// an intermediate copy. Bytes are appended to any existing contents of the
+/// buffer.
 ///
-/// Most methods of `CodedInputStream` that return a `Result` return an error if
-/// an underlying I/O error occurs or if the data is malformed. Once such a
-/// failure occurs, the `CodedInputStream` is broken and is no longer useful.
-pub struct CodedInputStream<'a> {
+/// This type is only available if the `bytes` feature is enabled.
+#[cfg(feature = "bytes")]
+pub struct BufMutOutputStream<'a> {
     _opaque: PhantomPinned,
     _lifetime: PhantomData<&'a ()>,
 }
 
-impl<'a> Drop for CodedInputStream<'a> {
+#[cfg(feature = "bytes")]
+impl<'a> Drop for BufMutOutputStream<'a> {
     fn drop(&mut self) {
-        unsafe { ffi::DeleteCodedInputStream(self.as_ffi_mut_ptr_unpinned()) }
+        unsafe { ffi::DeleteRustOutputStream(self.as_ffi_mut_ptr_unpinned()) }
     }
 }
 
-impl<'a> CodedInputStream<'a> {
-    /// Creates a `CodedInputStream` that reads from the given
-    /// [`ZeroCopyInputStream`].
-    pub fn new(input: Pin<&'a mut dyn ZeroCopyInputStream>) -> Pin<Box<CodedInputStream<'a>>> {
-        let stream = unsafe { ffi::NewCodedInputStream(input.upcast_mut_ptr()) };
+#[cfg(feature = "bytes")]
+impl<'a> BufMutOutputStream<'a> {
+    /// Creates a new `BufMutOutputStream` that appends to the provided
+    /// buffer.
+    pub fn new(buf: &'a mut BytesMut) -> Pin<Box<BufMutOutputStream<'a>>> {
+        let sink = BytesMutSink {
+            buf,
+            pending: 0,
+            byte_count: 0,
+        };
+        let stream = ffi::NewRustOutputStream(Box::new(OutputStreamAdaptor(Box::new(sink))));
         unsafe { Self::from_ffi_owned(stream) }
     }
 
-    /// Reports whether this coded input stream reads from a flat array instead
-    /// of a [`ZeroCopyInputStream`].
-    pub fn is_flat(&self) -> bool {
-        self.as_ffi().IsFlat()
-    }
+    unsafe_ffi_conversions!(ffi::RustOutputStream);
+}
 
-    /// Reads an unsigned integer with varint encoding, truncating to 32 bits.
+#[cfg(feature = "bytes")]
+impl<'a> ZeroCopyOutputStream for BufMutOutputStream<'a> {}
+
+#[cfg(feature = "bytes")]
+impl<'a> zero_copy_output_stream::Sealed for BufMutOutputStream<'a> {
+    fn upcast(&self) -> &ffi::ZeroCopyOutputStream {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::ZeroCopyOutputStream> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+#[cfg(feature = "bytes")]
+struct BytesMutSink<'a> {
+    buf: &'a mut BytesMut,
+    /// The number of bytes of spare capacity returned by the last call to
+    /// `next` that are assumed to have been written but have not yet been
+    /// added to the buffer's length.
+    pending: usize,
+    byte_count: i64,
+}
+
+#[cfg(feature = "bytes")]
+impl BytesMutSink<'_> {
+    fn commit(&mut self) {
+        // SAFETY: the `ZeroCopyOutputStream` contract requires the caller to
+        // initialize every byte of the last buffer returned by `next` that it
+        // did not return via `back_up`.
+        unsafe { self.buf.advance_mut(self.pending) };
+        self.pending = 0;
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl ZeroCopyOutputSink for BytesMutSink<'_> {
+    fn next(&mut self) -> &mut [MaybeUninit<u8>] {
+        const MINIMUM_SIZE: usize = 16;
+
+        self.commit();
+        if self.buf.capacity() == self.buf.len() {
+            self.buf.reserve(self.buf.len().max(MINIMUM_SIZE));
+        }
+        let spare = self.buf.spare_capacity_mut();
+        let len = spare.len().min(c_int::MAX as usize);
+        self.pending = len;
+        self.byte_count += len as i64;
+        &mut spare[..len]
+    }
+
+    fn back_up(&mut self, count: usize) -> Result<(), &'static str> {
+        if count > self.pending {
+            return Err("cannot back up past the last buffer");
+        }
+        self.pending -= count;
+        self.byte_count -= count as i64;
+        Ok(())
+    }
+
+    fn byte_count(&self) -> i64 {
+        self.byte_count
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Drop for BytesMutSink<'_> {
+    fn drop(&mut self) {
+        self.commit();
+    }
+}
+
+/// Options for a [`GzipOutputStream`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GzipOptions {
+    /// The compression format. Defaults to [`GzipFormat::Gzip`].
+    pub format: GzipFormat,
+    /// The size of the internal buffer, in bytes. Defaults to 64KiB.
+    pub buffer_size: Option<usize>,
+    /// A number between 0 and 9, where 0 is no compression and 9 is best
+    /// compression. Defaults to zlib's default compression level.
+    pub compression_level: Option<u32>,
+}
+
+impl Default for GzipOptions {
+    fn default() -> GzipOptions {
+        GzipOptions {
+            format: GzipFormat::Gzip,
+            buffer_size: None,
+            compression_level: None,
+        }
+    }
+}
+
+/// A [`ZeroCopyOutputStream`] that compresses data using zlib and writes it
+/// to another `ZeroCopyOutputStream`.
+///
+/// The compressed stream is finished when the `GzipOutputStream` is dropped.
+/// Use [`GzipOutputStream::close`] to finish the stream explicitly and observe
+/// any errors.
+pub struct GzipOutputStream<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> GzipOutputStream<'a> {
+    /// Creates a new `GzipOutputStream` that writes compressed data to
+    /// `output` using the default options.
+    pub fn new(output: Pin<&'a mut dyn ZeroCopyOutputStream>) -> Pin<Box<GzipOutputStream<'a>>> {
+        GzipOutputStream::with_options(output, &GzipOptions::default())
+            .expect("default gzip options are valid")
+    }
+
+    /// Creates a new `GzipOutputStream` that writes compressed data to
+    /// `output` using the specified options.
+    ///
+    /// Returns an error if the compression level is greater than 9 or if the
+    /// buffer size does not fit in a C `int`.
+    pub fn with_options(
+        output: Pin<&'a mut dyn ZeroCopyOutputStream>,
+        options: &GzipOptions,
+    ) -> Result<Pin<Box<GzipOutputStream<'a>>>, OperationFailedError> {
+        let buffer_size = match options.buffer_size {
+            None => CInt(-1),
+            Some(buffer_size) => CInt::try_from(buffer_size).map_err(|_| {
+                OperationFailedError::with_message(format!(
+                    "gzip buffer size {} is too large",
+                    buffer_size
+                ))
+            })?,
+        };
+        let compression_level = match options.compression_level {
+            None => CInt(-1),
+            Some(level @ 0..=9) => CInt::expect_from(level),
+            Some(level) => {
+                return Err(OperationFailedError::with_message(format!(
+                    "gzip compression level {} is not between 0 and 9",
+                    level
+                )))
+            }
+        };
+        let stream = unsafe {
+            ffi::NewGzipOutputStream(
+                output.upcast_mut_ptr(),
+                options.format.to_ffi(),
+                buffer_size,
+                compression_level,
+            )
+        };
+        Ok(unsafe { Self::from_ffi_owned(stream) })
+    }
+
+    /// Flushes data written so far to zipped data in the underlying stream.
+    ///
+    /// It is the caller's responsibility to flush the underlying stream if
+    /// necessary. Compression may be less efficient stopping and starting
+    /// around flushes.
+    pub fn flush(self: Pin<&mut Self>) -> Result<(), OperationFailedError> {
+        self.as_ffi_mut().Flush().as_result()
+    }
+
+    /// Writes out all data and closes the gzip stream.
+    ///
+    /// It is the caller's responsibility to close the underlying stream if
+    /// necessary.
+    pub fn close(self: Pin<&mut Self>) -> Result<(), OperationFailedError> {
+        self.as_ffi_mut().Close().as_result()
+    }
+
+    unsafe_ffi_conversions!(ffi::GzipOutputStream);
+}
+
+impl<'a> Drop for GzipOutputStream<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteGzipOutputStream(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl<'a> ZeroCopyOutputStream for GzipOutputStream<'a> {}
+
+impl<'a> zero_copy_output_stream::Sealed for GzipOutputStream<'a> {
+    fn upcast(&self) -> &ffi::ZeroCopyOutputStream {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::ZeroCopyOutputStream> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// Type which reads and decodes binary data which is composed of varint-
+/// encoded integers and fixed-width pieces.
+///
+/// Wraps a [`ZeroCopyInputStream`]. Most users will not need to deal with
+/// `CodedInputStream`.
+///
+/// Most methods of `CodedInputStream` that return a `Result` return an error if
+/// an underlying I/O error occurs or if the data is malformed. Once such a
+/// failure occurs, the `CodedInputStream` is broken and is no longer useful.
+pub struct CodedInputStream<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> Drop for CodedInputStream<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteCodedInputStream(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl<'a> CodedInputStream<'a> {
+    /// Creates a `CodedInputStream` that reads from the given
+    /// [`ZeroCopyInputStream`].
+    pub fn new(input: Pin<&'a mut dyn ZeroCopyInputStream>) -> Pin<Box<CodedInputStream<'a>>> {
+        let stream = unsafe { ffi::NewCodedInputStream(input.upcast_mut_ptr()) };
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    /// Creates a `CodedInputStream` that reads directly from the given byte
+    /// slice.
+    ///
+    /// Reading from a flat array is faster than reading from a
+    /// [`ZeroCopyInputStream`] like [`SliceInputStream`], as the stream need
+    /// not check for the end of each buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice is larger than `i32::MAX` bytes.
+    pub fn from_slice(data: &'a [u8]) -> Pin<Box<CodedInputStream<'a>>> {
+        assert!(
+            CInt::try_from(data.len()).is_ok(),
+            "slice is too large for a CodedInputStream: {} bytes",
+            data.len()
+        );
+        let stream = ffi::NewCodedInputStreamFromArray(data);
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    /// Reports whether this coded input stream reads from a flat array instead
+    /// of a [`ZeroCopyInputStream`].
+    pub fn is_flat(&self) -> bool {
+        self.as_ffi().IsFlat()
+    }
+
+    /// Reads exactly `len` raw bytes.
+    ///
+    /// Returns an error if the stream ends or a limit is reached before `len`
+    /// bytes could be read, in which case the bytes that were available have
+    /// been consumed. This differs from the [`Read`] implementation for
+    /// `CodedInputStream`, which, like any `Read` implementation, may read
+    /// fewer bytes than requested.
+    ///
+    /// A buffer of `len` bytes is allocated up front. When `len` comes from
+    /// untrusted input, prefer [`read_string`], which checks `len` against the
+    /// stream's limits before allocating.
+    ///
+    /// [`read_string`]: CodedInputStream::read_string
+    pub fn read_raw(self: Pin<&mut Self>, len: usize) -> Result<Vec<u8>, OperationFailedError> {
+        let size = CInt::try_from(len).map_err(|_| OperationFailedError::new())?;
+        let mut buf = vec![0; len];
+        // SAFETY: `buf` is valid for writes of `len` bytes.
+        unsafe {
+            self.as_ffi_mut()
+                .ReadRaw(buf.as_mut_ptr() as *mut CVoid, size)
+                .as_result()?;
+        }
+        Ok(buf)
+    }
+
+    /// Reads a string of exactly `size` bytes.
+    ///
+    /// Returns an error if the stream ends or a limit is reached before `size`
+    /// bytes could be read. The underlying library checks the requested size
+    /// against the active limits before allocating, so a corrupt size cannot
+    /// trigger an enormous allocation.
+    pub fn read_string(self: Pin<&mut Self>, size: usize) -> Result<Vec<u8>, OperationFailedError> {
+        let size = CInt::try_from(size).map_err(|_| OperationFailedError::new())?;
+        let_cxx_string!(buffer = "");
+        // SAFETY: `buffer` is a valid, pinned C++ string that outlives the
+        // call.
+        unsafe {
+            self.as_ffi_mut()
+                .ReadString(buffer.as_mut().get_unchecked_mut(), size)
+                .as_result()?;
+        }
+        Ok(buffer.as_bytes().to_vec())
+    }
+
+    /// Reads a length-delimited byte string.
+    ///
+    /// This reads a varint-encoded length, then reads that many bytes using
+    /// [`read_string`], as is done for `string` and `bytes` fields.
+    ///
+    /// [`read_string`]: CodedInputStream::read_string
+    pub fn read_bytes(mut self: Pin<&mut Self>) -> Result<Vec<u8>, OperationFailedError> {
+        let size = self.as_mut().read_varint32()?;
+        let size = usize::try_from(size).map_err(|_| OperationFailedError::new())?;
+        self.read_string(size)
+    }
+
+    /// Reads a 32-bit little-endian integer.
+    pub fn read_little_endian32(self: Pin<&mut Self>) -> Result<u32, OperationFailedError> {
+        let mut value = MaybeUninit::uninit();
+        // SAFETY: `ReadLittleEndian32` promises to initialize `value` if it
+        // returns true.
+        unsafe {
+            match self.as_ffi_mut().ReadLittleEndian32(value.as_mut_ptr()) {
+                true => Ok(value.assume_init()),
+                false => Err(OperationFailedError::new()),
+            }
+        }
+    }
+
+    /// Reads a 64-bit little-endian integer.
+    pub fn read_little_endian64(self: Pin<&mut Self>) -> Result<u64, OperationFailedError> {
+        let mut value = MaybeUninit::uninit();
+        // SAFETY: `ReadLittleEndian64` promises to initialize `value` if it
+        // returns true.
+        unsafe {
+            match self.as_ffi_mut().ReadLittleEndian64(value.as_mut_ptr()) {
+                true => Ok(value.assume_init()),
+                false => Err(OperationFailedError::new()),
+            }
+        }
+    }
+
+    /// Reads an unsigned integer with varint encoding, truncating to 32 bits.
     ///
     /// Reading a 32-bit value is equivalent to reading a 64-bit one and casting
     /// it to `u32`, but may be more efficient.
@@ -647,7 +1697,7 @@ impl<'a> CodedInputStream<'a> {
         unsafe {
             match self.as_ffi_mut().ReadVarint32(value.as_mut_ptr()) {
                 true => Ok(value.assume_init()),
-                false => Err(OperationFailedError),
+                false => Err(OperationFailedError::new()),
             }
         }
     }
@@ -660,7 +1710,7 @@ impl<'a> CodedInputStream<'a> {
         unsafe {
             match self.as_ffi_mut().ReadVarint64(value.as_mut_ptr()) {
                 true => Ok(value.assume_init()),
-                false => Err(OperationFailedError),
+                false => Err(OperationFailedError::new()),
             }
         }
     }
@@ -674,7 +1724,7 @@ impl<'a> CodedInputStream<'a> {
     /// [`last_tag_was`]: CodedInputStream::last_tag_was
     pub fn read_tag(self: Pin<&mut Self>) -> Result<u32, OperationFailedError> {
         match self.as_ffi_mut().ReadTag() {
-            0 => Err(OperationFailedError), // 0 is error sentinel
+            0 => Err(OperationFailedError::new()), // 0 is error sentinel
             tag => Ok(tag),
         }
     }
@@ -685,7 +1735,7 @@ impl<'a> CodedInputStream<'a> {
     /// [`read_tag`]: `CodedInputStream::read_tag`
     pub fn read_tag_no_last_tag(self: Pin<&mut Self>) -> Result<u32, OperationFailedError> {
         match self.as_ffi_mut().ReadTag() {
-            0 => Err(OperationFailedError), // 0 is error sentinel
+            0 => Err(OperationFailedError::new()), // 0 is error sentinel
             tag => Ok(tag),
         }
     }
@@ -725,6 +1775,15 @@ impl<'a> CodedInputStream<'a> {
         self.as_ffi_mut().ConsumedEntireMessage()
     }
 
+    /// Skips `count` bytes.
+    ///
+    /// Returns an error if the end of the stream or a limit is reached before
+    /// `count` bytes could be skipped.
+    pub fn skip(self: Pin<&mut Self>, count: usize) -> Result<(), OperationFailedError> {
+        let count = CInt::try_from(count).map_err(|_| OperationFailedError::new())?;
+        self.as_ffi_mut().Skip(count).as_result()
+    }
+
     /// Returns the stream's current position relative to the beginning of the
     /// input.
     pub fn current_position(&self) -> usize {
@@ -734,10 +1793,216 @@ impl<'a> CodedInputStream<'a> {
             .expect("stream position not representable as usize")
     }
 
+    /// Places a limit on the number of bytes that the stream may read,
+    /// starting from the current position.
+    ///
+    /// Once the stream hits this limit, it will act like the end of the input
+    /// has been reached until [`pop_limit`] is called.
+    ///
+    /// As the names imply, the stream conceptually has a stack of limits. The
+    /// shortest limit on the stack is always enforced, even if it is not the
+    /// top limit.
+    ///
+    /// The value returned by `push_limit` is opaque to the caller, and must be
+    /// passed unchanged to the corresponding call to [`pop_limit`].
+    ///
+    /// [`pop_limit`]: CodedInputStream::pop_limit
+    pub fn push_limit(self: Pin<&mut Self>, byte_limit: usize) -> Limit {
+        Limit(self.as_ffi_mut().PushLimit(CInt::expect_from(byte_limit)))
+    }
+
+    /// Pops the last limit pushed by [`push_limit`].
+    ///
+    /// The input must be the value returned by that call to `push_limit`.
+    ///
+    /// [`push_limit`]: CodedInputStream::push_limit
+    pub fn pop_limit(self: Pin<&mut Self>, limit: Limit) {
+        self.as_ffi_mut().PopLimit(limit.0)
+    }
+
+    /// Returns the number of bytes left until the nearest limit on the stack
+    /// is hit, or `None` if no limits are in place.
+    pub fn bytes_until_limit(&self) -> Option<usize> {
+        self.as_ffi().BytesUntilLimit().to_usize().ok()
+    }
+
+    /// Returns the number of bytes left until the total bytes limit is hit, or
+    /// `None` if no total bytes limit is in place.
+    pub fn bytes_until_total_bytes_limit(&self) -> Option<usize> {
+        self.as_ffi().BytesUntilTotalBytesLimit().to_usize().ok()
+    }
+
+    /// Sets the maximum number of bytes that this `CodedInputStream` will read
+    /// before refusing to continue.
+    ///
+    /// To prevent servers from allocating enormous amounts of memory to hold
+    /// parsed messages, the maximum message length should be limited to the
+    /// shortest length that will not harm usability. The default limit is
+    /// `i32::MAX` (~2GB) and apps should set shorter limits if possible.
+    ///
+    /// Once a limit is set, [`bytes_until_total_bytes_limit`] reports the
+    /// number of bytes remaining before it is reached. Setting a limit less
+    /// than the current read position is interpreted as a limit on the current
+    /// position.
+    ///
+    /// This is unrelated to [`push_limit`] and [`pop_limit`].
+    ///
+    /// [`bytes_until_total_bytes_limit`]: CodedInputStream::bytes_until_total_bytes_limit
+    /// [`push_limit`]: CodedInputStream::push_limit
+    /// [`pop_limit`]: CodedInputStream::pop_limit
+    pub fn set_total_bytes_limit(self: Pin<&mut Self>, total_bytes_limit: usize) {
+        self.as_ffi_mut()
+            .SetTotalBytesLimit(CInt::expect_from(total_bytes_limit))
+    }
+
+    /// Sets the maximum recursion depth.
+    ///
+    /// To prevent corrupt or malicious messages from causing stack overflows,
+    /// parsing fails when embedded messages and groups are nested more deeply
+    /// than this limit. The default is 100.
+    pub fn set_recursion_limit(self: Pin<&mut Self>, limit: usize) {
+        self.as_ffi_mut()
+            .SetRecursionLimit(CInt::expect_from(limit))
+    }
+
     unsafe_ffi_conversions!(ffi::CodedInputStream);
 }
 
+/// Decodes a 32-bit ZigZag-encoded integer, as used by `sint32` fields.
+///
+/// ZigZag encoding maps signed integers to unsigned integers so that numbers
+/// with a small absolute value (for instance, -1) have a small varint encoded
+/// value too.
+pub fn decode_zigzag32(n: u32) -> i32 {
+    ((n >> 1) as i32) ^ -((n & 1) as i32)
+}
+
+/// Decodes a 64-bit ZigZag-encoded integer, as used by `sint64` fields.
+///
+/// See [`decode_zigzag32`] for details.
+pub fn decode_zigzag64(n: u64) -> i64 {
+    ((n >> 1) as i64) ^ -((n & 1) as i64)
+}
+
+/// Reads a length-delimited message from an asynchronous reader and parses it
+/// into `message`, replacing the current contents of the message.
+///
+/// The message must be framed by a varint-encoded length prefix, as written by
+/// [`MessageLite::serialize_delimited_to_writer`] or Java's `writeDelimitedTo`.
+/// Because libprotobuf parses synchronously, the entire frame is read into
+/// memory before parsing begins. The buffer grows as the frame's bytes arrive,
+/// so a length prefix that overstates the size of the frame does not cause a
+/// large allocation. Frames longer than `i32::MAX` bytes are rejected.
+///
+/// Returns `Ok(false)` if the reader is at end of file before the first byte
+/// of the frame, and `Ok(true)` if a message was read. A truncated frame
+/// produces an error of kind [`UnexpectedEof`](io::ErrorKind::UnexpectedEof),
+/// and a frame that does not contain a valid message produces an error of
+/// kind [`InvalidData`](io::ErrorKind::InvalidData).
+///
+/// This function is only available if the `tokio` feature is enabled.
+#[cfg(feature = "tokio")]
+pub async fn parse_delimited_from_async_read<R, M>(
+    reader: &mut R,
+    message: Pin<&mut M>,
+) -> Result<bool, io::Error>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    M: MessageLite + ?Sized,
+{
+    let mut prefix = LengthPrefixDecoder::default();
+    let len = loop {
+        let byte = match reader.read_u8().await {
+            Ok(byte) => byte,
+            Err(e) if prefix.is_empty() && e.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(false)
+            }
+            Err(e) => return Err(e),
+        };
+        if let Some(len) = prefix.push(byte)? {
+            break len;
+        }
+    };
+    // The length is untrusted, so let the buffer grow as bytes arrive rather
+    // than allocating it up front.
+    let mut buf = vec![];
+    reader.take(len as u64).read_to_end(&mut buf).await?;
+    if buf.len() != len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "message truncated",
+        ));
+    }
+    message
+        .parse_from_bytes(&buf)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(true)
+}
+
+/// Decodes the varint32 length prefix of a length-delimited message one byte
+/// at a time.
+#[derive(Default)]
+pub(crate) struct LengthPrefixDecoder {
+    len: u64,
+    count: u32,
+}
+
+impl LengthPrefixDecoder {
+    /// Reports whether no bytes have been pushed yet.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Pushes the next byte of the length prefix.
+    ///
+    /// Returns the length of the frame once the final byte of the prefix has
+    /// been pushed, or an error if the prefix is malformed or the frame is
+    /// too large to parse.
+    pub(crate) fn push(&mut self, byte: u8) -> Result<Option<usize>, io::Error> {
+        self.len |= u64::from(byte & 0x7f) << (7 * self.count);
+        self.count += 1;
+        if byte & 0x80 != 0 {
+            if self.count == 5 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "malformed varint in length prefix",
+                ));
+            }
+            return Ok(None);
+        }
+        match usize::try_from(self.len) {
+            Ok(len) if CInt::try_from(len).is_ok() => Ok(Some(len)),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "frame is too large",
+            )),
+        }
+    }
+}
+
+/// An opaque token representing a limit pushed onto a [`CodedInputStream`].
+///
+/// See [`CodedInputStream::push_limit`].
+#[derive(Debug)]
+#[must_use = "limits must be passed to `CodedInputStream::pop_limit`"]
+pub struct Limit(CInt);
+
 impl<'a> Read for Pin<&mut CodedInputStream<'a>> {
+    /// Reads up to `buf.len()` bytes from the stream.
+    ///
+    /// Fewer bytes than requested are read only if the end of the stream or a
+    /// limit is reached. Reaching the end of the stream or a limit pushed via
+    /// [`push_limit`] is reported as end of file. If the underlying stream
+    /// has more data beyond the total bytes limit, reaching that limit is
+    /// instead reported as an error of kind
+    /// [`InvalidData`](io::ErrorKind::InvalidData), so that the input is not
+    /// silently truncated. Input that ends exactly at the total bytes limit
+    /// is reported as end of file.
+    ///
+    /// Errors in the underlying [`ZeroCopyInputStream`] are indistinguishable
+    /// from the end of the stream, and so are reported as end of file.
+    ///
+    /// [`push_limit`]: CodedInputStream::push_limit
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
         let start = self.current_position();
         let data = buf.as_mut_ptr() as *mut CVoid;
@@ -747,8 +2012,18 @@ impl<'a> Read for Pin<&mut CodedInputStream<'a>> {
                 "buffer exceeds size of a C int",
             )
         })?;
-        unsafe { self.as_mut().as_ffi_mut().ReadRaw(data, size) };
+        // libprotobuf logs an error when it refuses to read data that lies
+        // beyond the total bytes limit, but not when the input ends at the
+        // limit.
+        let (_, limit_error) =
+            log::capture_errors(|| unsafe { self.as_mut().as_ffi_mut().ReadRaw(data, size) });
         let end = self.current_position();
+        if limit_error.is_some() && end == start {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "total bytes limit exceeded",
+            ));
+        }
         Ok(end - start)
     }
 }
@@ -770,6 +2045,79 @@ pub struct CodedOutputStream<'a> {
 }
 
 impl<'a> CodedOutputStream<'a> {
+    /// Creates a `CodedOutputStream` that writes to the given
+    /// [`ZeroCopyOutputStream`].
+    ///
+    /// Any buffered but unwritten data is flushed to the underlying stream when
+    /// the `CodedOutputStream` is dropped.
+    pub fn new(output: Pin<&'a mut dyn ZeroCopyOutputStream>) -> Pin<Box<CodedOutputStream<'a>>> {
+        let stream = unsafe { ffi::NewCodedOutputStream(output.upcast_mut_ptr()) };
+        unsafe { Self::from_ffi_owned(stream) }
+    }
+
+    /// Writes raw bytes, copying them from the given buffer.
+    pub fn write_raw(self: Pin<&mut Self>, buf: &[u8]) {
+        let data = buf.as_ptr() as *const CVoid;
+        let size = CInt::expect_from(buf.len());
+        unsafe { self.as_ffi_mut().WriteRaw(data, size) }
+    }
+
+    /// Writes a 32-bit little-endian integer.
+    pub fn write_little_endian32(self: Pin<&mut Self>, value: u32) {
+        self.as_ffi_mut().WriteLittleEndian32(value)
+    }
+
+    /// Writes a 64-bit little-endian integer.
+    pub fn write_little_endian64(self: Pin<&mut Self>, value: u64) {
+        self.as_ffi_mut().WriteLittleEndian64(value)
+    }
+
+    /// Writes an unsigned 32-bit integer with varint encoding.
+    pub fn write_varint32(self: Pin<&mut Self>, value: u32) {
+        self.as_ffi_mut().WriteVarint32(value)
+    }
+
+    /// Writes an unsigned 64-bit integer with varint encoding.
+    pub fn write_varint64(self: Pin<&mut Self>, value: u64) {
+        self.as_ffi_mut().WriteVarint64(value)
+    }
+
+    /// Reports whether there was an error during writing.
+    ///
+    /// Once an error occurs, the stream is broken and no further writes will
+    /// succeed.
+    pub fn had_error(self: Pin<&mut Self>) -> bool {
+        self.as_ffi_mut().HadError()
+    }
+
+    /// Returns the total number of bytes written since this stream was
+    /// created.
+    pub fn byte_count(&self) -> usize {
+        self.as_ffi().ByteCount().expect_usize()
+    }
+
+    /// Sets whether serialization through this stream is deterministic.
+    ///
+    /// Deterministic serialization, if requested, guarantees that for a given
+    /// binary, equal messages will always be serialized to the same bytes. In
+    /// particular, map fields are serialized in order of their keys.
+    ///
+    /// Determinism does not hold across builds of libprotobuf, and
+    /// deterministic serialization is not canonical: different languages may
+    /// serialize equal messages differently.
+    pub fn set_serialization_deterministic(self: Pin<&mut Self>, value: bool) {
+        self.as_ffi_mut().SetSerializationDeterministic(value)
+    }
+
+    /// Reports whether serialization through this stream is deterministic.
+    ///
+    /// See [`set_serialization_deterministic`] for details.
+    ///
+    /// [`set_serialization_deterministic`]: CodedOutputStream::set_serialization_deterministic
+    pub fn is_serialization_deterministic(&self) -> bool {
+        self.as_ffi().IsSerializationDeterministic()
+    }
+
     unsafe_ffi_conversions!(ffi::CodedOutputStream);
 }
 
@@ -778,3 +2126,264 @@ impl<'a> Drop for CodedOutputStream<'a> {
         unsafe { ffi::DeleteCodedOutputStream(self.as_ffi_mut_ptr_unpinned()) }
     }
 }
+
+impl<'a> Write for Pin<&mut CodedOutputStream<'a>> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        let start = self.byte_count();
+        let data = buf.as_ptr() as *const CVoid;
+        let size = CInt::try_from(buf.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "buffer exceeds size of a C int",
+            )
+        })?;
+        unsafe { self.as_mut().as_ffi_mut().WriteRaw(data, size) };
+        if self.as_mut().had_error() {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to write to underlying output stream",
+            ));
+        }
+        let end = self.byte_count();
+        Ok(end - start)
+    }
+
+    /// Pushes any buffered data to the underlying [`ZeroCopyOutputStream`].
+    ///
+    /// The underlying stream itself is not flushed.
+    fn flush(&mut self) -> Result<(), io::Error> {
+        self.as_mut().as_ffi_mut().Trim();
+        if self.as_mut().had_error() {
+            return Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to write to underlying output stream",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A text output stream for code generators.
+///
+/// `Printer` writes text to a [`ZeroCopyOutputStream`], substituting variables
+/// and indenting lines as it goes. Variables are delimited by the delimiter
+/// character chosen at construction, typically `$`. For example, with the
+/// variable `name` set to `"Foo"`, the text `"class $name$ {\n"` is printed as
+/// `"class Foo {\n"`. Two consecutive delimiters print a literal delimiter.
+///
+/// Indentation is applied at the start of each line following a call to
+/// [`indent`](Printer::indent); each level indents by two spaces.
+///
+/// Text is flushed to the underlying stream when the `Printer` is dropped.
+pub struct Printer<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> Printer<'a> {
+    /// Creates a `Printer` that writes to `output`, using `delimiter` to
+    /// delimit variables.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `delimiter` is not an ASCII character.
+    pub fn new(
+        output: Pin<&'a mut dyn ZeroCopyOutputStream>,
+        delimiter: char,
+    ) -> Pin<Box<Printer<'a>>> {
+        assert!(delimiter.is_ascii(), "printer delimiter must be ASCII");
+        let printer = unsafe { ffi::NewPrinter(output.upcast_mut_ptr(), delimiter as u8) };
+        unsafe { Self::from_ffi_owned(printer) }
+    }
+
+    /// Prints `text`, which must not reference any variables.
+    ///
+    /// # Panics
+    ///
+    /// Panics under the same conditions as
+    /// [`print_with_vars`](Printer::print_with_vars).
+    pub fn print(self: Pin<&mut Self>, text: &str) {
+        self.print_with_vars(text, &[])
+    }
+
+    /// Prints `text`, substituting the variables in `vars`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `text` contains a NUL byte, contains an unterminated variable
+    /// reference, or references a variable that is not present in `vars`.
+    pub fn print_with_vars(self: Pin<&mut Self>, text: &str, vars: &[(&str, &str)]) {
+        assert!(!text.contains('\0'), "printer text contains a NUL byte");
+        let delimiter = char::from(self.as_ffi().Delimiter());
+        let parts: Vec<_> = text.split(delimiter).collect();
+        assert!(
+            parts.len() % 2 == 1,
+            "unterminated variable reference in printer text: {:?}",
+            text
+        );
+        for name in parts.iter().skip(1).step_by(2) {
+            assert!(
+                name.is_empty() || vars.iter().any(|(n, _)| n == name),
+                "undefined variable in printer text: {:?}",
+                name
+            );
+        }
+        let (names, values) = vars
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .unzip();
+        let_cxx_string!(text = text);
+        self.as_ffi_mut().Print(&text, &names, &values)
+    }
+
+    /// Increases the indentation level by one.
+    pub fn indent(self: Pin<&mut Self>) {
+        self.as_ffi_mut().Indent()
+    }
+
+    /// Decreases the indentation level by one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no matching call to [`indent`](Printer::indent).
+    pub fn outdent(self: Pin<&mut Self>) {
+        assert!(
+            self.as_ffi_mut().Outdent(),
+            "outdent without matching indent"
+        );
+    }
+
+    /// Reports whether any write to the underlying stream has failed.
+    pub fn failed(&self) -> bool {
+        self.as_ffi().Failed()
+    }
+
+    unsafe_ffi_conversions!(ffi::Printer);
+}
+
+impl<'a> Drop for Printer<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::DeletePrinter(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+/// The type of a token produced by a [`Tokenizer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenType {
+    /// [`Tokenizer::next`] has not yet been called.
+    Start,
+    /// The end of the input has been reached. The token text is empty.
+    End,
+    /// A sequence of letters, digits, and underscores, not starting with a
+    /// digit.
+    Identifier,
+    /// A sequence of digits representing an integer. A prefix of `0x`
+    /// indicates a hexadecimal number and a leading zero indicates an octal
+    /// number. A leading negative sign is not included in the token.
+    Integer,
+    /// A floating point literal, with a fractional part and/or an exponent.
+    /// Always in decimal and never negative.
+    Float,
+    /// A quoted sequence of escaped characters, with either single or double
+    /// quotes. The token text includes the quotes.
+    String,
+    /// Any other printable character, like `!` or `+`. Symbols are always a
+    /// single character.
+    Symbol,
+}
+
+impl TokenType {
+    fn from_ffi(ty: CInt) -> TokenType {
+        match ty.0 {
+            0 => TokenType::Start,
+            1 => TokenType::End,
+            2 => TokenType::Identifier,
+            3 => TokenType::Integer,
+            4 => TokenType::Float,
+            5 => TokenType::String,
+            6 => TokenType::Symbol,
+            _ => panic!("unknown token type: {}", ty.0),
+        }
+    }
+}
+
+/// Converts a [`ZeroCopyInputStream`] of .proto source text into a stream of
+/// tokens.
+///
+/// Comments and whitespace are skipped. Errors, such as an unterminated string
+/// literal, are reported to the [`MultiFileErrorCollector`] provided at
+/// construction, attributed to the given filename, and tokenizing continues
+/// after the offending text.
+///
+/// To parse the tokens into a [`FileDescriptorProto`], pass the tokenizer to
+/// [`Parser::parse`].
+///
+/// [`FileDescriptorProto`]: crate::FileDescriptorProto
+/// [`Parser::parse`]: crate::compiler::Parser::parse
+pub struct Tokenizer<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Creates a `Tokenizer` that reads from `input`, reporting errors in
+    /// `filename` to `error_collector`.
+    pub fn new(
+        input: Pin<&'a mut dyn ZeroCopyInputStream>,
+        filename: &str,
+        error_collector: Pin<&'a mut dyn MultiFileErrorCollector>,
+    ) -> Pin<Box<Tokenizer<'a>>> {
+        let_cxx_string!(filename = filename);
+        let tokenizer = unsafe {
+            ffi::NewTokenizer(
+                input.upcast_mut_ptr(),
+                &filename,
+                error_collector.upcast_mut_ptr(),
+            )
+        };
+        unsafe { Self::from_ffi_owned(tokenizer) }
+    }
+
+    /// Advances to the next token.
+    ///
+    /// Returns `false` if the end of the input has been reached.
+    pub fn next(self: Pin<&mut Self>) -> bool {
+        self.as_ffi_mut().Next()
+    }
+
+    /// Returns the type of the current token.
+    pub fn token_type(&self) -> TokenType {
+        TokenType::from_ffi(self.as_ffi().CurrentType())
+    }
+
+    /// Returns the exact text of the current token as it appeared in the
+    /// input.
+    pub fn text(&self) -> &[u8] {
+        self.as_ffi().CurrentText().as_bytes()
+    }
+
+    /// Returns the zero-based line on which the current token starts.
+    pub fn line(&self) -> usize {
+        self.as_ffi().CurrentLine().expect_usize()
+    }
+
+    /// Returns the zero-based column at which the current token starts.
+    ///
+    /// Tabs advance the column to the next multiple of eight.
+    pub fn column(&self) -> usize {
+        self.as_ffi().CurrentColumn().expect_usize()
+    }
+
+    /// Returns the zero-based column just past the end of the current token.
+    pub fn end_column(&self) -> usize {
+        self.as_ffi().CurrentEndColumn().expect_usize()
+    }
+
+    unsafe_ffi_conversions!(ffi::Tokenizer);
+}
+
+impl<'a> Drop for Tokenizer<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteTokenizer(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
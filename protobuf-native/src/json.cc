@@ -0,0 +1,59 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License in the LICENSE file at the
+// root of this repository, or online at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#include "protobuf-native/src/json.h"
+
+#include <google/protobuf/util/json_util.h>
+#include <google/protobuf/util/type_resolver.h>
+#include <google/protobuf/util/type_resolver_util.h>
+
+#include <memory>
+
+#include "protobuf-native/src/json.rs.h"
+
+namespace protobuf_native {
+namespace json {
+
+static const char kTypeUrlPrefix[] = "type.googleapis.com";
+
+bool MessageToJsonString(const Message& message, const DescriptorPool& pool,
+                         const PrintOptions& options, std::string& output) {
+    std::unique_ptr<util::TypeResolver> resolver(
+        util::NewTypeResolverForDescriptorPool(kTypeUrlPrefix, &pool));
+    std::string binary;
+    if (!message.SerializeToString(&binary)) {
+        return false;
+    }
+    util::JsonPrintOptions json_options;
+    json_options.add_whitespace = options.add_whitespace;
+    json_options.always_print_primitive_fields = options.always_print_primitive_fields;
+    json_options.always_print_enums_as_ints = options.always_print_enums_as_ints;
+    json_options.preserve_proto_field_names = options.preserve_proto_field_names;
+    std::string type_url =
+        std::string(kTypeUrlPrefix) + "/" + message.GetDescriptor()->full_name();
+    return util::BinaryToJsonString(resolver.get(), type_url, binary, &output, json_options).ok();
+}
+
+bool JsonStringToMessage(rust::Str input, Message& message, const ParseOptions& options) {
+    util::JsonParseOptions json_options;
+    json_options.ignore_unknown_fields = options.ignore_unknown_fields;
+    json_options.case_insensitive_enum_parsing = options.case_insensitive_enum_parsing;
+    return util::JsonStringToMessage(StringPiece(input.data(), input.size()), &message,
+                                     json_options)
+        .ok();
+}
+
+}  // namespace json
+}  // namespace protobuf_native
@@ -0,0 +1,153 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License in the LICENSE file at the
+// root of this repository, or online at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Utilities for converting between protocol messages and their [JSON
+//! representation][json-mapping].
+//!
+//! [json-mapping]: https://developers.google.com/protocol-buffers/docs/proto3#json
+
+use std::pin::Pin;
+
+use cxx::let_cxx_string;
+
+use crate::internal::BoolExt;
+use crate::{DescriptorPool, Message, OperationFailedError};
+
+pub use self::ffi::{ParseOptions, PrintOptions};
+
+#[cxx::bridge(namespace = "protobuf_native::json")]
+pub(crate) mod ffi {
+    /// Options that control how a message is converted to JSON.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct PrintOptions {
+        /// Whether to add spaces, line breaks and indentation to make the JSON
+        /// output easy to read.
+        pub add_whitespace: bool,
+        /// Whether to always print primitive fields.
+        ///
+        /// By default proto3 primitive fields with default values will be
+        /// omitted in JSON output. For example, an int32 field set to 0 will be
+        /// omitted. Set this flag to true will override the default behavior
+        /// and print primitive fields regardless of their values.
+        pub always_print_primitive_fields: bool,
+        /// Whether to always print enums as ints.
+        ///
+        /// By default they are rendered as strings.
+        pub always_print_enums_as_ints: bool,
+        /// Whether to preserve proto field names.
+        ///
+        /// By default field names are converted to lowerCamelCase.
+        pub preserve_proto_field_names: bool,
+    }
+
+    /// Options that control how JSON is parsed into a message.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct ParseOptions {
+        /// Whether to ignore unknown JSON fields during parsing.
+        pub ignore_unknown_fields: bool,
+        /// Whether to accept enum values whose names differ in case from the
+        /// names in the message definition.
+        pub case_insensitive_enum_parsing: bool,
+    }
+
+    unsafe extern "C++" {
+        include!("protobuf-native/src/json.h");
+
+        #[namespace = "google::protobuf"]
+        type Message = crate::ffi::Message;
+
+        #[namespace = "google::protobuf"]
+        type DescriptorPool = crate::ffi::DescriptorPool;
+
+        fn MessageToJsonString(
+            message: &Message,
+            pool: &DescriptorPool,
+            options: &PrintOptions,
+            output: Pin<&mut CxxString>,
+        ) -> bool;
+        fn JsonStringToMessage(
+            input: &str,
+            message: Pin<&mut Message>,
+            options: &ParseOptions,
+        ) -> bool;
+    }
+}
+
+impl PrintOptions {
+    /// Sets whether to add whitespace to the JSON output.
+    ///
+    /// See [`PrintOptions::add_whitespace`].
+    pub fn with_whitespace(mut self, add_whitespace: bool) -> PrintOptions {
+        self.add_whitespace = add_whitespace;
+        self
+    }
+
+    /// Sets whether to always print primitive fields.
+    ///
+    /// See [`PrintOptions::always_print_primitive_fields`].
+    pub fn with_always_print_primitive_fields(mut self, always: bool) -> PrintOptions {
+        self.always_print_primitive_fields = always;
+        self
+    }
+
+    /// Sets whether to always print enums as ints.
+    ///
+    /// See [`PrintOptions::always_print_enums_as_ints`].
+    pub fn with_always_print_enums_as_ints(mut self, always: bool) -> PrintOptions {
+        self.always_print_enums_as_ints = always;
+        self
+    }
+
+    /// Sets whether to preserve proto field names.
+    ///
+    /// See [`PrintOptions::preserve_proto_field_names`].
+    pub fn with_preserve_proto_field_names(mut self, preserve: bool) -> PrintOptions {
+        self.preserve_proto_field_names = preserve;
+        self
+    }
+}
+
+/// Converts a protocol message to its JSON representation.
+///
+/// The message's type, and the types of any `Any` fields it contains, are
+/// resolved using the descriptors in `pool`. Returns an error if the message
+/// could not be serialized or if a type could not be found in the pool.
+pub fn message_to_json(
+    message: &dyn Message,
+    pool: Pin<&mut DescriptorPool>,
+    options: PrintOptions,
+) -> Result<String, OperationFailedError> {
+    let_cxx_string!(output = "");
+    ffi::MessageToJsonString(
+        message.upcast_message(),
+        pool.as_ffi(),
+        &options,
+        output.as_mut(),
+    )
+    .as_result()?;
+    Ok(output.to_string_lossy().into_owned())
+}
+
+/// Parses the JSON representation of a protocol message into `message`.
+///
+/// The existing contents of `message` are replaced. Returns an error if the
+/// JSON is malformed or does not match the message's type.
+pub fn json_to_message(
+    json: &str,
+    message: Pin<&mut dyn Message>,
+    options: ParseOptions,
+) -> Result<(), OperationFailedError> {
+    ffi::JsonStringToMessage(json, message.upcast_message_mut(), &options).as_result()
+}
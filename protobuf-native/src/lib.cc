@@ -15,21 +15,157 @@
 
 #include "protobuf-native/src/lib.h"
 
+#include <climits>
+#include <vector>
+
+#include "protobuf-native/src/lib.rs.h"
+#include "protobuf-native/src/log.h"
+
 using namespace google::protobuf;
 
 namespace protobuf_native {
 
-// Disable libprotobuf's logging to stderr. Libraries should not log to
-// stderr.
-static LogHandler* default_log_handler = SetLogHandler(nullptr);
+// Route libprotobuf's logging through Rust, which discards log messages
+// unless a handler is installed. Libraries should not log to stderr.
+static LogHandler* default_log_handler = SetLogHandler(log::RustLogHandler);
 
 MessageLite* NewMessageLite(const MessageLite& message) { return message.New(); }
 
 void DeleteMessageLite(MessageLite* message) { delete message; }
 
-DescriptorPool* NewDescriptorPool() { return new DescriptorPool(); }
+void MessageLiteGetTypeName(const MessageLite& message, std::string& output) {
+    output = message.GetTypeName();
+}
+
+rust::String MessageLiteInitializationErrorString(const MessageLite& message) {
+    // For a full `Message`, this joins the paths reported by
+    // `FindInitializationErrors` with ", ".
+    return rust::String(message.InitializationErrorString());
+}
+
+RustDescriptorDatabase::RustDescriptorDatabase(rust::Box<DescriptorDatabaseAdaptor> adaptor)
+    : adaptor_(std::move(adaptor)) {}
+
+bool RustDescriptorDatabase::FindFileByName(const std::string& filename,
+                                            FileDescriptorProto* output) {
+    return adaptor_->find_file_by_name(
+        rust::Slice<const uint8_t>(reinterpret_cast<const uint8_t*>(filename.data()),
+                                   filename.size()),
+        *output);
+}
+
+bool RustDescriptorDatabase::FindFileContainingSymbol(const std::string& symbol_name,
+                                                      FileDescriptorProto* output) {
+    return adaptor_->find_file_containing_symbol(symbol_name, *output);
+}
+
+bool RustDescriptorDatabase::FindFileContainingExtension(const std::string& /* containing_type */,
+                                                         int /* field_number */,
+                                                         FileDescriptorProto* /* output */) {
+    return false;
+}
+
+OwningDescriptorPool::OwningDescriptorPool() : DescriptorPool() {}
+
+OwningDescriptorPool::OwningDescriptorPool(std::unique_ptr<DescriptorDatabase> database)
+    : DescriptorPool(database.get()), database_(std::move(database)) {}
+
+bool OwningDescriptorPool::HasDatabase() const { return database_ != nullptr; }
+
+DescriptorPool* NewDescriptorPool() { return new OwningDescriptorPool(); }
+
+DescriptorPool* NewDescriptorPoolWithDatabase(rust::Box<DescriptorDatabaseAdaptor> adaptor) {
+    return new OwningDescriptorPool(
+        std::unique_ptr<DescriptorDatabase>(new RustDescriptorDatabase(std::move(adaptor))));
+}
+
+void DeleteDescriptorPool(DescriptorPool* pool) {
+    delete static_cast<OwningDescriptorPool*>(pool);
+}
+
+const DescriptorPool* DescriptorPoolGeneratedPool() { return DescriptorPool::generated_pool(); }
+
+bool DescriptorPoolHasDatabase(const DescriptorPool& pool) {
+    return static_cast<const OwningDescriptorPool&>(pool).HasDatabase();
+}
+
+MultiFileErrorCollectorAdaptor::MultiFileErrorCollectorAdaptor(
+    compiler::MultiFileErrorCollector* error_collector, std::string& errors)
+    : error_collector_(error_collector), errors_(errors) {}
+
+void MultiFileErrorCollectorAdaptor::AddError(const std::string& filename,
+                                              const std::string& element_name,
+                                              const Message* /* descriptor */,
+                                              ErrorLocation /* location */,
+                                              const std::string& message) {
+    if (errors_.empty()) {
+        errors_ = "Invalid proto descriptor for file \"" + filename + "\":";
+    }
+    errors_ += "\n  " + element_name + ": " + message;
+    error_collector_->AddError(filename, -1, 0, message);
+}
+
+void MultiFileErrorCollectorAdaptor::AddWarning(const std::string& filename,
+                                                const std::string& /* element_name */,
+                                                const Message* /* descriptor */,
+                                                ErrorLocation /* location */,
+                                                const std::string& message) {
+    error_collector_->AddWarning(filename, -1, 0, message);
+}
+
+const FileDescriptor* DescriptorPoolBuildFileCollectingErrors(
+    DescriptorPool& pool, const FileDescriptorProto& proto,
+    compiler::MultiFileErrorCollector* error_collector, std::string& errors) {
+    MultiFileErrorCollectorAdaptor adaptor(error_collector, errors);
+    return pool.BuildFileCollectingErrors(proto, &adaptor);
+}
+
+rust::Vec<FieldDescriptorPtr> DescriptorPoolFindAllExtensions(const DescriptorPool& pool,
+                                                              const Descriptor& extendee) {
+    std::vector<const FieldDescriptor*> extensions;
+    pool.FindAllExtensions(&extendee, &extensions);
+    rust::Vec<FieldDescriptorPtr> out;
+    out.reserve(extensions.size());
+    for (const FieldDescriptor* extension : extensions) {
+        out.push_back(FieldDescriptorPtr{extension});
+    }
+    return out;
+}
+
+SimpleDescriptorDatabase* NewSimpleDescriptorDatabase() { return new SimpleDescriptorDatabase(); }
+
+void DeleteSimpleDescriptorDatabase(SimpleDescriptorDatabase* db) { delete db; }
+
+std::unique_ptr<std::vector<std::string>> SimpleDescriptorDatabaseFindAllFileNames(
+    SimpleDescriptorDatabase& db) {
+    std::unique_ptr<std::vector<std::string>> output(new std::vector<std::string>());
+    if (!db.FindAllFileNames(output.get())) {
+        return nullptr;
+    }
+    return output;
+}
 
-void DeleteDescriptorPool(DescriptorPool* pool) { delete pool; }
+EncodedDescriptorDatabase* NewEncodedDescriptorDatabase() {
+    return new EncodedDescriptorDatabase();
+}
+
+void DeleteEncodedDescriptorDatabase(EncodedDescriptorDatabase* db) { delete db; }
+
+bool EncodedDescriptorDatabaseAdd(EncodedDescriptorDatabase& db,
+                                  rust::Slice<const uint8_t> encoded_file_descriptor) {
+    if (encoded_file_descriptor.size() > INT_MAX) {
+        return false;
+    }
+    return db.Add(encoded_file_descriptor.data(), encoded_file_descriptor.size());
+}
+
+bool EncodedDescriptorDatabaseAddCopy(EncodedDescriptorDatabase& db,
+                                      rust::Slice<const uint8_t> encoded_file_descriptor) {
+    if (encoded_file_descriptor.size() > INT_MAX) {
+        return false;
+    }
+    return db.AddCopy(encoded_file_descriptor.data(), encoded_file_descriptor.size());
+}
 
 FileDescriptorSet* NewFileDescriptorSet() { return new FileDescriptorSet(); }
 
@@ -43,6 +179,83 @@ DescriptorProto* NewDescriptorProto() { return new DescriptorProto(); }
 
 void DeleteDescriptorProto(DescriptorProto* proto) { delete proto; }
 
+void DeleteFieldDescriptorProto(FieldDescriptorProto* proto) { delete proto; }
+
+internal::CInt FieldDescriptorProtoGetType(const FieldDescriptorProto& proto) {
+    return proto.type();
+}
+
+internal::CInt FieldDescriptorProtoGetLabel(const FieldDescriptorProto& proto) {
+    return proto.label();
+}
+
+void DeleteOneofDescriptorProto(OneofDescriptorProto* proto) { delete proto; }
+
+void DeleteEnumDescriptorProto(EnumDescriptorProto* proto) { delete proto; }
+
+void DeleteEnumValueDescriptorProto(EnumValueDescriptorProto* proto) { delete proto; }
+
+void DeleteServiceDescriptorProto(ServiceDescriptorProto* proto) { delete proto; }
+
+void DeleteSourceCodeInfo(SourceCodeInfo* info) { delete info; }
+
+void DeleteSourceCodeInfoLocation(SourceCodeInfo_Location* location) { delete location; }
+
+rust::Slice<const int32_t> SourceCodeInfoLocationPath(const SourceCodeInfo_Location& location) {
+    return rust::Slice<const int32_t>(location.path().data(), location.path_size());
+}
+
+rust::Slice<const int32_t> SourceCodeInfoLocationSpan(const SourceCodeInfo_Location& location) {
+    return rust::Slice<const int32_t>(location.span().data(), location.span_size());
+}
+
+void DeleteMethodDescriptorProto(MethodDescriptorProto* proto) { delete proto; }
+
 void DeleteFileDescriptor(FileDescriptor* descriptor) { delete descriptor; }
 
+internal::CInt FieldDescriptorGetType(const FieldDescriptor& field) { return field.type(); }
+
+void ReflectionGetString(const Message& message, const FieldDescriptor& field,
+                         std::string& output) {
+    output = message.GetReflection()->GetString(message, &field);
+}
+
+bool MessageEquals(const Message& a, const Message& b) {
+    // `MessageDifferencer` treats a comparison between messages of different
+    // types as a fatal error in debug builds.
+    if (a.GetDescriptor() != b.GetDescriptor()) {
+        return false;
+    }
+    // Treat NaN as equal to NaN, so that equality is reflexive.
+    google::protobuf::util::DefaultFieldComparator comparator;
+    comparator.set_treat_nan_as_equal(true);
+    google::protobuf::util::MessageDifferencer differencer;
+    differencer.set_field_comparator(&comparator);
+    return differencer.Compare(a, b);
+}
+
+rust::Vec<rust::String> MessageFindInitializationErrors(const Message& message) {
+    std::vector<std::string> errors;
+    message.FindInitializationErrors(&errors);
+    rust::Vec<rust::String> out;
+    out.reserve(errors.size());
+    for (const std::string& error : errors) {
+        out.push_back(rust::String(error));
+    }
+    return out;
+}
+
+void MessageDebugString(const Message& message, bool multiline, std::string& output) {
+    output = multiline ? message.DebugString() : message.ShortDebugString();
+}
+
+void DeleteMessage(Message* message) { delete message; }
+
+Message* NewGeneratedMessage(const Descriptor& descriptor) {
+    // The generated factory only knows about descriptors from the generated
+    // pool, and returns null for any other descriptor.
+    const Message* prototype = MessageFactory::generated_factory()->GetPrototype(&descriptor);
+    return prototype == nullptr ? nullptr : prototype->New();
+}
+
 }  // namespace protobuf_native
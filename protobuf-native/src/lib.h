@@ -17,18 +17,107 @@
 
 #include <google/protobuf/descriptor.h>
 #include <google/protobuf/descriptor.pb.h>
+#include <google/protobuf/descriptor_database.h>
+#include <google/protobuf/message.h>
+#include <google/protobuf/compiler/importer.h>
+#include <google/protobuf/util/field_comparator.h>
+#include <google/protobuf/util/message_differencer.h>
 
 #include <memory>
+#include <string>
+#include <vector>
+
+#include "protobuf-native/src/internal.h"
+#include "rust/cxx.h"
 
 using namespace google::protobuf;
 
 namespace protobuf_native {
 
+struct DescriptorDatabaseAdaptor;
+struct FieldDescriptorPtr;
+
 MessageLite* NewMessageLite(const MessageLite& message);
 void DeleteMessageLite(MessageLite*);
+void MessageLiteGetTypeName(const MessageLite& message, std::string& output);
+
+rust::String MessageLiteInitializationErrorString(const MessageLite& message);
+
+// A `DescriptorDatabase` that forwards to a Rust `DescriptorDatabase`.
+class RustDescriptorDatabase : public DescriptorDatabase {
+   public:
+    RustDescriptorDatabase(rust::Box<DescriptorDatabaseAdaptor> adaptor);
+
+    bool FindFileByName(const std::string& filename, FileDescriptorProto* output) override;
+    bool FindFileContainingSymbol(const std::string& symbol_name,
+                                  FileDescriptorProto* output) override;
+    bool FindFileContainingExtension(const std::string& containing_type, int field_number,
+                                     FileDescriptorProto* output) override;
+
+   private:
+    rust::Box<DescriptorDatabaseAdaptor> adaptor_;
+};
+
+// A `DescriptorPool` that owns its fallback database, if any.
+//
+// Every pool created by `NewDescriptorPool` or `NewDescriptorPoolWithDatabase`
+// is an `OwningDescriptorPool`. `DescriptorPool`'s destructor is not virtual,
+// so `DeleteDescriptorPool` downcasts before deleting. The generated pool is
+// the only other pool exposed to Rust, and Rust only ever borrows it
+// immutably.
+class OwningDescriptorPool : public DescriptorPool {
+   public:
+    OwningDescriptorPool();
+    OwningDescriptorPool(std::unique_ptr<DescriptorDatabase> database);
+
+    bool HasDatabase() const;
+
+   private:
+    std::unique_ptr<DescriptorDatabase> database_;
+};
 
 DescriptorPool* NewDescriptorPool();
+DescriptorPool* NewDescriptorPoolWithDatabase(rust::Box<DescriptorDatabaseAdaptor> adaptor);
 void DeleteDescriptorPool(DescriptorPool*);
+const DescriptorPool* DescriptorPoolGeneratedPool();
+bool DescriptorPoolHasDatabase(const DescriptorPool& pool);
+
+// Forwards errors to a `MultiFileErrorCollector`, while also recording them in
+// the format in which `DescriptorPool::BuildFile` logs them.
+class MultiFileErrorCollectorAdaptor : public DescriptorPool::ErrorCollector {
+   public:
+    MultiFileErrorCollectorAdaptor(compiler::MultiFileErrorCollector* error_collector,
+                                   std::string& errors);
+
+    void AddError(const std::string& filename, const std::string& element_name,
+                  const Message* descriptor, ErrorLocation location,
+                  const std::string& message) override;
+    void AddWarning(const std::string& filename, const std::string& element_name,
+                    const Message* descriptor, ErrorLocation location,
+                    const std::string& message) override;
+
+   private:
+    compiler::MultiFileErrorCollector* error_collector_;
+    std::string& errors_;
+};
+
+const FileDescriptor* DescriptorPoolBuildFileCollectingErrors(
+    DescriptorPool& pool, const FileDescriptorProto& proto,
+    compiler::MultiFileErrorCollector* error_collector, std::string& errors);
+rust::Vec<FieldDescriptorPtr> DescriptorPoolFindAllExtensions(const DescriptorPool& pool,
+                                                              const Descriptor& extendee);
+
+SimpleDescriptorDatabase* NewSimpleDescriptorDatabase();
+void DeleteSimpleDescriptorDatabase(SimpleDescriptorDatabase* db);
+std::unique_ptr<std::vector<std::string>> SimpleDescriptorDatabaseFindAllFileNames(
+    SimpleDescriptorDatabase& db);
+
+EncodedDescriptorDatabase* NewEncodedDescriptorDatabase();
+void DeleteEncodedDescriptorDatabase(EncodedDescriptorDatabase* db);
+bool EncodedDescriptorDatabaseAdd(EncodedDescriptorDatabase& db,
+                                  rust::Slice<const uint8_t> encoded_file_descriptor);
+bool EncodedDescriptorDatabaseAddCopy(EncodedDescriptorDatabase& db,
+                                      rust::Slice<const uint8_t> encoded_file_descriptor);
 
 FileDescriptorSet* NewFileDescriptorSet();
 void DeleteFileDescriptorSet(FileDescriptorSet* set);
@@ -39,6 +128,39 @@ void DeleteFileDescriptorProto(FileDescriptorProto*);
 DescriptorProto* NewDescriptorProto();
 void DeleteDescriptorProto(DescriptorProto* proto);
 
+void DeleteFieldDescriptorProto(FieldDescriptorProto* proto);
+internal::CInt FieldDescriptorProtoGetType(const FieldDescriptorProto& proto);
+internal::CInt FieldDescriptorProtoGetLabel(const FieldDescriptorProto& proto);
+
+void DeleteOneofDescriptorProto(OneofDescriptorProto* proto);
+
+void DeleteEnumDescriptorProto(EnumDescriptorProto* proto);
+
+void DeleteEnumValueDescriptorProto(EnumValueDescriptorProto* proto);
+
+void DeleteServiceDescriptorProto(ServiceDescriptorProto* proto);
+
+void DeleteSourceCodeInfo(SourceCodeInfo* info);
+void DeleteSourceCodeInfoLocation(SourceCodeInfo_Location* location);
+rust::Slice<const int32_t> SourceCodeInfoLocationPath(const SourceCodeInfo_Location& location);
+rust::Slice<const int32_t> SourceCodeInfoLocationSpan(const SourceCodeInfo_Location& location);
+
+void DeleteMethodDescriptorProto(MethodDescriptorProto* proto);
+
 void DeleteFileDescriptor(FileDescriptor*);
 
+internal::CInt FieldDescriptorGetType(const FieldDescriptor& field);
+
+void ReflectionGetString(const Message& message, const FieldDescriptor& field,
+                         std::string& output);
+
+bool MessageEquals(const Message& a, const Message& b);
+
+rust::Vec<rust::String> MessageFindInitializationErrors(const Message& message);
+
+void MessageDebugString(const Message& message, bool multiline, std::string& output);
+
+void DeleteMessage(Message* message);
+Message* NewGeneratedMessage(const Descriptor& descriptor);
+
 }  // namespace protobuf_native
@@ -46,24 +46,60 @@
 //! [Materialize]: https://materialize.com
 //! [Protocol Buffers]: https://github.com/google/protobuf
 
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
-use std::io::Write;
-use std::marker::PhantomPinned;
+use std::io::{Read, Write};
+use std::iter::FusedIterator;
+use std::marker::{PhantomData, PhantomPinned};
 use std::mem;
-use std::path::Path;
+use std::ops::{Index, Range};
+use std::path::{Path, PathBuf};
 use std::pin::Pin;
+use std::ptr;
 
-use crate::internal::{unsafe_ffi_conversions, BoolExt, CInt};
-use crate::io::{CodedInputStream, CodedOutputStream, WriterStream, ZeroCopyOutputStream};
+use cxx::let_cxx_string;
 
+use crate::compiler::MultiFileErrorCollector;
+use crate::internal::{
+    unsafe_ffi_conversions, BoolExt, CInt, DescriptorDatabaseAdaptor, ProtobufPath,
+};
+use crate::io::{
+    CodedInputStream, CodedOutputStream, LengthPrefixDecoder, ReaderStream, SliceInputStream,
+    VecOutputStream, WriterStream, ZeroCopyInputStream, ZeroCopyOutputStream,
+};
+
+pub mod compat;
 pub mod compiler;
 pub mod io;
+pub mod json;
+pub mod log;
+pub mod text_format;
+pub mod util;
 
 mod internal;
 
 #[cxx::bridge(namespace = "protobuf_native")]
 pub(crate) mod ffi {
+    struct FieldDescriptorPtr {
+        ptr: *const FieldDescriptor,
+    }
+
+    extern "Rust" {
+        type DescriptorDatabaseAdaptor<'a>;
+        fn find_file_by_name(
+            self: &mut DescriptorDatabaseAdaptor<'_>,
+            filename: &[u8],
+            output: Pin<&mut FileDescriptorProto>,
+        ) -> bool;
+        fn find_file_containing_symbol(
+            self: &mut DescriptorDatabaseAdaptor<'_>,
+            symbol_name: &CxxString,
+            output: Pin<&mut FileDescriptorProto>,
+        ) -> bool;
+    }
+
     unsafe extern "C++" {
         include!("protobuf-native/src/internal.h");
         include!("protobuf-native/src/lib.h");
@@ -71,6 +107,9 @@ pub(crate) mod ffi {
         #[namespace = "protobuf_native::internal"]
         type CInt = crate::internal::CInt;
 
+        #[namespace = "google::protobuf::compiler"]
+        type MultiFileErrorCollector = crate::compiler::ffi::MultiFileErrorCollector;
+
         #[namespace = "google::protobuf::io"]
         type ZeroCopyOutputStream = crate::io::ffi::ZeroCopyOutputStream;
 
@@ -100,24 +139,201 @@ pub(crate) mod ffi {
             output: *mut ZeroCopyOutputStream,
         ) -> bool;
         fn ByteSizeLong(self: &MessageLite) -> usize;
+        fn MessageLiteGetTypeName(message: &MessageLite, output: Pin<&mut CxxString>);
+        fn MessageLiteInitializationErrorString(message: &MessageLite) -> String;
 
         #[namespace = "google::protobuf"]
         type Message;
 
+        fn GetDescriptor(self: &Message) -> *const Descriptor;
+        fn GetReflection(self: &Message) -> *const Reflection;
+        fn SpaceUsedLong(self: &Message) -> usize;
+        fn MessageFindInitializationErrors(message: &Message) -> Vec<String>;
+
+        #[namespace = "google::protobuf"]
+        type Reflection;
+
+        unsafe fn HasField(
+            self: &Reflection,
+            message: &Message,
+            field: *const FieldDescriptor,
+        ) -> bool;
+        unsafe fn GetInt32(
+            self: &Reflection,
+            message: &Message,
+            field: *const FieldDescriptor,
+        ) -> i32;
+        unsafe fn GetInt64(
+            self: &Reflection,
+            message: &Message,
+            field: *const FieldDescriptor,
+        ) -> i64;
+        unsafe fn GetUInt32(
+            self: &Reflection,
+            message: &Message,
+            field: *const FieldDescriptor,
+        ) -> u32;
+        unsafe fn GetUInt64(
+            self: &Reflection,
+            message: &Message,
+            field: *const FieldDescriptor,
+        ) -> u64;
+        unsafe fn GetFloat(
+            self: &Reflection,
+            message: &Message,
+            field: *const FieldDescriptor,
+        ) -> f32;
+        unsafe fn GetDouble(
+            self: &Reflection,
+            message: &Message,
+            field: *const FieldDescriptor,
+        ) -> f64;
+        unsafe fn GetBool(
+            self: &Reflection,
+            message: &Message,
+            field: *const FieldDescriptor,
+        ) -> bool;
+        unsafe fn GetEnumValue(
+            self: &Reflection,
+            message: &Message,
+            field: *const FieldDescriptor,
+        ) -> CInt;
+        fn ReflectionGetString(
+            message: &Message,
+            field: &FieldDescriptor,
+            output: Pin<&mut CxxString>,
+        );
+        fn MessageEquals(a: &Message, b: &Message) -> bool;
+        fn MessageDebugString(message: &Message, multiline: bool, output: Pin<&mut CxxString>);
+        unsafe fn DeleteMessage(message: *mut Message);
+        fn NewGeneratedMessage(descriptor: &Descriptor) -> *mut Message;
+
         #[namespace = "google::protobuf"]
         type FileDescriptor;
 
         unsafe fn DeleteFileDescriptor(proto: *mut FileDescriptor);
+        fn name(self: &FileDescriptor) -> &CxxString;
+        fn package(self: &FileDescriptor) -> &CxxString;
+        fn dependency_count(self: &FileDescriptor) -> CInt;
+        fn dependency(self: &FileDescriptor, index: CInt) -> *const FileDescriptor;
+        fn message_type_count(self: &FileDescriptor) -> CInt;
+        fn message_type(self: &FileDescriptor, index: CInt) -> *const Descriptor;
+        unsafe fn CopyTo(self: &FileDescriptor, proto: *mut FileDescriptorProto);
+
+        #[namespace = "google::protobuf"]
+        type Descriptor;
+
+        fn name(self: &Descriptor) -> &CxxString;
+        fn full_name(self: &Descriptor) -> &CxxString;
+        fn field_count(self: &Descriptor) -> CInt;
+        fn field(self: &Descriptor, index: CInt) -> *const FieldDescriptor;
+        fn nested_type_count(self: &Descriptor) -> CInt;
+        fn nested_type(self: &Descriptor, index: CInt) -> *const Descriptor;
+        fn file(self: &Descriptor) -> *const FileDescriptor;
+        unsafe fn CopyTo(self: &Descriptor, proto: *mut DescriptorProto);
+
+        #[namespace = "google::protobuf"]
+        type EnumDescriptor;
+
+        fn name(self: &EnumDescriptor) -> &CxxString;
+        fn full_name(self: &EnumDescriptor) -> &CxxString;
+        fn file(self: &EnumDescriptor) -> *const FileDescriptor;
+
+        #[namespace = "google::protobuf"]
+        type FieldDescriptor;
+
+        fn name(self: &FieldDescriptor) -> &CxxString;
+        fn full_name(self: &FieldDescriptor) -> &CxxString;
+        fn number(self: &FieldDescriptor) -> CInt;
+        fn is_extension(self: &FieldDescriptor) -> bool;
+        fn is_repeated(self: &FieldDescriptor) -> bool;
+        fn containing_type(self: &FieldDescriptor) -> *const Descriptor;
+        fn FieldDescriptorGetType(field: &FieldDescriptor) -> CInt;
 
         #[namespace = "google::protobuf"]
         type DescriptorPool;
 
         fn NewDescriptorPool() -> *mut DescriptorPool;
+        fn NewDescriptorPoolWithDatabase(
+            adaptor: Box<DescriptorDatabaseAdaptor<'_>>,
+        ) -> *mut DescriptorPool;
         unsafe fn DeleteDescriptorPool(proto: *mut DescriptorPool);
+        fn DescriptorPoolGeneratedPool() -> *const DescriptorPool;
+        fn DescriptorPoolHasDatabase(pool: &DescriptorPool) -> bool;
+        unsafe fn DescriptorPoolBuildFileCollectingErrors(
+            pool: Pin<&mut DescriptorPool>,
+            proto: &FileDescriptorProto,
+            error_collector: *mut MultiFileErrorCollector,
+            errors: Pin<&mut CxxString>,
+        ) -> *const FileDescriptor;
         fn BuildFile(
             self: Pin<&mut DescriptorPool>,
             proto: &FileDescriptorProto,
         ) -> *const FileDescriptor;
+        fn FindFileByName(self: &DescriptorPool, name: &CxxString) -> *const FileDescriptor;
+        fn FindFileContainingSymbol(
+            self: &DescriptorPool,
+            symbol_name: &CxxString,
+        ) -> *const FileDescriptor;
+        fn FindMessageTypeByName(self: &DescriptorPool, name: &CxxString) -> *const Descriptor;
+        fn FindEnumTypeByName(self: &DescriptorPool, name: &CxxString) -> *const EnumDescriptor;
+        unsafe fn FindExtensionByNumber(
+            self: &DescriptorPool,
+            extendee: *const Descriptor,
+            number: CInt,
+        ) -> *const FieldDescriptor;
+        fn DescriptorPoolFindAllExtensions(
+            pool: &DescriptorPool,
+            extendee: &Descriptor,
+        ) -> Vec<FieldDescriptorPtr>;
+
+        #[namespace = "google::protobuf"]
+        type SimpleDescriptorDatabase;
+
+        fn NewSimpleDescriptorDatabase() -> *mut SimpleDescriptorDatabase;
+        unsafe fn DeleteSimpleDescriptorDatabase(db: *mut SimpleDescriptorDatabase);
+        fn Add(self: Pin<&mut SimpleDescriptorDatabase>, file: &FileDescriptorProto) -> bool;
+        unsafe fn AddAndOwn(
+            self: Pin<&mut SimpleDescriptorDatabase>,
+            file: *const FileDescriptorProto,
+        ) -> bool;
+        unsafe fn FindFileByName(
+            self: Pin<&mut SimpleDescriptorDatabase>,
+            filename: &CxxString,
+            output: *mut FileDescriptorProto,
+        ) -> bool;
+        unsafe fn FindFileContainingSymbol(
+            self: Pin<&mut SimpleDescriptorDatabase>,
+            symbol_name: &CxxString,
+            output: *mut FileDescriptorProto,
+        ) -> bool;
+        fn SimpleDescriptorDatabaseFindAllFileNames(
+            db: Pin<&mut SimpleDescriptorDatabase>,
+        ) -> UniquePtr<CxxVector<CxxString>>;
+
+        #[namespace = "google::protobuf"]
+        type EncodedDescriptorDatabase;
+
+        fn NewEncodedDescriptorDatabase() -> *mut EncodedDescriptorDatabase;
+        unsafe fn DeleteEncodedDescriptorDatabase(db: *mut EncodedDescriptorDatabase);
+        fn EncodedDescriptorDatabaseAdd(
+            db: Pin<&mut EncodedDescriptorDatabase>,
+            encoded_file_descriptor: &[u8],
+        ) -> bool;
+        fn EncodedDescriptorDatabaseAddCopy(
+            db: Pin<&mut EncodedDescriptorDatabase>,
+            encoded_file_descriptor: &[u8],
+        ) -> bool;
+        unsafe fn FindFileByName(
+            self: Pin<&mut EncodedDescriptorDatabase>,
+            filename: &CxxString,
+            output: *mut FileDescriptorProto,
+        ) -> bool;
+        unsafe fn FindFileContainingSymbol(
+            self: Pin<&mut EncodedDescriptorDatabase>,
+            symbol_name: &CxxString,
+            output: *mut FileDescriptorProto,
+        ) -> bool;
 
         #[namespace = "google::protobuf"]
         type FileDescriptorSet;
@@ -129,6 +345,7 @@ pub(crate) mod ffi {
         fn file(self: &FileDescriptorSet, i: CInt) -> &FileDescriptorProto;
         fn mutable_file(self: Pin<&mut FileDescriptorSet>, i: CInt) -> *mut FileDescriptorProto;
         fn add_file(self: Pin<&mut FileDescriptorSet>) -> *mut FileDescriptorProto;
+        fn CopyFrom(self: Pin<&mut FileDescriptorSet>, from: &FileDescriptorSet);
 
         #[namespace = "google::protobuf"]
         type FileDescriptorProto;
@@ -137,15 +354,105 @@ pub(crate) mod ffi {
         unsafe fn DeleteFileDescriptorProto(proto: *mut FileDescriptorProto);
         fn CopyFrom(self: Pin<&mut FileDescriptorProto>, from: &FileDescriptorProto);
         fn MergeFrom(self: Pin<&mut FileDescriptorProto>, from: &FileDescriptorProto);
+        fn name(self: &FileDescriptorProto) -> &CxxString;
         fn dependency_size(self: &FileDescriptorProto) -> CInt;
         fn dependency(self: &FileDescriptorProto, i: CInt) -> &CxxString;
+        fn add_dependency(self: Pin<&mut FileDescriptorProto>, value: &CxxString);
         fn message_type_size(self: &FileDescriptorProto) -> CInt;
         fn message_type(self: &FileDescriptorProto, i: CInt) -> &DescriptorProto;
+        fn add_message_type(self: Pin<&mut FileDescriptorProto>) -> *mut DescriptorProto;
+        fn set_name(self: Pin<&mut FileDescriptorProto>, value: &CxxString);
+        fn package(self: &FileDescriptorProto) -> &CxxString;
+        fn set_package(self: Pin<&mut FileDescriptorProto>, value: &CxxString);
+        fn syntax(self: &FileDescriptorProto) -> &CxxString;
+        fn enum_type_size(self: &FileDescriptorProto) -> CInt;
+        fn enum_type(self: &FileDescriptorProto, i: CInt) -> &EnumDescriptorProto;
+        fn service_size(self: &FileDescriptorProto) -> CInt;
+        fn service(self: &FileDescriptorProto, i: CInt) -> &ServiceDescriptorProto;
+        fn has_source_code_info(self: &FileDescriptorProto) -> bool;
+        fn source_code_info(self: &FileDescriptorProto) -> &SourceCodeInfo;
+
+        #[namespace = "google::protobuf"]
+        type SourceCodeInfo;
+        unsafe fn DeleteSourceCodeInfo(info: *mut SourceCodeInfo);
+        fn location_size(self: &SourceCodeInfo) -> CInt;
+        fn location(self: &SourceCodeInfo, i: CInt) -> &SourceCodeInfo_Location;
+
+        #[namespace = "google::protobuf"]
+        type SourceCodeInfo_Location;
+        unsafe fn DeleteSourceCodeInfoLocation(location: *mut SourceCodeInfo_Location);
+        fn SourceCodeInfoLocationPath(location: &SourceCodeInfo_Location) -> &[i32];
+        fn SourceCodeInfoLocationSpan(location: &SourceCodeInfo_Location) -> &[i32];
+        fn leading_comments(self: &SourceCodeInfo_Location) -> &CxxString;
+        fn trailing_comments(self: &SourceCodeInfo_Location) -> &CxxString;
+        fn leading_detached_comments_size(self: &SourceCodeInfo_Location) -> CInt;
+        fn leading_detached_comments(self: &SourceCodeInfo_Location, i: CInt) -> &CxxString;
 
         #[namespace = "google::protobuf"]
         type DescriptorProto;
+        fn NewDescriptorProto() -> *mut DescriptorProto;
         unsafe fn DeleteDescriptorProto(proto: *mut DescriptorProto);
         fn name(self: &DescriptorProto) -> &CxxString;
+        fn set_name(self: Pin<&mut DescriptorProto>, value: &CxxString);
+        fn field_size(self: &DescriptorProto) -> CInt;
+        fn field(self: &DescriptorProto, i: CInt) -> &FieldDescriptorProto;
+        fn nested_type_size(self: &DescriptorProto) -> CInt;
+        fn nested_type(self: &DescriptorProto, i: CInt) -> &DescriptorProto;
+        fn enum_type_size(self: &DescriptorProto) -> CInt;
+        fn enum_type(self: &DescriptorProto, i: CInt) -> &EnumDescriptorProto;
+        fn oneof_decl_size(self: &DescriptorProto) -> CInt;
+        fn oneof_decl(self: &DescriptorProto, i: CInt) -> &OneofDescriptorProto;
+        fn reserved_range_size(self: &DescriptorProto) -> CInt;
+        fn reserved_range(self: &DescriptorProto, i: CInt) -> &DescriptorProto_ReservedRange;
+        fn reserved_name_size(self: &DescriptorProto) -> CInt;
+        fn reserved_name(self: &DescriptorProto, i: CInt) -> &CxxString;
+
+        #[namespace = "google::protobuf"]
+        type DescriptorProto_ReservedRange;
+        fn start(self: &DescriptorProto_ReservedRange) -> i32;
+        fn end(self: &DescriptorProto_ReservedRange) -> i32;
+
+        #[namespace = "google::protobuf"]
+        type OneofDescriptorProto;
+        unsafe fn DeleteOneofDescriptorProto(proto: *mut OneofDescriptorProto);
+        fn name(self: &OneofDescriptorProto) -> &CxxString;
+
+        #[namespace = "google::protobuf"]
+        type FieldDescriptorProto;
+        unsafe fn DeleteFieldDescriptorProto(proto: *mut FieldDescriptorProto);
+        fn name(self: &FieldDescriptorProto) -> &CxxString;
+        fn number(self: &FieldDescriptorProto) -> i32;
+        fn has_type(self: &FieldDescriptorProto) -> bool;
+        fn FieldDescriptorProtoGetType(proto: &FieldDescriptorProto) -> CInt;
+        fn FieldDescriptorProtoGetLabel(proto: &FieldDescriptorProto) -> CInt;
+        fn type_name(self: &FieldDescriptorProto) -> &CxxString;
+
+        #[namespace = "google::protobuf"]
+        type EnumDescriptorProto;
+        unsafe fn DeleteEnumDescriptorProto(proto: *mut EnumDescriptorProto);
+        fn name(self: &EnumDescriptorProto) -> &CxxString;
+        fn value_size(self: &EnumDescriptorProto) -> CInt;
+        fn value(self: &EnumDescriptorProto, i: CInt) -> &EnumValueDescriptorProto;
+
+        #[namespace = "google::protobuf"]
+        type EnumValueDescriptorProto;
+        unsafe fn DeleteEnumValueDescriptorProto(proto: *mut EnumValueDescriptorProto);
+        fn name(self: &EnumValueDescriptorProto) -> &CxxString;
+        fn number(self: &EnumValueDescriptorProto) -> i32;
+
+        #[namespace = "google::protobuf"]
+        type ServiceDescriptorProto;
+        unsafe fn DeleteServiceDescriptorProto(proto: *mut ServiceDescriptorProto);
+        fn name(self: &ServiceDescriptorProto) -> &CxxString;
+        fn method_size(self: &ServiceDescriptorProto) -> CInt;
+        fn method(self: &ServiceDescriptorProto, i: CInt) -> &MethodDescriptorProto;
+
+        #[namespace = "google::protobuf"]
+        type MethodDescriptorProto;
+        unsafe fn DeleteMethodDescriptorProto(proto: *mut MethodDescriptorProto);
+        fn name(self: &MethodDescriptorProto) -> &CxxString;
+        fn input_type(self: &MethodDescriptorProto) -> &CxxString;
+        fn output_type(self: &MethodDescriptorProto) -> &CxxString;
     }
 
     impl UniquePtr<MessageLite> {}
@@ -162,7 +469,10 @@ mod private {
         fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MessageLite>;
     }
 
-    pub trait Message {}
+    pub trait Message {
+        fn upcast_message(&self) -> &ffi::Message;
+        fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message>;
+    }
 }
 
 /// Abstract interface for a database of descriptors.
@@ -179,6 +489,263 @@ pub trait DescriptorDatabase {
         self: Pin<&mut Self>,
         filename: &Path,
     ) -> Result<Pin<Box<FileDescriptorProto>>, OperationFailedError>;
+
+    /// Finds the file that declares the given fully-qualified symbol name.
+    ///
+    /// The default implementation always returns an error, which is
+    /// appropriate for databases that can only look up files by name.
+    fn find_file_containing_symbol(
+        self: Pin<&mut Self>,
+        symbol_name: &str,
+    ) -> Result<Pin<Box<FileDescriptorProto>>, OperationFailedError> {
+        let _ = symbol_name;
+        Err(OperationFailedError::new())
+    }
+}
+
+/// A [`DescriptorDatabase`] into which you can insert files manually.
+///
+/// `find_file_by_name` and `find_file_containing_symbol` are fully-implemented.
+/// The database does not validate the files added to it beyond checking that
+/// their names and the symbols they define do not conflict with those of files
+/// already in the database.
+///
+/// This is the usual way to assemble a [`DescriptorPool`] from the files of a
+/// [`FileDescriptorSet`]: add each file to a `SimpleDescriptorDatabase`, then
+/// wrap the database with [`DescriptorPool::with_database`].
+pub struct SimpleDescriptorDatabase {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for SimpleDescriptorDatabase {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteSimpleDescriptorDatabase(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl SimpleDescriptorDatabase {
+    /// Creates a new, empty database.
+    pub fn new() -> Pin<Box<SimpleDescriptorDatabase>> {
+        let db = ffi::NewSimpleDescriptorDatabase();
+        unsafe { Self::from_ffi_owned(db) }
+    }
+
+    /// Adds a copy of the given file to the database.
+    ///
+    /// Returns an error if the file conflicts with a file already in the
+    /// database.
+    pub fn add(
+        self: Pin<&mut Self>,
+        file: &FileDescriptorProto,
+    ) -> Result<(), OperationFailedError> {
+        self.as_ffi_mut().Add(file.as_ffi()).as_result()
+    }
+
+    /// Adds the given file to the database, transferring ownership of the file
+    /// to the database rather than copying it.
+    ///
+    /// Returns an error if the file conflicts with a file already in the
+    /// database.
+    pub fn add_and_own(
+        self: Pin<&mut Self>,
+        file: Pin<Box<FileDescriptorProto>>,
+    ) -> Result<(), OperationFailedError> {
+        // The database takes ownership of the file even if adding it fails.
+        let file = Box::into_raw(unsafe { Pin::into_inner_unchecked(file) });
+        unsafe { self.as_ffi_mut().AddAndOwn(file as *const _).as_result() }
+    }
+
+    /// Returns the names of all files in the database.
+    pub fn find_all_file_names(self: Pin<&mut Self>) -> Result<Vec<PathBuf>, OperationFailedError> {
+        let names = ffi::SimpleDescriptorDatabaseFindAllFileNames(self.as_ffi_mut());
+        let names = names.as_ref().ok_or_else(OperationFailedError::new)?;
+        Ok(names
+            .iter()
+            .map(|name| {
+                ProtobufPath::from(name.as_bytes())
+                    .as_path()
+                    .as_ref()
+                    .to_path_buf()
+            })
+            .collect())
+    }
+
+    unsafe_ffi_conversions!(ffi::SimpleDescriptorDatabase);
+}
+
+impl DescriptorDatabase for SimpleDescriptorDatabase {
+    fn find_file_by_name(
+        self: Pin<&mut Self>,
+        filename: &Path,
+    ) -> Result<Pin<Box<FileDescriptorProto>>, OperationFailedError> {
+        let mut fd = FileDescriptorProto::new();
+        let_cxx_string!(filename = ProtobufPath::from(filename));
+        unsafe {
+            self.as_ffi_mut()
+                .FindFileByName(&filename, fd.as_mut().as_ffi_mut_ptr())
+                .as_result()?;
+        }
+        Ok(fd)
+    }
+
+    fn find_file_containing_symbol(
+        self: Pin<&mut Self>,
+        symbol_name: &str,
+    ) -> Result<Pin<Box<FileDescriptorProto>>, OperationFailedError> {
+        let mut fd = FileDescriptorProto::new();
+        let_cxx_string!(symbol_name = symbol_name);
+        unsafe {
+            self.as_ffi_mut()
+                .FindFileContainingSymbol(&symbol_name, fd.as_mut().as_ffi_mut_ptr())
+                .as_result()?;
+        }
+        Ok(fd)
+    }
+}
+
+/// A [`DescriptorDatabase`] which stores serialized `FileDescriptorProto`s
+/// and parses them only when they are needed.
+///
+/// Files are indexed by name and by the symbols they define when they are
+/// added, but are otherwise left in their encoded form. A file is only parsed
+/// when a lookup returns it, which makes this database well suited to large
+/// collections of files of which only a few are used.
+///
+/// Encoded files can either be copied into the database with
+/// [`EncodedDescriptorDatabase::add`], or borrowed with
+/// [`EncodedDescriptorDatabase::add_borrowed`]. Borrowed buffers must outlive
+/// the database, which is enforced by the lifetime parameter `'a`.
+pub struct EncodedDescriptorDatabase<'a> {
+    _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> Drop for EncodedDescriptorDatabase<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteEncodedDescriptorDatabase(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl<'a> EncodedDescriptorDatabase<'a> {
+    /// Creates a new, empty database.
+    pub fn new() -> Pin<Box<EncodedDescriptorDatabase<'a>>> {
+        let db = ffi::NewEncodedDescriptorDatabase();
+        unsafe { Self::from_ffi_owned(db) }
+    }
+
+    /// Adds a serialized `FileDescriptorProto` to the database, copying the
+    /// bytes so that the caller need not keep them around.
+    ///
+    /// Returns an error if the bytes cannot be indexed or if the file
+    /// conflicts with a file already in the database.
+    pub fn add(self: Pin<&mut Self>, bytes: &[u8]) -> Result<(), OperationFailedError> {
+        ffi::EncodedDescriptorDatabaseAddCopy(self.as_ffi_mut(), bytes).as_result()
+    }
+
+    /// Like [`EncodedDescriptorDatabase::add`], but borrows the bytes rather
+    /// than copying them.
+    ///
+    /// The bytes must remain valid for as long as the database exists.
+    pub fn add_borrowed(self: Pin<&mut Self>, bytes: &'a [u8]) -> Result<(), OperationFailedError> {
+        ffi::EncodedDescriptorDatabaseAdd(self.as_ffi_mut(), bytes).as_result()
+    }
+
+    unsafe_ffi_conversions!(ffi::EncodedDescriptorDatabase);
+}
+
+impl<'a> DescriptorDatabase for EncodedDescriptorDatabase<'a> {
+    fn find_file_by_name(
+        self: Pin<&mut Self>,
+        filename: &Path,
+    ) -> Result<Pin<Box<FileDescriptorProto>>, OperationFailedError> {
+        let mut fd = FileDescriptorProto::new();
+        let_cxx_string!(filename = ProtobufPath::from(filename));
+        unsafe {
+            self.as_ffi_mut()
+                .FindFileByName(&filename, fd.as_mut().as_ffi_mut_ptr())
+                .as_result()?;
+        }
+        Ok(fd)
+    }
+
+    fn find_file_containing_symbol(
+        self: Pin<&mut Self>,
+        symbol_name: &str,
+    ) -> Result<Pin<Box<FileDescriptorProto>>, OperationFailedError> {
+        let mut fd = FileDescriptorProto::new();
+        let_cxx_string!(symbol_name = symbol_name);
+        unsafe {
+            self.as_ffi_mut()
+                .FindFileContainingSymbol(&symbol_name, fd.as_mut().as_ffi_mut_ptr())
+                .as_result()?;
+        }
+        Ok(fd)
+    }
+}
+
+/// A [`DescriptorDatabase`] which merges several other databases.
+///
+/// Lookups are performed against each database in order, and the first
+/// database that contains the requested file or symbol wins. This makes it
+/// possible to layer a set of overriding files on top of a base set.
+///
+/// When a symbol is found in a file in one database, but an earlier database
+/// contains a file of the same name, the symbol is ignored, as that file has
+/// been overridden by the earlier database.
+///
+/// The merged databases must be `Send`, so that the merged database can back a
+/// [`DescriptorPool`].
+pub struct MergedDescriptorDatabase<'a> {
+    sources: Vec<Pin<&'a mut (dyn DescriptorDatabase + Send)>>,
+}
+
+impl<'a> MergedDescriptorDatabase<'a> {
+    /// Creates a new database that merges the given databases, in order of
+    /// decreasing precedence.
+    pub fn new<I>(sources: I) -> Pin<Box<MergedDescriptorDatabase<'a>>>
+    where
+        I: IntoIterator<Item = Pin<&'a mut (dyn DescriptorDatabase + Send)>>,
+    {
+        Box::pin(MergedDescriptorDatabase {
+            sources: sources.into_iter().collect(),
+        })
+    }
+}
+
+impl<'a> DescriptorDatabase for MergedDescriptorDatabase<'a> {
+    fn find_file_by_name(
+        self: Pin<&mut Self>,
+        filename: &Path,
+    ) -> Result<Pin<Box<FileDescriptorProto>>, OperationFailedError> {
+        for source in &mut self.get_mut().sources {
+            if let Ok(file) = source.as_mut().find_file_by_name(filename) {
+                return Ok(file);
+            }
+        }
+        Err(OperationFailedError::new())
+    }
+
+    fn find_file_containing_symbol(
+        self: Pin<&mut Self>,
+        symbol_name: &str,
+    ) -> Result<Pin<Box<FileDescriptorProto>>, OperationFailedError> {
+        let sources = &mut self.get_mut().sources;
+        for i in 0..sources.len() {
+            if let Ok(file) = sources[i].as_mut().find_file_containing_symbol(symbol_name) {
+                let filename = ProtobufPath::from(file.as_ffi().name().as_bytes());
+                let overridden = sources[..i].iter_mut().any(|source| {
+                    source
+                        .as_mut()
+                        .find_file_by_name(filename.as_path().as_ref())
+                        .is_ok()
+                });
+                if !overridden {
+                    return Ok(file);
+                }
+            }
+        }
+        Err(OperationFailedError::new())
+    }
 }
 
 /// Describes a whole .proto file.
@@ -186,11 +753,89 @@ pub trait DescriptorDatabase {
 /// To get the `FileDescriptor` for a compiled-in file, get the descriptor for
 /// something defined in that file and call `descriptor.file()`. Use
 /// `DescriptorPool` to construct your own descriptors.
+///
+/// Like all descriptors, a `FileDescriptor` is immutable once built, and so
+/// may be shared between threads.
 pub struct FileDescriptor {
     _opaque: PhantomPinned,
 }
 
 impl FileDescriptor {
+    /// Returns the filename, relative to the source tree.
+    ///
+    /// For example, `"foo/bar/baz.proto"`.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Returns the package name declared in the file, e.g. `"foo.bar.baz"`.
+    ///
+    /// Returns an empty slice if the file does not declare a package.
+    pub fn package(&self) -> &[u8] {
+        self.as_ffi().package().as_bytes()
+    }
+
+    /// Returns the number of files imported by this file.
+    pub fn dependency_count(&self) -> usize {
+        self.as_ffi().dependency_count().expect_usize()
+    }
+
+    /// Returns the `i`th file imported by this file, where `i` is between 0
+    /// and [`FileDescriptor::dependency_count`].
+    ///
+    /// These are returned in the order they were imported in the .proto file.
+    pub fn dependency(&self, i: usize) -> &FileDescriptor {
+        if i >= self.dependency_count() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.dependency_count(),
+                i
+            );
+        }
+        unsafe { FileDescriptor::from_ffi_ptr(self.as_ffi().dependency(CInt::expect_from(i))) }
+    }
+
+    /// Returns the number of top-level message types defined in this file.
+    ///
+    /// This does not include nested types.
+    pub fn message_type_count(&self) -> usize {
+        self.as_ffi().message_type_count().expect_usize()
+    }
+
+    /// Returns the `i`th top-level message type, where `i` is between 0 and
+    /// [`FileDescriptor::message_type_count`].
+    ///
+    /// These are returned in the order they were defined in the .proto file.
+    pub fn message_type(&self, i: usize) -> &Descriptor {
+        if i >= self.message_type_count() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.message_type_count(),
+                i
+            );
+        }
+        unsafe { Descriptor::from_ffi_ptr(self.as_ffi().message_type(CInt::expect_from(i))) }
+    }
+
+    /// Writes the contents of this descriptor into the given file descriptor
+    /// proto.
+    ///
+    /// The proto should be empty. Otherwise, the descriptor's contents are
+    /// merged into the existing contents, which may produce an invalid proto.
+    /// Source code info is not copied.
+    pub fn copy_to(&self, proto: Pin<&mut FileDescriptorProto>) {
+        unsafe { self.as_ffi().CopyTo(proto.as_ffi_mut_ptr()) }
+    }
+
+    /// Converts this descriptor into a new file descriptor proto.
+    ///
+    /// See [`FileDescriptor::copy_to`] for details.
+    pub fn to_proto(&self) -> Pin<Box<FileDescriptorProto>> {
+        let mut proto = FileDescriptorProto::new();
+        self.copy_to(proto.as_mut());
+        proto
+    }
+
     unsafe_ffi_conversions!(ffi::FileDescriptor);
 }
 
@@ -224,32 +869,244 @@ impl Drop for FileDescriptor {
 ///
 /// You can also search for descriptors within a `DescriptorPool` by name, and
 /// extensions by number.
-pub struct DescriptorPool {
+///
+/// # Thread safety
+///
+/// A `DescriptorPool` can be sent to and shared between threads. Methods that
+/// take `&self`, like [`DescriptorPool::find_file_by_name`], may be called
+/// concurrently, and the descriptors they return may be used from any thread.
+/// If the pool is backed by a [`DescriptorDatabase`], the pool serializes its
+/// calls into the database with an internal mutex.
+pub struct DescriptorPool<'a> {
     _opaque: PhantomPinned,
+    _lifetime: PhantomData<&'a ()>,
+    // The pool accesses its database, if any, on whichever thread performs a
+    // lookup, so the pool is only `Send` because the database is.
+    _database: PhantomData<Box<dyn DescriptorDatabase + Send + 'a>>,
 }
 
-impl Drop for DescriptorPool {
+// SAFETY: libprotobuf guarantees that the const methods of a `DescriptorPool`
+// are thread safe. Descriptors are immutable once built, and a pool backed by
+// a database holds a mutex while loading files from it, so the database, which
+// is `Send`, is never accessed from two threads at once. This includes the pool
+// owned by a `compiler::Importer`, whose database loads files from a source
+// tree and reports errors to an error collector; both traits require `Send`.
+unsafe impl Sync for DescriptorPool<'_> {}
+
+impl<'a> Drop for DescriptorPool<'a> {
     fn drop(&mut self) {
         unsafe { ffi::DeleteDescriptorPool(self.as_ffi_mut_ptr_unpinned()) }
     }
 }
 
-impl DescriptorPool {
-    pub fn new() -> Pin<Box<DescriptorPool>> {
+impl DescriptorPool<'static> {
+    /// Creates a normal, empty descriptor pool.
+    pub fn new() -> Pin<Box<DescriptorPool<'static>>> {
         let pool = ffi::NewDescriptorPool();
         unsafe { Self::from_ffi_owned(pool) }
     }
 
+    /// Returns the pool containing the descriptors of the message types that
+    /// are compiled into libprotobuf, like those in `descriptor.proto` and the
+    /// well-known types.
+    ///
+    /// Messages of these types can be created with
+    /// [`Descriptor::new_generated_message`].
+    pub fn generated_pool() -> &'static DescriptorPool<'static> {
+        unsafe { Self::from_ffi_ptr(ffi::DescriptorPoolGeneratedPool()) }
+    }
+
+    /// Creates a descriptor pool containing all of the files in the given
+    /// `FileDescriptorSet`.
+    ///
+    /// The files may appear in the set in any order; each file is built after
+    /// the files it depends on. Returns an error as soon as a file fails to
+    /// build, e.g. because it is invalid or because one of its dependencies is
+    /// missing from the set.
+    pub fn build_from_file_descriptor_set(
+        set: &FileDescriptorSet,
+    ) -> Result<Pin<Box<DescriptorPool<'static>>>, OperationFailedError> {
+        let files: HashMap<_, _> = (0..set.file_size())
+            .map(|i| (set.file(i).as_ffi().name().as_bytes(), set.file(i)))
+            .collect();
+        let mut pool = DescriptorPool::new();
+        let mut visited = HashSet::new();
+        for i in 0..set.file_size() {
+            pool.as_mut()
+                .build_file_and_dependencies(set.file(i), &files, &mut visited)?;
+        }
+        Ok(pool)
+    }
+
+    fn build_file_and_dependencies<'b>(
+        mut self: Pin<&mut Self>,
+        file: &'b FileDescriptorProto,
+        files: &HashMap<&[u8], &'b FileDescriptorProto>,
+        visited: &mut HashSet<&'b [u8]>,
+    ) -> Result<(), OperationFailedError> {
+        if !visited.insert(file.as_ffi().name().as_bytes()) {
+            return Ok(());
+        }
+        for i in 0..file.dependency_size() {
+            if let Some(dependency) = files.get(file.dependency(i)) {
+                self.as_mut()
+                    .build_file_and_dependencies(dependency, files, visited)?;
+            }
+        }
+        self.build_file(file)?;
+        Ok(())
+    }
+}
+
+impl<'a> DescriptorPool<'a> {
+    /// Creates a descriptor pool which loads descriptors on demand from the
+    /// given [`DescriptorDatabase`].
+    ///
+    /// Whenever the pool is asked to find a file or symbol that it has not yet
+    /// loaded, it asks the database for the corresponding
+    /// [`FileDescriptorProto`] and builds it, along with any of its
+    /// dependencies, into the pool. Lookups of symbols will only succeed
+    /// before their file has been loaded if the database implements
+    /// [`DescriptorDatabase::find_file_containing_symbol`].
+    ///
+    /// Files cannot be added to such a pool with
+    /// [`DescriptorPool::build_file`]. The database must be `Send`, as the
+    /// pool may load files from it on any thread that the pool is used from.
+    pub fn with_database(
+        database: Pin<&'a mut (dyn DescriptorDatabase + Send)>,
+    ) -> Pin<Box<DescriptorPool<'a>>> {
+        let adaptor = Box::new(DescriptorDatabaseAdaptor(database));
+        let pool = ffi::NewDescriptorPoolWithDatabase(adaptor);
+        unsafe { Self::from_ffi_owned(pool) }
+    }
+
     /// Converts the `FileDescriptorProto` to real descriptors and places them
     /// in this descriptor pool.
     ///
     /// All dependencies of the file must already be in the pool. Returns the
-    /// resulting [`FileDescriptor`], or `None` if there were problems with the
-    /// input (e.g. the message was invalid, or dependencies were missing).
-    /// Details about the errors are written to the error log.
-    pub fn build_file(self: Pin<&mut Self>, proto: &FileDescriptorProto) -> &FileDescriptor {
-        let file = self.as_ffi_mut().BuildFile(proto.as_ffi());
-        unsafe { FileDescriptor::from_ffi_ptr(file) }
+    /// resulting [`FileDescriptor`], or an error if there were problems with
+    /// the input (e.g. the message was invalid, or dependencies were missing).
+    /// Use [`DescriptorPool::build_file_collecting_errors`] to learn the
+    /// details of any problems.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this pool was created with [`DescriptorPool::with_database`].
+    pub fn build_file(
+        self: Pin<&mut Self>,
+        proto: &FileDescriptorProto,
+    ) -> Result<&FileDescriptor, OperationFailedError> {
+        if ffi::DescriptorPoolHasDatabase(self.as_ffi()) {
+            panic!("cannot build files in a DescriptorPool backed by a DescriptorDatabase");
+        }
+        let (file, message) = log::capture_errors(|| self.as_ffi_mut().BuildFile(proto.as_ffi()));
+        (!file.is_null())
+            .then(|| unsafe { FileDescriptor::from_ffi_ptr(file) })
+            .ok_or(OperationFailedError { message })
+    }
+
+    /// Like [`DescriptorPool::build_file`], but reports any problems with the
+    /// input to the given [`MultiFileErrorCollector`].
+    ///
+    /// Problems are reported against the name of the file being built with a
+    /// line number of -1, as the descriptor pool does not know where in the
+    /// .proto file each problem occurred.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this pool was created with [`DescriptorPool::with_database`].
+    pub fn build_file_collecting_errors(
+        self: Pin<&mut Self>,
+        proto: &FileDescriptorProto,
+        error_collector: Pin<&mut dyn MultiFileErrorCollector>,
+    ) -> Result<&FileDescriptor, OperationFailedError> {
+        if ffi::DescriptorPoolHasDatabase(self.as_ffi()) {
+            panic!("cannot build files in a DescriptorPool backed by a DescriptorDatabase");
+        }
+        // Errors reported to the collector are not logged, so record them
+        // separately to attach to the returned error.
+        let_cxx_string!(errors = "");
+        let (file, message) = log::capture_errors(|| unsafe {
+            ffi::DescriptorPoolBuildFileCollectingErrors(
+                self.as_ffi_mut(),
+                proto.as_ffi(),
+                error_collector.upcast_mut_ptr(),
+                errors.as_mut(),
+            )
+        });
+        let message =
+            message.or_else(|| (!errors.is_empty()).then(|| errors.to_string_lossy().into_owned()));
+        (!file.is_null())
+            .then(|| unsafe { FileDescriptor::from_ffi_ptr(file) })
+            .ok_or(OperationFailedError { message })
+    }
+
+    /// Finds a file by its name.
+    ///
+    /// Returns `None` if no file with the given name has been built in the
+    /// pool.
+    pub fn find_file_by_name(&self, name: &str) -> Option<&FileDescriptor> {
+        let_cxx_string!(name = name);
+        let file = self.as_ffi().FindFileByName(&name);
+        (!file.is_null()).then(|| unsafe { FileDescriptor::from_ffi_ptr(file) })
+    }
+
+    /// Finds the file that defines the given fully-qualified symbol.
+    ///
+    /// The symbol may name a message, enum, service, field, enum value, or
+    /// method, among others. Returns `None` if no file in the pool defines
+    /// the symbol.
+    pub fn find_file_containing_symbol(&self, symbol_name: &str) -> Option<&FileDescriptor> {
+        let_cxx_string!(symbol_name = symbol_name);
+        let file = self.as_ffi().FindFileContainingSymbol(&symbol_name);
+        (!file.is_null()).then(|| unsafe { FileDescriptor::from_ffi_ptr(file) })
+    }
+
+    /// Finds a message type by its fully-qualified name.
+    ///
+    /// This will find both top-level and nested message types. Returns `None`
+    /// if no such message type exists in the pool.
+    pub fn find_message_type_by_name(&self, name: &str) -> Option<&Descriptor> {
+        let_cxx_string!(name = name);
+        let descriptor = self.as_ffi().FindMessageTypeByName(&name);
+        (!descriptor.is_null()).then(|| unsafe { Descriptor::from_ffi_ptr(descriptor) })
+    }
+
+    /// Finds an enum type by its fully-qualified name.
+    ///
+    /// This will find both top-level and nested enum types. Returns `None` if
+    /// no such enum type exists in the pool.
+    pub fn find_enum_type_by_name(&self, name: &str) -> Option<&EnumDescriptor> {
+        let_cxx_string!(name = name);
+        let descriptor = self.as_ffi().FindEnumTypeByName(&name);
+        (!descriptor.is_null()).then(|| unsafe { EnumDescriptor::from_ffi_ptr(descriptor) })
+    }
+
+    /// Finds an extension of the given type by number.
+    ///
+    /// The extendee must be a member of this `DescriptorPool`. Returns `None`
+    /// if no such extension exists.
+    pub fn find_extension_by_number(
+        &self,
+        containing_type: &Descriptor,
+        number: i32,
+    ) -> Option<&FieldDescriptor> {
+        let field = unsafe {
+            self.as_ffi()
+                .FindExtensionByNumber(containing_type.as_ffi(), CInt(number))
+        };
+        (!field.is_null()).then(|| unsafe { FieldDescriptor::from_ffi_ptr(field) })
+    }
+
+    /// Finds all extensions of the given type.
+    ///
+    /// The extensions are returned in an undefined order. Only extensions
+    /// defined directly in this `DescriptorPool` are guaranteed to be found.
+    pub fn find_all_extensions(&self, containing_type: &Descriptor) -> Vec<&FieldDescriptor> {
+        ffi::DescriptorPoolFindAllExtensions(self.as_ffi(), containing_type.as_ffi())
+            .into_iter()
+            .map(|field| unsafe { FieldDescriptor::from_ffi_ptr(field.ptr) })
+            .collect()
     }
 
     unsafe_ffi_conversions!(ffi::DescriptorPool);
@@ -262,24 +1119,274 @@ impl DescriptorPool {
 /// [`Message::get_descriptor`]. Generated message classes also have a static
 /// method called `descriptor` which returns the type's descriptor. Use
 /// [`DescriptorPool`] to construct your own descriptors.
-pub struct Descriptor {}
+pub struct Descriptor {
+    _opaque: PhantomPinned,
+}
 
-/// Interface to light weight protocol messages.
-///
-/// This interface is implemented by all protocol message objects.  Non-lite
-/// messages additionally implement the [`Message`] interface, which is a
-/// subclass of `MessageLite`. Use `MessageLite` instead when you only need the
-/// subset of features which it supports -- namely, nothing that uses
-/// descriptors or reflection. You can instruct the protocol compiler to
-/// generate classes which implement only `MessageLite`, not the full `Message`
-/// interface, by adding the following line to the .proto file:
-///
-/// ```proto
-/// option optimize_for = LITE_RUNTIME;
-/// ```
-///
-/// This is particularly useful on resource-constrained systems where the full
-/// protocol buffers runtime library is too big.
+impl Descriptor {
+    /// Returns the name of the message type, not including its scope.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Returns the fully-qualified name of the message type, scope delimited
+    /// by periods.
+    ///
+    /// For example, message type `Foo` which is declared in package `bar` has
+    /// full name `bar.Foo`. If a type `Baz` is nested within `Foo`, `Baz`'s
+    /// full name is `bar.Foo.Baz`.
+    pub fn full_name(&self) -> &[u8] {
+        self.as_ffi().full_name().as_bytes()
+    }
+
+    /// Returns the number of fields in this message type.
+    pub fn field_count(&self) -> usize {
+        self.as_ffi().field_count().expect_usize()
+    }
+
+    /// Gets a field by index, where `i` is between 0 and
+    /// [`Descriptor::field_count`].
+    ///
+    /// These are returned in the order they were defined in the .proto file.
+    pub fn field(&self, i: usize) -> &FieldDescriptor {
+        if i >= self.field_count() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.field_count(),
+                i
+            );
+        }
+        unsafe { FieldDescriptor::from_ffi_ptr(self.as_ffi().field(CInt::expect_from(i))) }
+    }
+
+    /// Returns the number of nested types in this message type.
+    pub fn nested_type_count(&self) -> usize {
+        self.as_ffi().nested_type_count().expect_usize()
+    }
+
+    /// Gets a nested type by index, where `i` is between 0 and
+    /// [`Descriptor::nested_type_count`].
+    ///
+    /// These are returned in the order they were defined in the .proto file.
+    pub fn nested_type(&self, i: usize) -> &Descriptor {
+        if i >= self.nested_type_count() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.nested_type_count(),
+                i
+            );
+        }
+        unsafe { Descriptor::from_ffi_ptr(self.as_ffi().nested_type(CInt::expect_from(i))) }
+    }
+
+    /// Returns the .proto file in which this message type was defined.
+    pub fn file(&self) -> &FileDescriptor {
+        unsafe { FileDescriptor::from_ffi_ptr(self.as_ffi().file()) }
+    }
+
+    /// Creates a new, empty message of this type.
+    ///
+    /// Returns `None` unless this descriptor belongs to the
+    /// [generated pool](DescriptorPool::generated_pool), as libprotobuf only
+    /// has implementations for the message types compiled into it.
+    pub fn new_generated_message(&self) -> Option<Pin<Box<dyn Message>>> {
+        let message = ffi::NewGeneratedMessage(self.as_ffi());
+        (!message.is_null()).then(|| unsafe { DynMessage::from_ffi_owned(message) as _ })
+    }
+
+    /// Writes the contents of this descriptor into the given descriptor proto.
+    ///
+    /// Nested types are included. As with [`FileDescriptor::copy_to`], the
+    /// proto should be empty, and type names in the proto are fully
+    /// qualified.
+    pub fn copy_to(&self, proto: Pin<&mut DescriptorProto>) {
+        unsafe { self.as_ffi().CopyTo(proto.as_ffi_mut_ptr()) }
+    }
+
+    /// Converts this descriptor into a new descriptor proto.
+    ///
+    /// See [`Descriptor::copy_to`] for details.
+    pub fn to_proto(&self) -> Pin<Box<DescriptorProto>> {
+        let mut proto = DescriptorProto::new();
+        self.copy_to(proto.as_mut());
+        proto
+    }
+
+    unsafe_ffi_conversions!(ffi::Descriptor);
+}
+
+/// Describes an enum type defined in a .proto file.
+///
+/// To get the `EnumDescriptor` for a given enum type, look it up in a
+/// [`DescriptorPool`] with [`DescriptorPool::find_enum_type_by_name`].
+pub struct EnumDescriptor {
+    _opaque: PhantomPinned,
+}
+
+impl EnumDescriptor {
+    /// Returns the name of the enum type, not including its scope.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Returns the fully-qualified name of the enum type, scope delimited by
+    /// periods.
+    ///
+    /// See [`Descriptor::full_name`] for details.
+    pub fn full_name(&self) -> &[u8] {
+        self.as_ffi().full_name().as_bytes()
+    }
+
+    /// Returns the .proto file in which this enum type was defined.
+    pub fn file(&self) -> &FileDescriptor {
+        unsafe { FileDescriptor::from_ffi_ptr(self.as_ffi().file()) }
+    }
+
+    unsafe_ffi_conversions!(ffi::EnumDescriptor);
+}
+
+/// Describes a single field of a message.
+///
+/// To get the descriptor for a given field, first get the [`Descriptor`] for
+/// the message in which it is defined, then call [`Descriptor::field`].
+pub struct FieldDescriptor {
+    _opaque: PhantomPinned,
+}
+
+impl FieldDescriptor {
+    /// Returns the name of this field.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Returns the fully-qualified name of this field.
+    pub fn full_name(&self) -> &[u8] {
+        self.as_ffi().full_name().as_bytes()
+    }
+
+    /// Returns the declared tag number of this field.
+    pub fn number(&self) -> i32 {
+        self.as_ffi().number().0
+    }
+
+    /// Returns the declared type of this field.
+    pub fn type_(&self) -> FieldType {
+        FieldType::from_ffi(ffi::FieldDescriptorGetType(self.as_ffi()))
+    }
+
+    /// Reports whether this field is a proto2 group.
+    ///
+    /// Groups are encoded on the wire as tag-delimited messages, rather than
+    /// as length-delimited messages.
+    pub fn is_group(&self) -> bool {
+        self.type_() == FieldType::Group
+    }
+
+    /// Reports whether this field is an extension.
+    pub fn is_extension(&self) -> bool {
+        self.as_ffi().is_extension()
+    }
+
+    /// Reports whether this field is repeated.
+    pub fn is_repeated(&self) -> bool {
+        self.as_ffi().is_repeated()
+    }
+
+    /// Returns the message type of which this field is a member.
+    ///
+    /// For extensions, this is the extended type.
+    pub fn containing_type(&self) -> &Descriptor {
+        unsafe { Descriptor::from_ffi_ptr(self.as_ffi().containing_type()) }
+    }
+
+    unsafe_ffi_conversions!(ffi::FieldDescriptor);
+}
+
+/// Identifies the declared type of a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    /// `double`, exactly eight bytes on the wire.
+    Double,
+    /// `float`, exactly four bytes on the wire.
+    Float,
+    /// `int64`, varint on the wire. Negative numbers take 10 bytes. Use
+    /// `Sint64` if negative values are likely.
+    Int64,
+    /// `uint64`, varint on the wire.
+    Uint64,
+    /// `int32`, varint on the wire. Negative numbers take 10 bytes. Use
+    /// `Sint32` if negative values are likely.
+    Int32,
+    /// `uint64`, exactly eight bytes on the wire.
+    Fixed64,
+    /// `uint32`, exactly four bytes on the wire.
+    Fixed32,
+    /// `bool`, varint on the wire.
+    Bool,
+    /// UTF-8 text.
+    String,
+    /// Tag-delimited message. Deprecated.
+    Group,
+    /// Length-delimited message.
+    Message,
+    /// Arbitrary byte array.
+    Bytes,
+    /// `uint32`, varint on the wire.
+    Uint32,
+    /// Enum, varint on the wire.
+    Enum,
+    /// `int32`, exactly four bytes on the wire.
+    Sfixed32,
+    /// `int64`, exactly eight bytes on the wire.
+    Sfixed64,
+    /// `int32`, ZigZag-encoded varint on the wire.
+    Sint32,
+    /// `int64`, ZigZag-encoded varint on the wire.
+    Sint64,
+}
+
+impl FieldType {
+    fn from_ffi(ty: CInt) -> FieldType {
+        match ty.0 {
+            1 => FieldType::Double,
+            2 => FieldType::Float,
+            3 => FieldType::Int64,
+            4 => FieldType::Uint64,
+            5 => FieldType::Int32,
+            6 => FieldType::Fixed64,
+            7 => FieldType::Fixed32,
+            8 => FieldType::Bool,
+            9 => FieldType::String,
+            10 => FieldType::Group,
+            11 => FieldType::Message,
+            12 => FieldType::Bytes,
+            13 => FieldType::Uint32,
+            14 => FieldType::Enum,
+            15 => FieldType::Sfixed32,
+            16 => FieldType::Sfixed64,
+            17 => FieldType::Sint32,
+            18 => FieldType::Sint64,
+            _ => panic!("unknown field type: {}", ty.0),
+        }
+    }
+}
+
+/// Interface to light weight protocol messages.
+///
+/// This interface is implemented by all protocol message objects.  Non-lite
+/// messages additionally implement the [`Message`] interface, which is a
+/// subclass of `MessageLite`. Use `MessageLite` instead when you only need the
+/// subset of features which it supports -- namely, nothing that uses
+/// descriptors or reflection. You can instruct the protocol compiler to
+/// generate classes which implement only `MessageLite`, not the full `Message`
+/// interface, by adding the following line to the .proto file:
+///
+/// ```proto
+/// option optimize_for = LITE_RUNTIME;
+/// ```
+///
+/// This is particularly useful on resource-constrained systems where the full
+/// protocol buffers runtime library is too big.
 ///
 /// Note that on non-constrained systems (e.g. servers) when you need to link in
 /// lots of protocol definitions, a better way to reduce total code footprint is
@@ -324,11 +1431,153 @@ pub trait MessageLite: private::MessageLite {
         self: Pin<&mut Self>,
         input: Pin<&mut CodedInputStream>,
     ) -> Result<(), OperationFailedError> {
-        unsafe {
+        OperationFailedError::capture(|| unsafe {
             self.upcast_mut()
                 .MergeFromCodedStream(input.as_ffi_mut_ptr())
-                .as_result()
+        })
+    }
+
+    /// Parses a protocol buffer contained in a byte slice, replacing the
+    /// current contents of this message.
+    ///
+    /// Returns an error if the bytes do not contain exactly one valid message.
+    fn parse_from_bytes(
+        mut self: Pin<&mut Self>,
+        bytes: &[u8],
+    ) -> Result<(), OperationFailedError> {
+        self.as_mut().clear();
+        self.merge_from_bytes(bytes)
+    }
+
+    /// Parses a protocol buffer contained in a byte slice and merges it into
+    /// this message.
+    ///
+    /// Singular fields read from the bytes overwrite what is already in the
+    /// message and repeated fields are appended to those already present.
+    ///
+    /// Returns an error if the bytes do not contain exactly one valid message.
+    fn merge_from_bytes(self: Pin<&mut Self>, bytes: &[u8]) -> Result<(), OperationFailedError> {
+        self.merge_from_zero_copy_stream(SliceInputStream::new(bytes).as_mut())
+    }
+
+    /// Parses a protocol buffer from the given zero-copy input stream,
+    /// replacing the current contents of this message.
+    ///
+    /// The stream is read until it is exhausted. Returns an error if the
+    /// stream does not contain exactly one valid message.
+    fn parse_from_zero_copy_stream(
+        mut self: Pin<&mut Self>,
+        input: Pin<&mut dyn ZeroCopyInputStream>,
+    ) -> Result<(), OperationFailedError> {
+        self.as_mut().clear();
+        self.merge_from_zero_copy_stream(input)
+    }
+
+    /// Parses a protocol buffer from the given zero-copy input stream and
+    /// merges it into this message.
+    ///
+    /// The stream is read until it is exhausted. Returns an error if the
+    /// stream does not contain exactly one valid message.
+    fn merge_from_zero_copy_stream(
+        self: Pin<&mut Self>,
+        input: Pin<&mut dyn ZeroCopyInputStream>,
+    ) -> Result<(), OperationFailedError> {
+        let mut input = CodedInputStream::new(input);
+        self.merge_from_coded_stream(input.as_mut())?;
+        input.as_mut().consumed_entire_message().as_result()
+    }
+
+    /// Parses a protocol buffer from the given [`Read`] implementor, replacing
+    /// the current contents of this message.
+    ///
+    /// The reader is read until it reaches EOF. Returns an error if the reader
+    /// fails or if its contents are not exactly one valid message.
+    fn parse_from_reader(
+        self: Pin<&mut Self>,
+        input: &mut dyn Read,
+    ) -> Result<(), OperationFailedError> {
+        self.parse_from_zero_copy_stream(ReaderStream::new(input).as_mut())
+    }
+
+    /// Parses a length-delimited message from the given coded input stream,
+    /// replacing the current contents of this message.
+    ///
+    /// The message must be preceded by its size, encoded as a varint, as
+    /// written by [`MessageLite::serialize_delimited_to_coded_stream`].
+    /// Exactly the bytes of the message are consumed from the stream, so
+    /// several messages may be read from the same stream in succession.
+    fn parse_delimited_from_coded_stream(
+        mut self: Pin<&mut Self>,
+        mut input: Pin<&mut CodedInputStream>,
+    ) -> Result<(), OperationFailedError> {
+        let size = input.as_mut().read_varint32()?;
+        let size = usize::try_from(size).map_err(|_| OperationFailedError::new())?;
+        let limit = input.as_mut().push_limit(size);
+        self.as_mut().clear();
+        let mut result = self.merge_from_coded_stream(input.as_mut());
+        if result.is_ok() && !input.as_mut().consumed_entire_message() {
+            result = Err(OperationFailedError::new());
+        }
+        if result.is_err() {
+            // Skip the remainder of the malformed message, so that the stream
+            // is positioned at the start of the next message.
+            if let Some(remaining) = input.bytes_until_limit() {
+                let _ = input.as_mut().skip(remaining);
+            }
+        }
+        input.pop_limit(limit);
+        result
+    }
+
+    /// Reads a length-delimited message from the given [`Read`] implementor,
+    /// replacing the current contents of this message.
+    ///
+    /// The message must be preceded by its size, encoded as a varint, as
+    /// written by [`MessageLite::serialize_delimited_to_writer`] or Java's
+    /// `writeDelimitedTo`. This is the format commonly used to store a
+    /// sequence of messages in a single file.
+    ///
+    /// Returns `Ok(false)` if the reader is at end of file before the first
+    /// byte of the message, and `Ok(true)` if a message was read. No bytes
+    /// past the end of the message are consumed from the reader. Because the
+    /// size prefix is read one byte at a time, consider wrapping unbuffered
+    /// readers in a [`BufReader`](std::io::BufReader).
+    ///
+    /// A truncated message produces an error of kind
+    /// [`UnexpectedEof`](std::io::ErrorKind::UnexpectedEof), and a message
+    /// that cannot be parsed produces an error of kind
+    /// [`InvalidData`](std::io::ErrorKind::InvalidData).
+    fn parse_delimited_from_reader(
+        self: Pin<&mut Self>,
+        input: &mut dyn Read,
+    ) -> Result<bool, std::io::Error> {
+        let mut prefix = LengthPrefixDecoder::default();
+        let len = loop {
+            let mut byte = [0];
+            match input.read_exact(&mut byte) {
+                Ok(()) => (),
+                Err(e) if prefix.is_empty() && e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return Ok(false)
+                }
+                Err(e) => return Err(e),
+            }
+            if let Some(len) = prefix.push(byte[0])? {
+                break len;
+            }
+        };
+        // The length is untrusted, so let the buffer grow as bytes arrive rather
+        // than allocating it up front.
+        let mut buf = vec![];
+        input.take(len as u64).read_to_end(&mut buf)?;
+        if buf.len() != len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "message truncated",
+            ));
         }
+        self.parse_from_bytes(&buf)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(true)
     }
 
     /// Writes a protocol buffer of this message to the given output.
@@ -359,6 +1608,45 @@ pub trait MessageLite: private::MessageLite {
         }
     }
 
+    /// Writes the size of the message, encoded as a varint, followed by the
+    /// message to the given output.
+    ///
+    /// Messages written in this way can be read back with
+    /// [`MessageLite::parse_delimited_from_coded_stream`].
+    ///
+    /// All required fields must be set.
+    fn serialize_delimited_to_coded_stream(
+        &self,
+        mut output: Pin<&mut CodedOutputStream>,
+    ) -> Result<(), OperationFailedError> {
+        let size = u32::try_from(self.byte_size()).map_err(|_| OperationFailedError::new())?;
+        output.as_mut().write_varint32(size);
+        self.serialize_to_coded_stream(output)
+    }
+
+    /// Writes the size of the message, encoded as a varint, followed by the
+    /// message to the given [`Write`] implementor.
+    ///
+    /// This is the format written by Java's `writeDelimitedTo`. Messages
+    /// written in this way can be read back with
+    /// [`MessageLite::parse_delimited_from_reader`].
+    ///
+    /// All required fields must be set.
+    fn serialize_delimited_to_writer(
+        &self,
+        output: &mut dyn Write,
+    ) -> Result<(), OperationFailedError> {
+        let mut buf = vec![];
+        let mut stream = VecOutputStream::new(&mut buf);
+        let mut coded = CodedOutputStream::new(stream.as_mut());
+        self.serialize_delimited_to_coded_stream(coded.as_mut())?;
+        drop(coded);
+        drop(stream);
+        output
+            .write_all(&buf)
+            .map_err(|e| OperationFailedError::with_message(e.to_string()))
+    }
+
     /// Writes the message to the given [`Write`] implementor.
     ///
     /// All required fields must be set.
@@ -368,10 +1656,52 @@ pub trait MessageLite: private::MessageLite {
 
     /// Serializes the message to a byte vector.
     ///
-    /// All required fields must be set.
+    /// All required fields must be set. If any are missing, the returned error
+    /// lists their paths, as reported by
+    /// [`Message::find_initialization_errors`].
     fn serialize(&self) -> Result<Vec<u8>, OperationFailedError> {
+        if !self.is_initialized() {
+            return Err(OperationFailedError::with_message(
+                ffi::MessageLiteInitializationErrorString(self.upcast()),
+            ));
+        }
+        let mut output = vec![];
+        self.serialize_to_vec(&mut output)?;
+        Ok(output)
+    }
+
+    /// Serializes the message into an existing byte vector, replacing its
+    /// contents.
+    ///
+    /// The vector's existing allocation is reused, so repeatedly serializing
+    /// into the same vector avoids allocating a new buffer for each message.
+    ///
+    /// All required fields must be set.
+    fn serialize_to_vec(&self, output: &mut Vec<u8>) -> Result<(), OperationFailedError> {
+        output.clear();
+        self.serialize_to_zero_copy_stream(VecOutputStream::new(output).as_mut())
+    }
+
+    /// Serializes the message to a byte vector using deterministic
+    /// serialization.
+    ///
+    /// Unlike [`serialize`], which makes no guarantees about the order in which
+    /// map entries are written, this method sorts map entries by key, so equal
+    /// messages always serialize to the same bytes within a given build of
+    /// libprotobuf. See
+    /// [`CodedOutputStream::set_serialization_deterministic`] for details.
+    ///
+    /// All required fields must be set.
+    ///
+    /// [`serialize`]: MessageLite::serialize
+    fn serialize_deterministic(&self) -> Result<Vec<u8>, OperationFailedError> {
         let mut output = vec![];
-        self.serialize_to_writer(&mut output)?;
+        let mut stream = VecOutputStream::new(&mut output);
+        let mut coded = CodedOutputStream::new(stream.as_mut());
+        coded.as_mut().set_serialization_deterministic(true);
+        self.serialize_to_coded_stream(coded.as_mut())?;
+        drop(coded);
+        drop(stream);
         Ok(output)
     }
 
@@ -383,6 +1713,14 @@ pub trait MessageLite: private::MessageLite {
     fn byte_size(&self) -> usize {
         self.upcast().ByteSizeLong()
     }
+
+    /// Returns the fully-qualified name of the message's type, e.g.
+    /// `google.protobuf.FileDescriptorProto`.
+    fn type_name(&self) -> String {
+        let_cxx_string!(output = "");
+        ffi::MessageLiteGetTypeName(self.upcast(), output.as_mut());
+        output.to_string_lossy().into_owned()
+    }
 }
 
 struct DynMessageLite {
@@ -411,6 +1749,44 @@ impl private::MessageLite for DynMessageLite {
     }
 }
 
+struct DynMessage {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for DynMessage {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteMessage(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl DynMessage {
+    unsafe_ffi_conversions!(ffi::Message);
+}
+
+impl MessageLite for DynMessage {}
+
+impl private::MessageLite for DynMessage {
+    fn upcast(&self) -> &ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for DynMessage {}
+
+impl private::Message for DynMessage {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
 /// Abstract interface for protocol messages.
 ///
 /// See also `MessageLite`, which contains most every-day operations.  `Message`
@@ -424,63 +1800,426 @@ impl private::MessageLite for DynMessageLite {
 ///
 /// Users must not derive from this class. Only the protocol compiler and the
 /// internal library are allowed to create subclasses.
-pub trait Message: private::Message + MessageLite {}
-
-/// The protocol compiler can output a file descriptor set containing the .proto
-/// files it parses.
-pub struct FileDescriptorSet {
-    _opaque: PhantomPinned,
-}
-
-impl Drop for FileDescriptorSet {
-    fn drop(&mut self) {
-        unsafe { ffi::DeleteFileDescriptorSet(self.as_ffi_mut_ptr_unpinned()) }
+pub trait Message: private::Message + MessageLite {
+    /// Gets a [`Descriptor`] for this message's type.
+    ///
+    /// This describes what fields the message contains, the types of those
+    /// fields, etc.
+    fn get_descriptor(&self) -> &Descriptor {
+        unsafe { Descriptor::from_ffi_ptr(self.upcast_message().GetDescriptor()) }
     }
-}
 
-impl FileDescriptorSet {
-    /// Creates a a new file descriptor set.
-    fn new() -> Pin<Box<FileDescriptorSet>> {
-        let set = ffi::NewFileDescriptorSet();
-        unsafe { Self::from_ffi_owned(set) }
+    /// Computes the approximate number of bytes of memory used by the message,
+    /// including the message itself.
+    ///
+    /// This is computed via reflection and is only an estimate. It is useful
+    /// for budgeting memory, but should not be relied upon to be exact.
+    fn space_used(&self) -> usize {
+        self.upcast_message().SpaceUsedLong()
     }
 
-    /// Returns the number of file descriptors in the file descriptor set.
-    pub fn file_size(&self) -> usize {
-        self.as_ffi().file_size().expect_usize()
+    /// Returns the paths of all required fields in the message and its
+    /// embedded messages that are not set, e.g. `foo.bar[2].baz`.
+    ///
+    /// The returned vector is empty if and only if the message is
+    /// [initialized](MessageLite::is_initialized).
+    fn find_initialization_errors(&self) -> Vec<String> {
+        ffi::MessageFindInitializationErrors(self.upcast_message())
     }
 
-    /// Clears the file descriptors.
-    pub fn clear_file(self: Pin<&mut Self>) {
-        self.as_ffi_mut().clear_file()
+    /// Reports whether the given singular field is set.
+    ///
+    /// For proto3 fields without presence, this reports whether the field has
+    /// a non-default value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a member of this message's type or if `field`
+    /// is repeated.
+    fn has_field(&self, field: &FieldDescriptor) -> bool {
+        check_singular_field(self.get_descriptor(), field, "has_field", None);
+        unsafe { reflection(self).HasField(self.upcast_message(), field.as_ffi()) }
     }
 
-    /// Returns a reference the `i`th file descriptor.
-    pub fn file(&self, i: usize) -> &FileDescriptorProto {
-        let file = self.as_ffi().file(CInt::expect_from(i));
-        FileDescriptorProto::from_ffi_ref(file)
+    /// Gets the value of the given singular field, which must be an `int32`,
+    /// `sint32`, or `sfixed32` field.
+    ///
+    /// Returns the field's default value if the field is not set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a member of this message's type, if `field` is
+    /// repeated, or if `field` has the wrong type.
+    fn get_field_i32(&self, field: &FieldDescriptor) -> i32 {
+        let types = [FieldType::Int32, FieldType::Sint32, FieldType::Sfixed32];
+        check_singular_field(self.get_descriptor(), field, "get_field_i32", Some(&types));
+        unsafe { reflection(self).GetInt32(self.upcast_message(), field.as_ffi()) }
     }
 
-    /// Returns a mutable reference to the `i`th file descriptor.
-    pub fn file_mut(self: Pin<&mut Self>, i: usize) -> Pin<&mut FileDescriptorProto> {
-        let file = self.as_ffi_mut().mutable_file(CInt::expect_from(i));
-        unsafe { FileDescriptorProto::from_ffi_mut(file) }
+    /// Gets the value of the given singular field, which must be an `int64`,
+    /// `sint64`, or `sfixed64` field.
+    ///
+    /// Returns the field's default value if the field is not set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a member of this message's type, if `field` is
+    /// repeated, or if `field` has the wrong type.
+    fn get_field_i64(&self, field: &FieldDescriptor) -> i64 {
+        let types = [FieldType::Int64, FieldType::Sint64, FieldType::Sfixed64];
+        check_singular_field(self.get_descriptor(), field, "get_field_i64", Some(&types));
+        unsafe { reflection(self).GetInt64(self.upcast_message(), field.as_ffi()) }
     }
 
-    /// Adds a new empty file descriptor and returns a mutable reference to it.
-    pub fn add_file(self: Pin<&mut Self>) -> Pin<&mut FileDescriptorProto> {
-        let file = self.as_ffi_mut().add_file();
-        unsafe { FileDescriptorProto::from_ffi_mut(file) }
+    /// Gets the value of the given singular field, which must be a `uint32` or
+    /// `fixed32` field.
+    ///
+    /// Returns the field's default value if the field is not set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a member of this message's type, if `field` is
+    /// repeated, or if `field` has the wrong type.
+    fn get_field_u32(&self, field: &FieldDescriptor) -> u32 {
+        let types = [FieldType::Uint32, FieldType::Fixed32];
+        check_singular_field(self.get_descriptor(), field, "get_field_u32", Some(&types));
+        unsafe { reflection(self).GetUInt32(self.upcast_message(), field.as_ffi()) }
     }
 
-    unsafe_ffi_conversions!(ffi::FileDescriptorSet);
-}
-
-impl MessageLite for FileDescriptorSet {}
-
-impl private::MessageLite for FileDescriptorSet {
-    fn upcast(&self) -> &ffi::MessageLite {
-        unsafe { mem::transmute(self) }
+    /// Gets the value of the given singular field, which must be a `uint64` or
+    /// `fixed64` field.
+    ///
+    /// Returns the field's default value if the field is not set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a member of this message's type, if `field` is
+    /// repeated, or if `field` has the wrong type.
+    fn get_field_u64(&self, field: &FieldDescriptor) -> u64 {
+        let types = [FieldType::Uint64, FieldType::Fixed64];
+        check_singular_field(self.get_descriptor(), field, "get_field_u64", Some(&types));
+        unsafe { reflection(self).GetUInt64(self.upcast_message(), field.as_ffi()) }
+    }
+
+    /// Gets the value of the given singular field, which must be a `float`
+    /// field.
+    ///
+    /// Returns the field's default value if the field is not set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a member of this message's type, if `field` is
+    /// repeated, or if `field` has the wrong type.
+    fn get_field_f32(&self, field: &FieldDescriptor) -> f32 {
+        let types = [FieldType::Float];
+        check_singular_field(self.get_descriptor(), field, "get_field_f32", Some(&types));
+        unsafe { reflection(self).GetFloat(self.upcast_message(), field.as_ffi()) }
+    }
+
+    /// Gets the value of the given singular field, which must be a `double`
+    /// field.
+    ///
+    /// Returns the field's default value if the field is not set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a member of this message's type, if `field` is
+    /// repeated, or if `field` has the wrong type.
+    fn get_field_f64(&self, field: &FieldDescriptor) -> f64 {
+        let types = [FieldType::Double];
+        check_singular_field(self.get_descriptor(), field, "get_field_f64", Some(&types));
+        unsafe { reflection(self).GetDouble(self.upcast_message(), field.as_ffi()) }
+    }
+
+    /// Gets the value of the given singular field, which must be a `bool`
+    /// field.
+    ///
+    /// Returns the field's default value if the field is not set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a member of this message's type, if `field` is
+    /// repeated, or if `field` has the wrong type.
+    fn get_field_bool(&self, field: &FieldDescriptor) -> bool {
+        let types = [FieldType::Bool];
+        check_singular_field(self.get_descriptor(), field, "get_field_bool", Some(&types));
+        unsafe { reflection(self).GetBool(self.upcast_message(), field.as_ffi()) }
+    }
+
+    /// Gets the numeric value of the given singular enum field.
+    ///
+    /// Returns the field's default value if the field is not set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a member of this message's type, if `field` is
+    /// repeated, or if `field` is not an enum field.
+    fn get_field_enum(&self, field: &FieldDescriptor) -> i32 {
+        let types = [FieldType::Enum];
+        check_singular_field(self.get_descriptor(), field, "get_field_enum", Some(&types));
+        unsafe {
+            reflection(self)
+                .GetEnumValue(self.upcast_message(), field.as_ffi())
+                .0
+        }
+    }
+
+    /// Gets the value of the given singular field, which must be a `string` or
+    /// `bytes` field.
+    ///
+    /// Returns the field's default value if the field is not set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `field` is not a member of this message's type, if `field` is
+    /// repeated, or if `field` has the wrong type.
+    fn get_field_string(&self, field: &FieldDescriptor) -> Vec<u8> {
+        let types = [FieldType::String, FieldType::Bytes];
+        check_singular_field(
+            self.get_descriptor(),
+            field,
+            "get_field_string",
+            Some(&types),
+        );
+        let_cxx_string!(output = "");
+        ffi::ReflectionGetString(self.upcast_message(), field.as_ffi(), output.as_mut());
+        output.as_bytes().to_vec()
+    }
+}
+
+fn reflection<M>(message: &M) -> &ffi::Reflection
+where
+    M: Message + ?Sized,
+{
+    unsafe { &*message.upcast_message().GetReflection() }
+}
+
+/// Verifies that `field` can be accessed via the reflection method `method` on
+/// a message of type `descriptor`.
+///
+/// libprotobuf aborts the process on reflection usage errors, so these
+/// conditions must be checked before calling into C++.
+fn check_singular_field(
+    descriptor: &Descriptor,
+    field: &FieldDescriptor,
+    method: &str,
+    types: Option<&[FieldType]>,
+) {
+    if !ptr::eq(field.containing_type(), descriptor) {
+        panic!(
+            "Message::{}: field {} does not match message type {}",
+            method,
+            String::from_utf8_lossy(field.full_name()),
+            String::from_utf8_lossy(descriptor.full_name()),
+        );
+    }
+    if field.is_repeated() {
+        panic!(
+            "Message::{}: field {} is repeated",
+            method,
+            String::from_utf8_lossy(field.full_name()),
+        );
+    }
+    if let Some(types) = types {
+        if !types.contains(&field.type_()) {
+            panic!(
+                "Message::{}: field {} has type {:?}",
+                method,
+                String::from_utf8_lossy(field.full_name()),
+                field.type_(),
+            );
+        }
+    }
+}
+
+/// Compares two messages for equality using libprotobuf's
+/// `MessageDifferencer`.
+///
+/// Messages of different types are never equal. Otherwise, two messages are
+/// equal if they have the same set of fields with equal values, regardless of
+/// the order in which the fields were serialized. Unknown fields are compared
+/// as well. Floating-point fields are compared exactly, except that NaN is
+/// considered equal to NaN, so that every message is equal to itself.
+impl<'a> PartialEq for dyn Message + 'a {
+    fn eq(&self, other: &Self) -> bool {
+        ffi::MessageEquals(self.upcast_message(), other.upcast_message())
+    }
+}
+
+/// Formats a message in protobuf text format, via libprotobuf's
+/// `DebugString`.
+///
+/// The alternate form (`{:#?}`) prints one field per line, while the default
+/// form prints the entire message on a single line.
+impl<'a> fmt::Debug for dyn Message + 'a {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_message(self.upcast_message(), f)
+    }
+}
+
+fn fmt_message(message: &ffi::Message, f: &mut fmt::Formatter) -> fmt::Result {
+    let_cxx_string!(output = "");
+    ffi::MessageDebugString(message, f.alternate(), output.as_mut());
+    f.write_str(&output.to_string_lossy())
+}
+
+/// Implements [`PartialEq`], [`Eq`], and [`Debug`](fmt::Debug) for a concrete
+/// message type, matching the implementations for `dyn Message`.
+macro_rules! impl_message_traits {
+    ($ty:ty) => {
+        impl PartialEq for $ty {
+            fn eq(&self, other: &Self) -> bool {
+                use $crate::private::Message;
+                $crate::ffi::MessageEquals(self.upcast_message(), other.upcast_message())
+            }
+        }
+
+        impl Eq for $ty {}
+
+        impl std::fmt::Debug for $ty {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                use $crate::private::Message;
+                $crate::fmt_message(self.upcast_message(), f)
+            }
+        }
+    };
+}
+
+pub(crate) use impl_message_traits;
+
+/// The protocol compiler can output a file descriptor set containing the .proto
+/// files it parses.
+pub struct FileDescriptorSet {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for FileDescriptorSet {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteFileDescriptorSet(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl FileDescriptorSet {
+    /// Creates a a new file descriptor set.
+    pub fn new() -> Pin<Box<FileDescriptorSet>> {
+        let set = ffi::NewFileDescriptorSet();
+        unsafe { Self::from_ffi_owned(set) }
+    }
+
+    /// Parses a serialized file descriptor set, like those written by
+    /// `protoc --descriptor_set_out`, into a new file descriptor set.
+    pub fn parse_from_bytes(
+        bytes: &[u8],
+    ) -> Result<Pin<Box<FileDescriptorSet>>, OperationFailedError> {
+        let mut set = FileDescriptorSet::new();
+        MessageLite::parse_from_bytes(set.as_mut(), bytes)?;
+        Ok(set)
+    }
+
+    /// Returns a deep copy of this file descriptor set.
+    ///
+    /// The copy is allocated and populated by libprotobuf, and shares no
+    /// state with the original. Note that `FileDescriptorSet` cannot implement
+    /// [`Clone`], as it can only be accessed behind a pinned pointer.
+    #[allow(clippy::should_implement_trait)]
+    pub fn clone(&self) -> Pin<Box<FileDescriptorSet>> {
+        let mut set = FileDescriptorSet::new();
+        set.as_mut().as_ffi_mut().CopyFrom(self.as_ffi());
+        set
+    }
+
+    /// Returns the number of file descriptors in the file descriptor set.
+    pub fn file_size(&self) -> usize {
+        self.as_ffi().file_size().expect_usize()
+    }
+
+    /// Clears the file descriptors.
+    pub fn clear_file(self: Pin<&mut Self>) {
+        self.as_ffi_mut().clear_file()
+    }
+
+    /// Returns a reference the `i`th file descriptor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn file(&self, i: usize) -> &FileDescriptorProto {
+        if i >= self.file_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.file_size(),
+                i
+            );
+        }
+        let file = self.as_ffi().file(CInt::expect_from(i));
+        FileDescriptorProto::from_ffi_ref(file)
+    }
+
+    /// Returns an iterator over the file descriptors in the set.
+    pub fn iter(&self) -> FileDescriptorSetIter<'_> {
+        FileDescriptorSetIter {
+            set: self,
+            range: 0..self.file_size(),
+        }
+    }
+
+    /// Returns a mutable reference to the `i`th file descriptor.
+    pub fn file_mut(self: Pin<&mut Self>, i: usize) -> Pin<&mut FileDescriptorProto> {
+        let file = self.as_ffi_mut().mutable_file(CInt::expect_from(i));
+        unsafe { FileDescriptorProto::from_ffi_mut(file) }
+    }
+
+    /// Adds a new empty file descriptor and returns a mutable reference to it.
+    pub fn add_file(self: Pin<&mut Self>) -> Pin<&mut FileDescriptorProto> {
+        let file = self.as_ffi_mut().add_file();
+        unsafe { FileDescriptorProto::from_ffi_mut(file) }
+    }
+
+    /// Appends the files in `other` whose names are not already present in
+    /// this set.
+    ///
+    /// This is the usual way to combine descriptor sets, which often share
+    /// common dependencies like protobuf's well-known types, before building
+    /// them into a [`DescriptorPool`].
+    ///
+    /// Returns an error if a file in `other` has the same name as a file in
+    /// this set but different contents. In this case, this set is left
+    /// unchanged.
+    pub fn merge_dedup(
+        mut self: Pin<&mut Self>,
+        other: &FileDescriptorSet,
+    ) -> Result<(), OperationFailedError> {
+        let mut files: HashMap<_, _> = self.iter().map(|file| (file.name(), file)).collect();
+        let mut new_files = vec![];
+        for file in other {
+            match files.entry(file.name()) {
+                Entry::Occupied(existing) if *existing.get() != file => {
+                    return Err(OperationFailedError::with_message(format!(
+                        "file {} is defined differently in both descriptor sets",
+                        String::from_utf8_lossy(file.name())
+                    )));
+                }
+                Entry::Occupied(_) => (),
+                Entry::Vacant(entry) => {
+                    entry.insert(file);
+                    new_files.push(file);
+                }
+            }
+        }
+        for file in new_files {
+            self.as_mut().add_file().copy_from(file);
+        }
+        Ok(())
+    }
+
+    unsafe_ffi_conversions!(ffi::FileDescriptorSet);
+}
+
+impl MessageLite for FileDescriptorSet {}
+
+impl private::MessageLite for FileDescriptorSet {
+    fn upcast(&self) -> &ffi::MessageLite {
+        unsafe { mem::transmute(self) }
     }
 
     fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MessageLite> {
@@ -489,7 +2228,65 @@ impl private::MessageLite for FileDescriptorSet {
 }
 
 impl Message for FileDescriptorSet {}
-impl private::Message for FileDescriptorSet {}
+
+impl_message_traits!(FileDescriptorSet);
+
+impl private::Message for FileDescriptorSet {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Index<usize> for FileDescriptorSet {
+    type Output = FileDescriptorProto;
+
+    fn index(&self, i: usize) -> &FileDescriptorProto {
+        self.file(i)
+    }
+}
+
+impl<'a> IntoIterator for &'a FileDescriptorSet {
+    type Item = &'a FileDescriptorProto;
+    type IntoIter = FileDescriptorSetIter<'a>;
+
+    fn into_iter(self) -> FileDescriptorSetIter<'a> {
+        self.iter()
+    }
+}
+
+/// An iterator over the file descriptors in a [`FileDescriptorSet`].
+///
+/// This struct is created by [`FileDescriptorSet::iter`].
+pub struct FileDescriptorSetIter<'a> {
+    set: &'a FileDescriptorSet,
+    range: Range<usize>,
+}
+
+impl<'a> Iterator for FileDescriptorSetIter<'a> {
+    type Item = &'a FileDescriptorProto;
+
+    fn next(&mut self) -> Option<&'a FileDescriptorProto> {
+        self.range.next().map(|i| self.set.file(i))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for FileDescriptorSetIter<'a> {
+    fn next_back(&mut self) -> Option<&'a FileDescriptorProto> {
+        self.range.next_back().map(|i| self.set.file(i))
+    }
+}
+
+impl<'a> ExactSizeIterator for FileDescriptorSetIter<'a> {}
+
+impl<'a> FusedIterator for FileDescriptorSetIter<'a> {}
 
 /// Describes a complete .proto file.
 pub struct FileDescriptorProto {
@@ -503,6 +2300,18 @@ impl FileDescriptorProto {
         unsafe { Self::from_ffi_owned(proto) }
     }
 
+    /// Returns a deep copy of this file descriptor proto.
+    ///
+    /// The copy is allocated and populated by libprotobuf, and shares no
+    /// state with the original. Note that `FileDescriptorProto` cannot
+    /// implement [`Clone`], as it can only be accessed behind a pinned pointer.
+    #[allow(clippy::should_implement_trait)]
+    pub fn clone(&self) -> Pin<Box<FileDescriptorProto>> {
+        let mut proto = FileDescriptorProto::new();
+        proto.as_mut().copy_from(self);
+        proto
+    }
+
     /// Make this file descriptor proto into a copy of the given file descriptor
     /// proto.
     pub fn copy_from(self: Pin<&mut Self>, from: &FileDescriptorProto) {
@@ -515,6 +2324,35 @@ impl FileDescriptorProto {
         self.as_ffi_mut().MergeFrom(from.as_ffi())
     }
 
+    /// Returns the file name, relative to the root of the source tree.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Sets the file name.
+    pub fn set_name(self: Pin<&mut Self>, value: &str) {
+        let_cxx_string!(value = value);
+        self.as_ffi_mut().set_name(&value)
+    }
+
+    /// Returns the package name, e.g. `foo` or `foo.bar`.
+    pub fn package(&self) -> &[u8] {
+        self.as_ffi().package().as_bytes()
+    }
+
+    /// Sets the package name.
+    pub fn set_package(self: Pin<&mut Self>, value: &str) {
+        let_cxx_string!(value = value);
+        self.as_ffi_mut().set_package(&value)
+    }
+
+    /// Returns the syntax of the file, e.g. `proto2` or `proto3`.
+    ///
+    /// An empty syntax indicates `proto2`.
+    pub fn syntax(&self) -> &[u8] {
+        self.as_ffi().syntax().as_bytes()
+    }
+
     /// Returns the number of entries in the `dependency` field.
     pub fn dependency_size(&self) -> usize {
         self.as_ffi().dependency_size().expect_usize()
@@ -532,6 +2370,12 @@ impl FileDescriptorProto {
         self.as_ffi().dependency(CInt::expect_from(i)).as_bytes()
     }
 
+    /// Appends an entry to the `dependency` field.
+    pub fn add_dependency(self: Pin<&mut Self>, value: &str) {
+        let_cxx_string!(value = value);
+        self.as_ffi_mut().add_dependency(&value)
+    }
+
     /// Returns the number of entries in the `message_type` field.
     pub fn message_type_size(&self) -> usize {
         self.as_ffi().message_type_size().expect_usize()
@@ -549,6 +2393,61 @@ impl FileDescriptorProto {
         DescriptorProto::from_ffi_ref(self.as_ffi().message_type(CInt::expect_from(i)))
     }
 
+    /// Adds a new empty entry to the `message_type` field and returns a mutable
+    /// reference to it.
+    pub fn add_message_type(self: Pin<&mut Self>) -> Pin<&mut DescriptorProto> {
+        let message_type = self.as_ffi_mut().add_message_type();
+        unsafe { DescriptorProto::from_ffi_mut(message_type) }
+    }
+
+    /// Returns the number of entries in the `enum_type` field.
+    pub fn enum_type_size(&self) -> usize {
+        self.as_ffi().enum_type_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `enum_type` field.
+    pub fn enum_type(&self, i: usize) -> &EnumDescriptorProto {
+        if i >= self.enum_type_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.enum_type_size(),
+                i
+            );
+        }
+        EnumDescriptorProto::from_ffi_ref(self.as_ffi().enum_type(CInt::expect_from(i)))
+    }
+
+    /// Returns the number of entries in the `service` field.
+    pub fn service_size(&self) -> usize {
+        self.as_ffi().service_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `service` field.
+    pub fn service(&self, i: usize) -> &ServiceDescriptorProto {
+        if i >= self.service_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.service_size(),
+                i
+            );
+        }
+        ServiceDescriptorProto::from_ffi_ref(self.as_ffi().service(CInt::expect_from(i)))
+    }
+
+    /// Returns the source code info for this file, if present.
+    ///
+    /// The source code info is populated when the file is parsed from a .proto
+    /// file, unless disabled with
+    /// [`SourceTreeDescriptorDatabase::set_retain_source_code_info`].
+    ///
+    /// [`SourceTreeDescriptorDatabase::set_retain_source_code_info`]:
+    ///     compiler::SourceTreeDescriptorDatabase::set_retain_source_code_info
+    pub fn source_code_info(&self) -> Option<&SourceCodeInfo> {
+        self.as_ffi()
+            .has_source_code_info()
+            .then(|| SourceCodeInfo::from_ffi_ref(self.as_ffi().source_code_info()))
+    }
+
     unsafe_ffi_conversions!(ffi::FileDescriptorProto);
 }
 
@@ -571,31 +2470,55 @@ impl private::MessageLite for FileDescriptorProto {
 }
 
 impl Message for FileDescriptorProto {}
-impl private::Message for FileDescriptorProto {}
 
-/// Describes a message type.
-pub struct DescriptorProto {
+impl_message_traits!(FileDescriptorProto);
+
+impl private::Message for FileDescriptorProto {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// Describes the locations in a .proto file at which the elements of the file
+/// were defined, along with any comments attached to them.
+pub struct SourceCodeInfo {
     _opaque: PhantomPinned,
 }
 
-impl Drop for DescriptorProto {
+impl Drop for SourceCodeInfo {
     fn drop(&mut self) {
-        unsafe { ffi::DeleteDescriptorProto(self.as_ffi_mut_ptr_unpinned()) }
+        unsafe { ffi::DeleteSourceCodeInfo(self.as_ffi_mut_ptr_unpinned()) }
     }
 }
 
-impl DescriptorProto {
-    /// Returns the name of tis message.
-    pub fn name(&self) -> &[u8] {
-        self.as_ffi().name().as_bytes()
+impl SourceCodeInfo {
+    /// Returns the number of entries in the `location` field.
+    pub fn location_size(&self) -> usize {
+        self.as_ffi().location_size().expect_usize()
     }
 
-    unsafe_ffi_conversions!(ffi::DescriptorProto);
+    /// Returns the `i`th entry in the `location` field.
+    pub fn location(&self, i: usize) -> &SourceCodeInfoLocation {
+        if i >= self.location_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.location_size(),
+                i
+            );
+        }
+        SourceCodeInfoLocation::from_ffi_ref(self.as_ffi().location(CInt::expect_from(i)))
+    }
+
+    unsafe_ffi_conversions!(ffi::SourceCodeInfo);
 }
 
-impl MessageLite for DescriptorProto {}
+impl MessageLite for SourceCodeInfo {}
 
-impl private::MessageLite for DescriptorProto {
+impl private::MessageLite for SourceCodeInfo {
     fn upcast(&self) -> &ffi::MessageLite {
         unsafe { mem::transmute(self) }
     }
@@ -605,25 +2528,719 @@ impl private::MessageLite for DescriptorProto {
     }
 }
 
-impl Message for DescriptorProto {}
-impl private::Message for DescriptorProto {}
+impl Message for SourceCodeInfo {}
 
-/// An operation failed.
-///
-/// This error does not contain details about why the operation failed or what
-/// the operation was. Unfortunately this is a limitation of the underlying
-/// `libprotobuf` APIs.
-///
-/// In some cases, you may be able to find an alternative API that returns a
-/// more descriptive error type (e.g., the APIs that return
-/// [`compiler::FileLoadError`]), but in most cases the underlying library
-/// simply provides no additional details about what went wrong.
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-pub struct OperationFailedError;
+impl_message_traits!(SourceCodeInfo);
 
-impl fmt::Display for OperationFailedError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str("operation failed")
+impl private::Message for SourceCodeInfo {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// Describes the location of one element of a .proto file.
+pub struct SourceCodeInfoLocation {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for SourceCodeInfoLocation {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteSourceCodeInfoLocation(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl SourceCodeInfoLocation {
+    /// Identifies which element of the file this location describes.
+    ///
+    /// The path is a sequence of field numbers and indices that leads from the
+    /// [`FileDescriptorProto`] to the element. For example, the path `[4, 3]`
+    /// refers to the fourth message type in the file, as `message_type` is
+    /// field 4 of `FileDescriptorProto`.
+    pub fn path(&self) -> &[i32] {
+        ffi::SourceCodeInfoLocationPath(self.as_ffi())
+    }
+
+    /// Returns the span of the element in the file.
+    ///
+    /// The span contains either three or four zero-based elements: the start
+    /// line, the start column, the end line (omitted if it is the same as the
+    /// start line), and the end column.
+    pub fn span(&self) -> &[i32] {
+        ffi::SourceCodeInfoLocationSpan(self.as_ffi())
+    }
+
+    /// Returns the comment immediately preceding the element, if any.
+    pub fn leading_comments(&self) -> &[u8] {
+        self.as_ffi().leading_comments().as_bytes()
+    }
+
+    /// Returns the comment immediately following the element, if any.
+    pub fn trailing_comments(&self) -> &[u8] {
+        self.as_ffi().trailing_comments().as_bytes()
+    }
+
+    /// Returns the number of entries in the `leading_detached_comments` field.
+    pub fn leading_detached_comments_size(&self) -> usize {
+        self.as_ffi()
+            .leading_detached_comments_size()
+            .expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `leading_detached_comments` field.
+    ///
+    /// Detached comments precede the element but are separated from it, and
+    /// from each other, by blank lines.
+    pub fn leading_detached_comments(&self, i: usize) -> &[u8] {
+        if i >= self.leading_detached_comments_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.leading_detached_comments_size(),
+                i
+            );
+        }
+        self.as_ffi()
+            .leading_detached_comments(CInt::expect_from(i))
+            .as_bytes()
+    }
+
+    unsafe_ffi_conversions!(ffi::SourceCodeInfo_Location);
+}
+
+impl MessageLite for SourceCodeInfoLocation {}
+
+impl private::MessageLite for SourceCodeInfoLocation {
+    fn upcast(&self) -> &ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for SourceCodeInfoLocation {}
+
+impl_message_traits!(SourceCodeInfoLocation);
+
+impl private::Message for SourceCodeInfoLocation {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// Describes a message type.
+pub struct DescriptorProto {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for DescriptorProto {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteDescriptorProto(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl DescriptorProto {
+    /// Creates a new descriptor proto.
+    fn new() -> Pin<Box<DescriptorProto>> {
+        let proto = ffi::NewDescriptorProto();
+        unsafe { Self::from_ffi_owned(proto) }
+    }
+
+    /// Returns the name of tis message.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Sets the name of this message.
+    pub fn set_name(self: Pin<&mut Self>, value: &str) {
+        let_cxx_string!(value = value);
+        self.as_ffi_mut().set_name(&value)
+    }
+
+    /// Returns the number of entries in the `field` field.
+    pub fn field_size(&self) -> usize {
+        self.as_ffi().field_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `field` field.
+    pub fn field(&self, i: usize) -> &FieldDescriptorProto {
+        if i >= self.field_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.field_size(),
+                i
+            );
+        }
+        FieldDescriptorProto::from_ffi_ref(self.as_ffi().field(CInt::expect_from(i)))
+    }
+
+    /// Returns the number of entries in the `nested_type` field.
+    pub fn nested_type_size(&self) -> usize {
+        self.as_ffi().nested_type_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `nested_type` field.
+    pub fn nested_type(&self, i: usize) -> &DescriptorProto {
+        if i >= self.nested_type_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.nested_type_size(),
+                i
+            );
+        }
+        DescriptorProto::from_ffi_ref(self.as_ffi().nested_type(CInt::expect_from(i)))
+    }
+
+    /// Returns the number of entries in the `enum_type` field.
+    pub fn enum_type_size(&self) -> usize {
+        self.as_ffi().enum_type_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `enum_type` field.
+    pub fn enum_type(&self, i: usize) -> &EnumDescriptorProto {
+        if i >= self.enum_type_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.enum_type_size(),
+                i
+            );
+        }
+        EnumDescriptorProto::from_ffi_ref(self.as_ffi().enum_type(CInt::expect_from(i)))
+    }
+
+    /// Returns the number of entries in the `oneof_decl` field.
+    pub fn oneof_decl_size(&self) -> usize {
+        self.as_ffi().oneof_decl_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `oneof_decl` field.
+    pub fn oneof_decl(&self, i: usize) -> &OneofDescriptorProto {
+        if i >= self.oneof_decl_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.oneof_decl_size(),
+                i
+            );
+        }
+        OneofDescriptorProto::from_ffi_ref(self.as_ffi().oneof_decl(CInt::expect_from(i)))
+    }
+
+    /// Returns the number of entries in the `reserved_range` field.
+    pub fn reserved_range_size(&self) -> usize {
+        self.as_ffi().reserved_range_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `reserved_range` field.
+    ///
+    /// Field numbers in the returned range may not be used by fields in this
+    /// message.
+    pub fn reserved_range(&self, i: usize) -> Range<i32> {
+        if i >= self.reserved_range_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.reserved_range_size(),
+                i
+            );
+        }
+        let range = self.as_ffi().reserved_range(CInt::expect_from(i));
+        range.start()..range.end()
+    }
+
+    /// Returns the number of entries in the `reserved_name` field.
+    pub fn reserved_name_size(&self) -> usize {
+        self.as_ffi().reserved_name_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `reserved_name` field.
+    ///
+    /// Field names in the `reserved_name` field may not be used by fields in
+    /// this message.
+    pub fn reserved_name(&self, i: usize) -> &[u8] {
+        if i >= self.reserved_name_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.reserved_name_size(),
+                i
+            );
+        }
+        self.as_ffi().reserved_name(CInt::expect_from(i)).as_bytes()
+    }
+
+    unsafe_ffi_conversions!(ffi::DescriptorProto);
+}
+
+impl MessageLite for DescriptorProto {}
+
+impl private::MessageLite for DescriptorProto {
+    fn upcast(&self) -> &ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for DescriptorProto {}
+
+impl_message_traits!(DescriptorProto);
+
+impl private::Message for DescriptorProto {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// Describes a field within a message.
+pub struct FieldDescriptorProto {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for FieldDescriptorProto {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteFieldDescriptorProto(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl FieldDescriptorProto {
+    /// Returns the name of this field.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Returns the field number of this field.
+    pub fn number(&self) -> i32 {
+        self.as_ffi().number()
+    }
+
+    /// Returns the type of this field, if it is set.
+    ///
+    /// The type is not set for message and enum fields in descriptors that
+    /// have been parsed from .proto files but not yet built, as resolving the
+    /// name in [`FieldDescriptorProto::type_name`] is required to determine
+    /// whether the field refers to a message or an enum.
+    pub fn type_(&self) -> Option<FieldType> {
+        self.as_ffi()
+            .has_type()
+            .then(|| FieldType::from_ffi(ffi::FieldDescriptorProtoGetType(self.as_ffi())))
+    }
+
+    /// Returns the label of this field.
+    pub fn label(&self) -> FieldLabel {
+        FieldLabel::from_ffi(ffi::FieldDescriptorProtoGetLabel(self.as_ffi()))
+    }
+
+    /// For message and enum fields, returns the name of the type.
+    ///
+    /// If the name starts with a `.`, it is fully qualified. Otherwise, C++-like
+    /// scoping rules are used to find the type.
+    pub fn type_name(&self) -> &[u8] {
+        self.as_ffi().type_name().as_bytes()
+    }
+
+    unsafe_ffi_conversions!(ffi::FieldDescriptorProto);
+}
+
+impl MessageLite for FieldDescriptorProto {}
+
+impl private::MessageLite for FieldDescriptorProto {
+    fn upcast(&self) -> &ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for FieldDescriptorProto {}
+
+impl_message_traits!(FieldDescriptorProto);
+
+impl private::Message for FieldDescriptorProto {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// Describes a oneof.
+pub struct OneofDescriptorProto {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for OneofDescriptorProto {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteOneofDescriptorProto(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl OneofDescriptorProto {
+    /// Returns the name of this oneof.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    unsafe_ffi_conversions!(ffi::OneofDescriptorProto);
+}
+
+impl MessageLite for OneofDescriptorProto {}
+
+impl private::MessageLite for OneofDescriptorProto {
+    fn upcast(&self) -> &ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for OneofDescriptorProto {}
+
+impl_message_traits!(OneofDescriptorProto);
+
+impl private::Message for OneofDescriptorProto {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// Describes an enum type.
+pub struct EnumDescriptorProto {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for EnumDescriptorProto {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteEnumDescriptorProto(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl EnumDescriptorProto {
+    /// Returns the name of this enum.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Returns the number of entries in the `value` field.
+    pub fn value_size(&self) -> usize {
+        self.as_ffi().value_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `value` field.
+    pub fn value(&self, i: usize) -> &EnumValueDescriptorProto {
+        if i >= self.value_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.value_size(),
+                i
+            );
+        }
+        EnumValueDescriptorProto::from_ffi_ref(self.as_ffi().value(CInt::expect_from(i)))
+    }
+
+    unsafe_ffi_conversions!(ffi::EnumDescriptorProto);
+}
+
+impl MessageLite for EnumDescriptorProto {}
+
+impl private::MessageLite for EnumDescriptorProto {
+    fn upcast(&self) -> &ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for EnumDescriptorProto {}
+
+impl_message_traits!(EnumDescriptorProto);
+
+impl private::Message for EnumDescriptorProto {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// Describes a value within an enum.
+pub struct EnumValueDescriptorProto {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for EnumValueDescriptorProto {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteEnumValueDescriptorProto(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl EnumValueDescriptorProto {
+    /// Returns the name of this enum value.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Returns the numeric value of this enum value.
+    pub fn number(&self) -> i32 {
+        self.as_ffi().number()
+    }
+
+    unsafe_ffi_conversions!(ffi::EnumValueDescriptorProto);
+}
+
+impl MessageLite for EnumValueDescriptorProto {}
+
+impl private::MessageLite for EnumValueDescriptorProto {
+    fn upcast(&self) -> &ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for EnumValueDescriptorProto {}
+
+impl_message_traits!(EnumValueDescriptorProto);
+
+impl private::Message for EnumValueDescriptorProto {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// Describes a service.
+pub struct ServiceDescriptorProto {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for ServiceDescriptorProto {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteServiceDescriptorProto(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl ServiceDescriptorProto {
+    /// Returns the name of this service.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Returns the number of entries in the `method` field.
+    pub fn method_size(&self) -> usize {
+        self.as_ffi().method_size().expect_usize()
+    }
+
+    /// Returns the `i`th entry in the `method` field.
+    pub fn method(&self, i: usize) -> &MethodDescriptorProto {
+        if i >= self.method_size() {
+            panic!(
+                "index out of bounds: the length is {} but the index is {}",
+                self.method_size(),
+                i
+            );
+        }
+        MethodDescriptorProto::from_ffi_ref(self.as_ffi().method(CInt::expect_from(i)))
+    }
+
+    unsafe_ffi_conversions!(ffi::ServiceDescriptorProto);
+}
+
+impl MessageLite for ServiceDescriptorProto {}
+
+impl private::MessageLite for ServiceDescriptorProto {
+    fn upcast(&self) -> &ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for ServiceDescriptorProto {}
+
+impl_message_traits!(ServiceDescriptorProto);
+
+impl private::Message for ServiceDescriptorProto {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// Describes a method of a service.
+pub struct MethodDescriptorProto {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for MethodDescriptorProto {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteMethodDescriptorProto(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl MethodDescriptorProto {
+    /// Returns the name of this method.
+    pub fn name(&self) -> &[u8] {
+        self.as_ffi().name().as_bytes()
+    }
+
+    /// Returns the name of the method's input type.
+    ///
+    /// If the name starts with a `.`, it is fully qualified.
+    pub fn input_type(&self) -> &[u8] {
+        self.as_ffi().input_type().as_bytes()
+    }
+
+    /// Returns the name of the method's output type.
+    ///
+    /// If the name starts with a `.`, it is fully qualified.
+    pub fn output_type(&self) -> &[u8] {
+        self.as_ffi().output_type().as_bytes()
+    }
+
+    unsafe_ffi_conversions!(ffi::MethodDescriptorProto);
+}
+
+impl MessageLite for MethodDescriptorProto {}
+
+impl private::MessageLite for MethodDescriptorProto {
+    fn upcast(&self) -> &ffi::MessageLite {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::MessageLite> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+impl Message for MethodDescriptorProto {}
+
+impl_message_traits!(MethodDescriptorProto);
+
+impl private::Message for MethodDescriptorProto {
+    fn upcast_message(&self) -> &ffi::Message {
+        unsafe { mem::transmute(self) }
+    }
+
+    fn upcast_message_mut(self: Pin<&mut Self>) -> Pin<&mut ffi::Message> {
+        unsafe { mem::transmute(self) }
+    }
+}
+
+/// Identifies whether a field is optional, required, or repeated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldLabel {
+    /// An optional field.
+    Optional,
+    /// A required field.
+    Required,
+    /// A repeated field.
+    Repeated,
+}
+
+impl FieldLabel {
+    fn from_ffi(label: CInt) -> FieldLabel {
+        match label.0 {
+            1 => FieldLabel::Optional,
+            2 => FieldLabel::Required,
+            3 => FieldLabel::Repeated,
+            _ => panic!("unknown field label: {}", label.0),
+        }
+    }
+}
+
+/// An operation failed.
+///
+/// The underlying `libprotobuf` APIs report only whether an operation
+/// succeeded, not why it failed. When `libprotobuf` logs an error describing
+/// the failure, as it does for invalid descriptors and unparseable text-format
+/// input, the logged message is attached to the error and is available via
+/// [`OperationFailedError::message`]. Otherwise the error contains no details
+/// about what went wrong.
+///
+/// In some cases, you may be able to find an alternative API that returns a
+/// more descriptive error type (e.g., the APIs that return
+/// [`compiler::FileLoadError`]).
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct OperationFailedError {
+    message: Option<String>,
+}
+
+impl OperationFailedError {
+    /// Constructs a new error without a message.
+    pub fn new() -> OperationFailedError {
+        OperationFailedError { message: None }
+    }
+
+    /// Constructs a new error with the given message.
+    pub fn with_message<S>(message: S) -> OperationFailedError
+    where
+        S: Into<String>,
+    {
+        OperationFailedError {
+            message: Some(message.into()),
+        }
+    }
+
+    /// Returns the message describing why the operation failed, if known.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// Calls `f`, which reports whether an operation succeeded, attaching any
+    /// errors that `libprotobuf` logs during the call to the returned error.
+    pub(crate) fn capture<F>(f: F) -> Result<(), OperationFailedError>
+    where
+        F: FnOnce() -> bool,
+    {
+        match log::capture_errors(f) {
+            (true, _) => Ok(()),
+            (false, message) => Err(OperationFailedError { message }),
+        }
+    }
+}
+
+impl fmt::Display for OperationFailedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.message {
+            None => f.write_str("operation failed"),
+            Some(message) => write!(f, "operation failed: {}", message),
+        }
     }
 }
 
@@ -0,0 +1,169 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License in the LICENSE file at the
+// root of this repository, or online at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Control over `libprotobuf`'s log output.
+//!
+//! By default, this crate discards all log messages emitted by `libprotobuf`,
+//! as libraries should not write to stderr. Use [`set_log_handler`] to receive
+//! these messages instead, e.g., to forward them to your application's
+//! logging framework.
+//!
+//! Regardless of the handler, errors that `libprotobuf` logs during a failed
+//! operation are attached to the [`OperationFailedError`] that the operation
+//! returns.
+//!
+//! [`OperationFailedError`]: crate::OperationFailedError
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::internal::CInt;
+
+#[cxx::bridge(namespace = "protobuf_native::log")]
+pub(crate) mod ffi {
+    extern "Rust" {
+        fn handle_log(level: CInt, filename: &str, line: CInt, message: &CxxString);
+    }
+
+    unsafe extern "C++" {
+        include!("protobuf-native/src/log.h");
+
+        #[namespace = "protobuf_native::internal"]
+        type CInt = crate::internal::CInt;
+    }
+}
+
+type LogHandler = Box<dyn FnMut(LogLevel, &str, i32, &str) + Send>;
+
+static LOG_HANDLER: Mutex<Option<LogHandler>> = Mutex::new(None);
+
+thread_local! {
+    static IN_LOG_HANDLER: Cell<bool> = const { Cell::new(false) };
+    static CAPTURED_ERRORS: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+/// The severity of a log message emitted by `libprotobuf`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum LogLevel {
+    /// An informational message.
+    Info,
+    /// A warning about a potential problem.
+    Warning,
+    /// An error that `libprotobuf` recovered from.
+    Error,
+    /// An error that `libprotobuf` cannot recover from.
+    ///
+    /// After a fatal message is logged, `libprotobuf` terminates the process,
+    /// unless the message was logged during an operation that reports such
+    /// failures as errors, like [`ZeroCopyInputStream::try_back_up`].
+    ///
+    /// [`ZeroCopyInputStream::try_back_up`]: crate::io::ZeroCopyInputStream::try_back_up
+    Fatal,
+}
+
+impl LogLevel {
+    fn from_ffi(level: CInt) -> LogLevel {
+        match level.0 {
+            0 => LogLevel::Info,
+            1 => LogLevel::Warning,
+            2 => LogLevel::Error,
+            3 => LogLevel::Fatal,
+            _ => panic!("unknown log level: {}", level.0),
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::Info => "INFO",
+            LogLevel::Warning => "WARNING",
+            LogLevel::Error => "ERROR",
+            LogLevel::Fatal => "FATAL",
+        })
+    }
+}
+
+/// Installs a handler for log messages emitted by `libprotobuf`.
+///
+/// The handler is called with the severity of the message, the name of the
+/// `libprotobuf` source file and line that emitted it, and the message itself.
+/// It replaces any previously installed handler.
+///
+/// The handler is process-wide and may be called from any thread. It must not
+/// panic. Log messages emitted by `libprotobuf` while the handler is running on
+/// the same thread are discarded.
+pub fn set_log_handler<F>(handler: F)
+where
+    F: FnMut(LogLevel, &str, i32, &str) + Send + 'static,
+{
+    *lock_log_handler() = Some(Box::new(handler));
+}
+
+/// Discards all log messages emitted by `libprotobuf`.
+///
+/// This removes any handler installed by [`set_log_handler`] and restores
+/// the default behavior of this crate.
+pub fn silence_logs() {
+    *lock_log_handler() = None;
+}
+
+fn lock_log_handler() -> std::sync::MutexGuard<'static, Option<LogHandler>> {
+    // Replacing the handler cannot leave it in an inconsistent state, so
+    // recover from poisoning rather than propagating the panic.
+    LOG_HANDLER.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Calls `f`, collecting the error messages that `libprotobuf` logs on the
+/// current thread while `f` runs.
+///
+/// The messages are joined with newlines. Returns `None` if no errors were
+/// logged.
+pub(crate) fn capture_errors<R>(f: impl FnOnce() -> R) -> (R, Option<String>) {
+    // Restores the enclosing capture, if any, even if `f` panics.
+    struct Restore(Option<Vec<String>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CAPTURED_ERRORS.with(|captured| *captured.borrow_mut() = self.0.take());
+        }
+    }
+
+    let _restore = Restore(CAPTURED_ERRORS.with(|captured| captured.replace(Some(vec![]))));
+    let res = f();
+    let errors = CAPTURED_ERRORS.with(|captured| captured.take().unwrap_or_default());
+    let message = (!errors.is_empty()).then(|| errors.join("\n"));
+    (res, message)
+}
+
+fn handle_log(level: CInt, filename: &str, line: CInt, message: &cxx::CxxString) {
+    let level = LogLevel::from_ffi(level);
+    let message = message.to_string_lossy();
+    if level >= LogLevel::Error {
+        CAPTURED_ERRORS.with(|captured| {
+            if let Some(captured) = captured.borrow_mut().as_mut() {
+                captured.push(message.clone().into_owned());
+            }
+        });
+    }
+    if IN_LOG_HANDLER.with(|in_handler| in_handler.replace(true)) {
+        return;
+    }
+    if let Some(handler) = lock_log_handler().as_mut() {
+        handler(level, filename, line.0, &message);
+    }
+    IN_LOG_HANDLER.with(|in_handler| in_handler.set(false));
+}
@@ -0,0 +1,42 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License in the LICENSE file at the
+// root of this repository, or online at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#include "protobuf-native/src/text_format.h"
+
+namespace protobuf_native {
+namespace text_format {
+
+Printer* NewPrinter() { return new Printer(); }
+
+void DeletePrinter(Printer* printer) { delete printer; }
+
+bool PrinterPrintToString(const Printer& printer, const Message& message, std::string& output) {
+    return printer.PrintToString(message, &output);
+}
+
+Parser* NewParser() { return new Parser(); }
+
+void DeleteParser(Parser* parser) { delete parser; }
+
+bool ParserParseFromString(Parser& parser, rust::Str input, Message& message) {
+    return parser.ParseFromString(std::string(input), &message);
+}
+
+bool ParserMergeFromString(Parser& parser, rust::Str input, Message& message) {
+    return parser.MergeFromString(std::string(input), &message);
+}
+
+}  // namespace text_format
+}  // namespace protobuf_native
@@ -0,0 +1,217 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License in the LICENSE file at the
+// root of this repository, or online at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Utilities for printing and parsing protocol messages in a human-readable,
+//! text-based format.
+
+use std::marker::PhantomPinned;
+use std::pin::Pin;
+
+use cxx::let_cxx_string;
+
+use crate::internal::{unsafe_ffi_conversions, BoolExt};
+use crate::{Message, OperationFailedError};
+
+#[cxx::bridge(namespace = "protobuf_native::text_format")]
+pub(crate) mod ffi {
+    unsafe extern "C++" {
+        include!("protobuf-native/src/text_format.h");
+
+        #[namespace = "google::protobuf"]
+        type Message = crate::ffi::Message;
+
+        type Printer;
+
+        fn NewPrinter() -> *mut Printer;
+        unsafe fn DeletePrinter(printer: *mut Printer);
+        fn SetSingleLineMode(self: Pin<&mut Printer>, single_line_mode: bool);
+        fn SetExpandAny(self: Pin<&mut Printer>, expand: bool);
+        fn SetUseUtf8StringEscaping(self: Pin<&mut Printer>, as_utf8: bool);
+        fn PrinterPrintToString(
+            printer: &Printer,
+            message: &Message,
+            output: Pin<&mut CxxString>,
+        ) -> bool;
+
+        type Parser;
+
+        fn NewParser() -> *mut Parser;
+        unsafe fn DeleteParser(parser: *mut Parser);
+        fn AllowUnknownField(self: Pin<&mut Parser>, allow: bool);
+        fn AllowPartialMessage(self: Pin<&mut Parser>, allow: bool);
+        fn ParserParseFromString(
+            parser: Pin<&mut Parser>,
+            input: &str,
+            message: Pin<&mut Message>,
+        ) -> bool;
+        fn ParserMergeFromString(
+            parser: Pin<&mut Parser>,
+            input: &str,
+            message: Pin<&mut Message>,
+        ) -> bool;
+    }
+}
+
+/// Outputs a textual representation of the message to a string.
+///
+/// This is a convenience wrapper around a [`Printer`] with the default
+/// options.
+pub fn print_to_string(message: &dyn Message) -> Result<String, OperationFailedError> {
+    Printer::new().print_to_string(message)
+}
+
+/// Parses a text-format protocol message from the given string into
+/// `message`, replacing the current contents of the message.
+///
+/// This is a convenience wrapper around a [`Parser`] with the default options.
+pub fn parse_from_str(
+    input: &str,
+    message: Pin<&mut dyn Message>,
+) -> Result<(), OperationFailedError> {
+    Parser::new().as_mut().parse_from_str(input, message)
+}
+
+/// Like [`parse_from_str`], but merges the parsed fields into the existing
+/// contents of `message`.
+///
+/// Singular fields in the input overwrite those already present in the
+/// message, and repeated fields are appended.
+pub fn merge_from_str(
+    input: &str,
+    message: Pin<&mut dyn Message>,
+) -> Result<(), OperationFailedError> {
+    Parser::new().as_mut().merge_from_str(input, message)
+}
+
+/// A class for printing protobufs with fine-grained control over the output.
+pub struct Printer {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for Printer {
+    fn drop(&mut self) {
+        unsafe { ffi::DeletePrinter(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl Printer {
+    /// Creates a new printer with the default options.
+    pub fn new() -> Pin<Box<Printer>> {
+        let printer = ffi::NewPrinter();
+        unsafe { Self::from_ffi_owned(printer) }
+    }
+
+    /// Sets whether to print the message on a single line.
+    ///
+    /// In single-line mode, fields are separated by spaces rather than
+    /// newlines, and no indentation is used.
+    pub fn set_single_line_mode(self: Pin<&mut Self>, single_line_mode: bool) {
+        self.as_ffi_mut().SetSingleLineMode(single_line_mode)
+    }
+
+    /// Sets whether to expand `google.protobuf.Any` fields.
+    ///
+    /// If true, `Any` fields are printed using the special syntax
+    /// `[type_url] { ... }` showing the embedded message, provided that the
+    /// message's type can be found in the descriptor pool of the message being
+    /// printed. Otherwise they are printed as ordinary messages.
+    pub fn set_expand_any(self: Pin<&mut Self>, expand: bool) {
+        self.as_ffi_mut().SetExpandAny(expand)
+    }
+
+    /// Sets whether to print UTF-8 string fields as UTF-8.
+    ///
+    /// By default, bytes outside of the printable ASCII range are escaped with
+    /// octal escape sequences. If true, valid UTF-8 sequences in string fields
+    /// are printed as-is.
+    pub fn set_use_utf8_string_escaping(self: Pin<&mut Self>, as_utf8: bool) {
+        self.as_ffi_mut().SetUseUtf8StringEscaping(as_utf8)
+    }
+
+    /// Outputs a textual representation of the message to a string.
+    pub fn print_to_string(&self, message: &dyn Message) -> Result<String, OperationFailedError> {
+        let_cxx_string!(output = "");
+        ffi::PrinterPrintToString(self.as_ffi(), message.upcast_message(), output.as_mut())
+            .as_result()?;
+        Ok(output.to_string_lossy().into_owned())
+    }
+
+    unsafe_ffi_conversions!(ffi::Printer);
+}
+
+/// A class for parsing text-format protobufs with fine-grained control over
+/// the accepted input.
+pub struct Parser {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for Parser {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteParser(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl Parser {
+    /// Creates a new parser with the default options.
+    pub fn new() -> Pin<Box<Parser>> {
+        let parser = ffi::NewParser();
+        unsafe { Self::from_ffi_owned(parser) }
+    }
+
+    /// Sets whether to allow fields that are not defined in the message's
+    /// type.
+    ///
+    /// If true, unknown fields and extensions are skipped rather than causing
+    /// parsing to fail. The values of unknown fields are discarded. This is
+    /// useful when reading text written against a newer version of a schema,
+    /// but it also means that misspelled field names go unnoticed.
+    pub fn allow_unknown_fields(self: Pin<&mut Self>, allow: bool) {
+        self.as_ffi_mut().AllowUnknownField(allow)
+    }
+
+    /// Sets whether to allow parsing messages with missing required fields.
+    ///
+    /// By default, parsing fails if the resulting message is not
+    /// [initialized](crate::MessageLite::is_initialized).
+    pub fn allow_partial(self: Pin<&mut Self>, allow: bool) {
+        self.as_ffi_mut().AllowPartialMessage(allow)
+    }
+
+    /// Parses a text-format protocol message from the given string into
+    /// `message`, replacing the current contents of the message.
+    pub fn parse_from_str(
+        self: Pin<&mut Self>,
+        input: &str,
+        message: Pin<&mut dyn Message>,
+    ) -> Result<(), OperationFailedError> {
+        OperationFailedError::capture(|| {
+            ffi::ParserParseFromString(self.as_ffi_mut(), input, message.upcast_message_mut())
+        })
+    }
+
+    /// Like [`Parser::parse_from_str`], but merges the parsed fields into the
+    /// existing contents of `message`.
+    pub fn merge_from_str(
+        self: Pin<&mut Self>,
+        input: &str,
+        message: Pin<&mut dyn Message>,
+    ) -> Result<(), OperationFailedError> {
+        OperationFailedError::capture(|| {
+            ffi::ParserMergeFromString(self.as_ffi_mut(), input, message.upcast_message_mut())
+        })
+    }
+
+    unsafe_ffi_conversions!(ffi::Parser);
+}
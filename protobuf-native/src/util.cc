@@ -0,0 +1,54 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License in the LICENSE file at the
+// root of this repository, or online at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#include "protobuf-native/src/util.h"
+
+namespace protobuf_native {
+namespace util {
+
+Differencer* NewDifferencer() { return new Differencer(); }
+
+void DeleteDifferencer(Differencer* differencer) { delete differencer; }
+
+void DifferencerSetPartial(Differencer& differencer, bool partial) {
+    differencer.set_scope(partial ? Differencer::PARTIAL : Differencer::FULL);
+}
+
+void DifferencerSetRepeatedAsSet(Differencer& differencer, bool as_set) {
+    differencer.set_repeated_field_comparison(as_set ? Differencer::AS_SET : Differencer::AS_LIST);
+}
+
+void DifferencerSetApproximateFloat(Differencer& differencer, bool approximate) {
+    differencer.set_float_comparison(approximate ? Differencer::APPROXIMATE : Differencer::EXACT);
+}
+
+bool DifferencerCompare(Differencer& differencer, const Message& a, const Message& b,
+                        std::string& report) {
+    report.clear();
+    // `MessageDifferencer` treats a comparison between messages of different
+    // types as a fatal error in debug builds.
+    if (a.GetDescriptor() != b.GetDescriptor()) {
+        report = "message types differ: " + a.GetDescriptor()->full_name() + " vs " +
+                 b.GetDescriptor()->full_name() + "\n";
+        return false;
+    }
+    differencer.ReportDifferencesToString(&report);
+    bool equal = differencer.Compare(a, b);
+    differencer.ReportDifferencesTo(nullptr);
+    return equal;
+}
+
+}  // namespace util
+}  // namespace protobuf_native
@@ -0,0 +1,40 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License in the LICENSE file at the
+// root of this repository, or online at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#pragma once
+
+#include <google/protobuf/message.h>
+#include <google/protobuf/util/message_differencer.h>
+
+#include <string>
+
+#include "rust/cxx.h"
+
+namespace protobuf_native {
+namespace util {
+
+using Message = google::protobuf::Message;
+using Differencer = google::protobuf::util::MessageDifferencer;
+
+Differencer* NewDifferencer();
+void DeleteDifferencer(Differencer*);
+void DifferencerSetPartial(Differencer& differencer, bool partial);
+void DifferencerSetRepeatedAsSet(Differencer& differencer, bool as_set);
+void DifferencerSetApproximateFloat(Differencer& differencer, bool approximate);
+bool DifferencerCompare(Differencer& differencer, const Message& a, const Message& b,
+                        std::string& report);
+
+}  // namespace util
+}  // namespace protobuf_native
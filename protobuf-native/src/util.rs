@@ -0,0 +1,143 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License in the LICENSE file at the
+// root of this repository, or online at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Utilities for working with protocol messages.
+
+use std::marker::PhantomPinned;
+use std::pin::Pin;
+
+use cxx::let_cxx_string;
+
+use crate::internal::unsafe_ffi_conversions;
+use crate::Message;
+
+#[cxx::bridge(namespace = "protobuf_native::util")]
+pub(crate) mod ffi {
+    unsafe extern "C++" {
+        include!("protobuf-native/src/util.h");
+
+        #[namespace = "google::protobuf"]
+        type Message = crate::ffi::Message;
+
+        type Differencer;
+
+        fn NewDifferencer() -> *mut Differencer;
+        unsafe fn DeleteDifferencer(differencer: *mut Differencer);
+        fn DifferencerSetPartial(differencer: Pin<&mut Differencer>, partial: bool);
+        fn DifferencerSetRepeatedAsSet(differencer: Pin<&mut Differencer>, as_set: bool);
+        fn DifferencerSetApproximateFloat(differencer: Pin<&mut Differencer>, approximate: bool);
+        fn DifferencerCompare(
+            differencer: Pin<&mut Differencer>,
+            a: &Message,
+            b: &Message,
+            report: Pin<&mut CxxString>,
+        ) -> bool;
+    }
+}
+
+/// Reports whether two messages are equal.
+///
+/// Messages of different types are never equal. Otherwise, two messages are
+/// equal if they have the same set of fields with equal values, regardless of
+/// the order in which the fields were serialized.
+///
+/// This is equivalent to comparing the messages with `==`. Unlike the default
+/// [`Differencer`], it considers NaN equal to NaN, so that every message is
+/// equal to itself.
+pub fn messages_equal(a: &dyn Message, b: &dyn Message) -> bool {
+    crate::ffi::MessageEquals(a.upcast_message(), b.upcast_message())
+}
+
+/// Compares two protocol messages field by field and reports the
+/// differences.
+///
+/// # Examples
+///
+/// ```
+/// use protobuf_native::util::Differencer;
+/// use protobuf_native::FileDescriptorSet;
+///
+/// let a = FileDescriptorSet::new();
+/// let mut b = FileDescriptorSet::new();
+/// b.as_mut().add_file().set_name("b.proto");
+///
+/// let report = Differencer::new().as_mut().diff(&*a, &*b).unwrap();
+/// assert_eq!(report, "added: file[0]: { name: \"b.proto\" }\n");
+/// ```
+pub struct Differencer {
+    _opaque: PhantomPinned,
+}
+
+impl Drop for Differencer {
+    fn drop(&mut self) {
+        unsafe { ffi::DeleteDifferencer(self.as_ffi_mut_ptr_unpinned()) }
+    }
+}
+
+impl Differencer {
+    /// Creates a new differencer with the default options.
+    pub fn new() -> Pin<Box<Differencer>> {
+        let differencer = ffi::NewDifferencer();
+        unsafe { Self::from_ffi_owned(differencer) }
+    }
+
+    /// Sets whether to ignore fields that are set in the second message but
+    /// not in the first.
+    ///
+    /// By default, all fields of both messages are compared.
+    pub fn set_partial(self: Pin<&mut Self>, partial: bool) {
+        ffi::DifferencerSetPartial(self.as_ffi_mut(), partial)
+    }
+
+    /// Sets whether to compare repeated fields as sets, ignoring the order
+    /// of their elements.
+    ///
+    /// By default, repeated fields are compared as lists, so two fields with
+    /// the same elements in a different order are not equal.
+    pub fn set_repeated_as_set(self: Pin<&mut Self>, as_set: bool) {
+        ffi::DifferencerSetRepeatedAsSet(self.as_ffi_mut(), as_set)
+    }
+
+    /// Sets whether to compare floating-point fields approximately.
+    ///
+    /// By default, floating-point fields must be exactly equal.
+    pub fn set_approximate_float(self: Pin<&mut Self>, approximate: bool) {
+        ffi::DifferencerSetApproximateFloat(self.as_ffi_mut(), approximate)
+    }
+
+    /// Reports whether the two messages are equal under this differencer's
+    /// options.
+    pub fn compare(self: Pin<&mut Self>, a: &dyn Message, b: &dyn Message) -> bool {
+        self.diff(a, b).is_none()
+    }
+
+    /// Compares two messages and describes how they differ.
+    ///
+    /// Returns `None` if the messages are equal under this differencer's
+    /// options. Otherwise returns a human-readable report with one line per
+    /// differing field, identified by its path from the root message.
+    pub fn diff(self: Pin<&mut Self>, a: &dyn Message, b: &dyn Message) -> Option<String> {
+        let_cxx_string!(report = "");
+        let equal = ffi::DifferencerCompare(
+            self.as_ffi_mut(),
+            a.upcast_message(),
+            b.upcast_message(),
+            report.as_mut(),
+        );
+        (!equal).then(|| report.to_string_lossy().into_owned())
+    }
+
+    unsafe_ffi_conversions!(ffi::Differencer);
+}
@@ -31,12 +31,18 @@
 //! chunks separated at different points. The whole process is run with a
 //! variety of block sizes for both the input and the output.
 
-use std::io::{Seek, SeekFrom};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::mem::MaybeUninit;
 use std::pin::Pin;
+use std::slice;
+use std::thread;
 
+use protobuf_native::compiler::SimpleErrorCollector;
 use protobuf_native::io::{
-    ReaderStream, SliceInputStream, SliceOutputStream, VecOutputStream, WriterStream,
-    ZeroCopyInputStream, ZeroCopyOutputStream,
+    decode_zigzag32, decode_zigzag64, CodedInputStream, CodedOutputStream,
+    ConcatenatingInputStream, GzipFormat, GzipInputStream, GzipOptions, GzipOutputStream,
+    LimitingInputStream, Printer, ReaderStream, SliceInputStream, SliceOutputStream, TokenType,
+    Tokenizer, VecOutputStream, WriterStream, ZeroCopyInputStream, ZeroCopyOutputStream,
 };
 
 use crate::util;
@@ -112,6 +118,131 @@ fn test_io_slice() {
     check_some_reads(SliceInputStream::new(&buffer).as_mut());
 }
 
+#[test]
+fn test_io_slice_position() {
+    let mut input = SliceInputStream::new(b"hello world");
+    assert_eq!(input.current_position(), 0);
+    assert_eq!(input.bytes_remaining(), 11);
+    input.as_mut().skip(6).unwrap();
+    assert_eq!(input.current_position(), 6);
+    assert_eq!(input.bytes_remaining(), 5);
+    assert_eq!(input.as_mut().next().unwrap(), b"world");
+    input.as_mut().back_up(2);
+    assert_eq!(input.current_position(), 9);
+    assert_eq!(input.bytes_remaining(), 2);
+}
+
+fn check_try_back_up(mut input: Pin<&mut dyn ZeroCopyInputStream>, first_chunk: &[u8]) {
+    assert!(input.as_mut().try_back_up(0).is_err());
+    assert_eq!(input.as_mut().next().unwrap(), first_chunk);
+    assert!(input.as_mut().try_back_up(first_chunk.len() + 1).is_err());
+    input.as_mut().try_back_up(2).unwrap();
+    assert!(input.as_mut().try_back_up(1).is_err());
+    assert!(input.as_mut().skip(1).is_ok());
+    assert!(input.as_mut().try_back_up(1).is_err());
+    assert_eq!(
+        input.as_mut().next().unwrap(),
+        &first_chunk[first_chunk.len() - 1..]
+    );
+}
+
+#[test]
+fn test_io_try_back_up() {
+    let mut input = SliceInputStream::new(b"hello world");
+    assert!(input.as_mut().try_back_up(1).is_err());
+    input.as_mut().skip(6).unwrap();
+    assert_eq!(input.as_mut().next().unwrap(), b"world");
+    assert!(input.as_mut().try_back_up(12).is_err());
+    input.as_mut().try_back_up(2).unwrap();
+    // Backing up again is rejected, as `next` has not been called since.
+    assert!(input.as_mut().try_back_up(1).is_err());
+    assert_eq!(input.as_mut().next().unwrap(), b"ld");
+
+    // Streams that do not validate `count` themselves are protected too.
+    let mut gzipped = vec![];
+    let mut output = VecOutputStream::new(&mut gzipped);
+    let mut gzip = GzipOutputStream::new(output.as_mut());
+    write_bytes(gzip.as_mut(), b"hello world");
+    gzip.as_mut().close().unwrap();
+    drop(gzip);
+    drop(output);
+    let mut input = SliceInputStream::new(&gzipped);
+    check_try_back_up(
+        GzipInputStream::new(input.as_mut()).as_mut(),
+        b"hello world",
+    );
+
+    let mut input = SliceInputStream::new(b"hello world");
+    check_try_back_up(
+        LimitingInputStream::new(input.as_mut(), 5).as_mut(),
+        b"hello",
+    );
+
+    let mut a = SliceInputStream::new(b"hello ");
+    let mut b = SliceInputStream::new(b"world");
+    let mut input = ConcatenatingInputStream::new([
+        a.as_mut() as Pin<&mut dyn ZeroCopyInputStream>,
+        b.as_mut(),
+    ]);
+    check_try_back_up(input.as_mut(), b"hello ");
+
+    // Calls on other streams do not interfere, even from another thread.
+    let mut a = SliceInputStream::new(b"hello");
+    let mut b = SliceInputStream::new(b"world");
+    assert_eq!(a.as_mut().next().unwrap(), b"hello");
+    assert_eq!(b.as_mut().next().unwrap(), b"world");
+    a.as_mut().try_back_up(2).unwrap();
+    let mut b = thread::spawn(move || {
+        b.as_mut().try_back_up(1).unwrap();
+        b
+    })
+    .join()
+    .unwrap();
+    assert!(a.as_mut().try_back_up(1).is_err());
+    assert!(b.as_mut().try_back_up(1).is_err());
+    assert_eq!(a.as_mut().next().unwrap(), b"lo");
+    assert_eq!(b.as_mut().next().unwrap(), b"d");
+
+    let mut buffer = [0; 8];
+    let mut output = SliceOutputStream::new(&mut buffer);
+    let len = unsafe { output.as_mut().next() }.unwrap().len();
+    assert!(output.as_mut().try_back_up(len + 1).is_err());
+    output.as_mut().try_back_up(4).unwrap();
+    assert!(output.as_mut().try_back_up(1).is_err());
+    assert_eq!(output.byte_count(), 4);
+}
+
+#[test]
+fn test_io_slice_byte_count() {
+    let mut buffer = [0; 16];
+    let mut output = SliceOutputStream::with_block_size(&mut buffer, 4);
+    assert_eq!(output.byte_count(), 0);
+    for chunk in [&b"abcd"[..], b"ef"] {
+        let buf = unsafe { output.as_mut().next() }.unwrap();
+        assert_eq!(buf.len(), 4);
+        for (dst, src) in buf.iter_mut().zip(chunk) {
+            dst.write(*src);
+        }
+        output.as_mut().back_up(4 - chunk.len());
+    }
+    let written = usize::try_from(output.byte_count()).unwrap();
+    drop(output);
+    assert_eq!(written, 6);
+    assert_eq!(&buffer[..written], b"abcdef");
+}
+
+#[test]
+fn test_io_uninit_slice() {
+    let mut buffer = vec![MaybeUninit::uninit(); 1 << 18];
+    let mut output = SliceOutputStream::new_uninit(&mut buffer);
+    check_some_writes(output.as_mut());
+    let len = usize::try_from(output.byte_count()).unwrap();
+    drop(output);
+    // SAFETY: the stream initialized the first `len` bytes of the buffer.
+    let buffer = unsafe { slice::from_raw_parts(buffer.as_ptr().cast::<u8>(), len) };
+    check_some_reads(SliceInputStream::new(buffer).as_mut());
+}
+
 #[test]
 fn test_io_vec() {
     let mut buffer = vec![];
@@ -119,6 +250,227 @@ fn test_io_vec() {
     let mut input = SliceInputStream::new(&buffer);
     check_some_reads(input.as_mut());
     assert!(input.as_mut().next().is_err()); // check for EOF
+
+    let mut buffer = vec![];
+    let mut output = VecOutputStream::with_capacity(&mut buffer, 1024);
+    // SAFETY: we back up over the entire buffer without writing to it.
+    let len = unsafe { output.as_mut().next() }.unwrap().len();
+    assert!(len >= 1024);
+    output.as_mut().back_up(len);
+    drop(output);
+    assert!(buffer.is_empty());
+}
+
+#[test]
+fn test_io_read_to_end() {
+    let mut buffer = vec![];
+    check_some_writes(VecOutputStream::new(&mut buffer).as_mut());
+    let mut input = SliceInputStream::new(&buffer);
+    input.as_mut().skip(13).unwrap();
+    let mut out = b"prefix".to_vec();
+    assert_eq!(input.as_mut().read_to_end(&mut out).unwrap(), 200_042);
+    assert_eq!(&out[..6], b"prefix");
+    assert_eq!(&out[6..], &buffer[13..]);
+    assert_eq!(input.as_mut().read_to_end(&mut out).unwrap(), 0);
+}
+
+#[test]
+fn test_io_gzip() {
+    for format in [GzipFormat::Gzip, GzipFormat::Zlib] {
+        let mut buffer = vec![];
+        let mut output = VecOutputStream::new(&mut buffer);
+        let mut gzip = GzipOutputStream::with_options(
+            output.as_mut(),
+            &GzipOptions {
+                format,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        check_some_writes(gzip.as_mut());
+        gzip.as_mut().close().unwrap();
+        drop(gzip);
+        drop(output);
+        assert!(buffer.len() < 200_055);
+
+        let mut input = SliceInputStream::new(&buffer);
+        check_some_reads(GzipInputStream::new(input.as_mut()).as_mut());
+        let mut input = SliceInputStream::new(&buffer);
+        check_some_reads(GzipInputStream::with_format(input.as_mut(), format).as_mut());
+    }
+}
+
+#[test]
+fn test_io_gzip_options() {
+    let mut buffer = vec![];
+    let mut output = VecOutputStream::new(&mut buffer);
+    for compression_level in [0, 9] {
+        let options = GzipOptions {
+            compression_level: Some(compression_level),
+            ..Default::default()
+        };
+        let mut gzip = GzipOutputStream::with_options(output.as_mut(), &options).unwrap();
+        check_some_writes(gzip.as_mut());
+        gzip.as_mut().close().unwrap();
+    }
+    for options in [
+        GzipOptions {
+            compression_level: Some(10),
+            ..Default::default()
+        },
+        GzipOptions {
+            compression_level: Some(u32::MAX),
+            ..Default::default()
+        },
+        GzipOptions {
+            buffer_size: Some(usize::MAX),
+            ..Default::default()
+        },
+    ] {
+        assert!(GzipOutputStream::with_options(output.as_mut(), &options).is_err());
+    }
+}
+
+#[test]
+fn test_io_limiting() {
+    let mut buffer = vec![0; 1 << 18];
+    check_some_writes(SliceOutputStream::new(&mut buffer).as_mut());
+    buffer.truncate(200_055);
+    buffer.extend(b"trailing");
+
+    let mut input = SliceInputStream::new(&buffer);
+    let mut limiting = LimitingInputStream::new(input.as_mut(), 200_055);
+    check_some_reads(limiting.as_mut());
+    assert!(limiting.as_mut().next().is_err()); // check for EOF
+    drop(limiting);
+    check_read(input.as_mut(), b"trailing");
+}
+
+#[test]
+fn test_io_limiting_back_up() {
+    let mut input = SliceInputStream::new(b"hello world");
+    let mut limiting = LimitingInputStream::new(input.as_mut(), 5);
+    assert_eq!(limiting.as_mut().next(), Ok(&b"hello"[..]));
+    limiting.as_mut().back_up(2);
+    assert_eq!(limiting.byte_count(), 3);
+    assert_eq!(limiting.as_mut().next(), Ok(&b"lo"[..]));
+    assert!(limiting.as_mut().next().is_err());
+    drop(limiting);
+    assert_eq!(input.as_mut().next(), Ok(&b" world"[..]));
+}
+
+#[test]
+fn test_io_concatenating() {
+    let mut buffer = vec![0; 1 << 18];
+    check_some_writes(SliceOutputStream::new(&mut buffer).as_mut());
+    let (a, rest) = buffer.split_at(7);
+    let (b, c) = rest.split_at(100_000);
+    let mut a = SliceInputStream::new(a);
+    let mut b = SliceInputStream::new(b);
+    let mut c = SliceInputStream::new(c);
+    let mut input = ConcatenatingInputStream::new([
+        a.as_mut() as Pin<&mut dyn ZeroCopyInputStream>,
+        b.as_mut(),
+        c.as_mut(),
+    ]);
+    check_some_reads(input.as_mut());
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_io_buf() {
+    use bytes::{Buf, Bytes};
+    use protobuf_native::io::BufInputStream;
+
+    let mut buffer = vec![0; 1 << 18];
+    check_some_writes(SliceOutputStream::new(&mut buffer).as_mut());
+    buffer.truncate(200_055);
+    let buffer = Bytes::from(buffer);
+    let mut chained = buffer
+        .slice(..7)
+        .chain(buffer.slice(7..100_007))
+        .chain(buffer.slice(100_007..));
+    check_some_reads(BufInputStream::new(&mut chained).as_mut());
+    assert!(!chained.has_remaining());
+
+    let mut buf = Bytes::from_static(b"hello world");
+    let mut input = BufInputStream::new(&mut buf);
+    assert_eq!(input.as_mut().next(), Ok(&b"hello world"[..]));
+    input.as_mut().back_up(6);
+    assert_eq!(input.byte_count(), 5);
+    drop(input);
+    assert_eq!(buf, &b" world"[..]);
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_io_buf_mut() {
+    use bytes::BytesMut;
+    use protobuf_native::io::BufMutOutputStream;
+
+    let mut buf = BytesMut::from(&b"prefix"[..]);
+    check_some_writes(BufMutOutputStream::new(&mut buf).as_mut());
+    assert_eq!(buf.len(), 6 + 200_055);
+    assert_eq!(&buf[..6], b"prefix");
+    let mut input = SliceInputStream::new(&buf[6..]);
+    check_some_reads(input.as_mut());
+
+    let mut buf = BytesMut::new();
+    let mut output = BufMutOutputStream::new(&mut buf);
+    write_bytes(output.as_mut(), b"hello");
+    assert_eq!(output.byte_count(), 5);
+    drop(output);
+    assert_eq!(&buf[..], b"hello");
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_parse_delimited_from_async_read() {
+    use protobuf_native::io::parse_delimited_from_async_read;
+    use protobuf_native::{FileDescriptorSet, MessageLite};
+
+    let mut fds = FileDescriptorSet::new();
+    fds.as_mut().add_file().set_name("a.proto");
+    let message = fds.serialize().unwrap();
+    let mut input = vec![];
+    input.push(message.len() as u8);
+    input.extend(&message);
+    input.extend([0x80, 0x01]);
+    input.extend([0; 128]);
+    input.extend([0x02, 0xff]);
+
+    let mut reader = &input[..];
+    let mut out = FileDescriptorSet::new();
+    assert!(parse_delimited_from_async_read(&mut reader, out.as_mut())
+        .await
+        .unwrap());
+    assert_eq!(out, fds);
+    // A frame of 128 zeros, whose length prefix spans two bytes, is not a
+    // valid message.
+    let err = parse_delimited_from_async_read(&mut reader, out.as_mut())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    let err = parse_delimited_from_async_read(&mut reader, out.as_mut())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    assert!(!parse_delimited_from_async_read(&mut reader, out.as_mut())
+        .await
+        .unwrap());
+
+    let mut reader = &[0xff, 0xff, 0xff, 0xff, 0xff, 0x01][..];
+    let err = parse_delimited_from_async_read(&mut reader, out.as_mut())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    // A huge length prefix must not allocate a buffer of that size up front.
+    let mut reader = &[0xff, 0xff, 0xff, 0xff, 0x07, 0x0a, 0x00][..];
+    let err = parse_delimited_from_async_read(&mut reader, out.as_mut())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
 }
 
 #[test]
@@ -128,3 +480,281 @@ fn test_io_file() {
     file.seek(SeekFrom::Start(0)).unwrap();
     check_some_reads(ReaderStream::new(&mut file).as_mut());
 }
+
+#[test]
+fn test_io_block_size() {
+    let mut buffer = vec![0; 1 << 18];
+    let mut output = SliceOutputStream::with_block_size(&mut buffer, 1000);
+    // SAFETY: we back up over the entire buffer without writing to it.
+    let len = unsafe { output.as_mut().next() }.unwrap().len();
+    assert_eq!(len, 1000);
+    output.as_mut().back_up(len);
+    check_some_writes(output.as_mut());
+    drop(output);
+    check_some_reads(SliceInputStream::new(&buffer).as_mut());
+
+    let mut file = tempfile::tempfile().unwrap();
+    let mut output = WriterStream::with_block_size(&mut file, 1 << 16);
+    // SAFETY: we back up over the entire buffer without writing to it.
+    let len = unsafe { output.as_mut().next() }.unwrap().len();
+    assert_eq!(len, 1 << 16);
+    output.as_mut().back_up(len);
+    check_some_writes(output.as_mut());
+    drop(output);
+    file.seek(SeekFrom::Start(0)).unwrap();
+    let mut input = ReaderStream::with_block_size(&mut file, 1 << 16);
+    let len = input.as_mut().next().unwrap().len();
+    assert_eq!(len, 1 << 16);
+    input.as_mut().back_up(len);
+    check_some_reads(input.as_mut());
+}
+
+#[test]
+fn test_coded_stream() {
+    let mut buffer = vec![];
+    {
+        let mut output = VecOutputStream::new(&mut buffer);
+        let mut coded = CodedOutputStream::new(output.as_mut());
+        coded.as_mut().write_varint32(300);
+        coded.as_mut().write_varint64(u64::MAX);
+        coded.as_mut().write_little_endian32(0xdeadbeef);
+        coded.as_mut().write_little_endian64(0x0123456789abcdef);
+        coded.as_mut().write_raw(b"hello");
+        assert!(!coded.as_mut().had_error());
+    }
+    assert_eq!(buffer.len(), 2 + 10 + 4 + 8 + 5);
+
+    let mut input = SliceInputStream::new(&buffer);
+    let coded = CodedInputStream::new(input.as_mut());
+    assert!(!coded.is_flat());
+    check_coded_stream(coded);
+    let coded = CodedInputStream::from_slice(&buffer);
+    assert!(coded.is_flat());
+    check_coded_stream(coded);
+}
+
+fn check_coded_stream(mut coded: Pin<Box<CodedInputStream>>) {
+    assert_eq!(coded.as_mut().read_varint32(), Ok(300));
+    assert_eq!(coded.as_mut().read_varint64(), Ok(u64::MAX));
+    let mut rest = vec![];
+    coded.as_mut().read_to_end(&mut rest).unwrap();
+    assert_eq!(
+        rest,
+        b"\xef\xbe\xad\xde\xef\xcd\xab\x89\x67\x45\x23\x01hello"
+    );
+}
+
+#[test]
+fn test_coded_output_stream_deterministic() {
+    let mut buffer = vec![];
+    let mut output = VecOutputStream::new(&mut buffer);
+    let mut coded = CodedOutputStream::new(output.as_mut());
+    assert!(!coded.is_serialization_deterministic());
+    coded.as_mut().set_serialization_deterministic(true);
+    assert!(coded.is_serialization_deterministic());
+}
+
+#[test]
+fn test_coded_output_stream_error() {
+    let mut buffer = [0; 4];
+    let mut output = SliceOutputStream::new(&mut buffer);
+    let mut coded = CodedOutputStream::new(output.as_mut());
+    coded.as_mut().write_raw(b"too long");
+    assert!(coded.as_mut().had_error());
+}
+
+#[test]
+fn test_coded_output_stream_write() {
+    let mut buffer = vec![];
+    let mut output = VecOutputStream::new(&mut buffer);
+    let mut coded = CodedOutputStream::new(output.as_mut());
+    coded.as_mut().write_varint32(300);
+    write!(coded.as_mut(), "hello {}", 42).unwrap();
+    assert_eq!(coded.byte_count(), 10);
+    coded.as_mut().flush().unwrap();
+    drop(coded);
+    drop(output);
+    assert_eq!(buffer, b"\xac\x02hello 42");
+
+    let mut buffer = [0; 4];
+    let mut output = SliceOutputStream::new(&mut buffer);
+    let mut coded = CodedOutputStream::new(output.as_mut());
+    assert!(coded.as_mut().write_all(b"too long").is_err());
+}
+
+#[test]
+fn test_coded_input_stream_limit() {
+    let buffer = b"\x01\x02\x03\x04\x05";
+    let mut input = SliceInputStream::new(buffer);
+    let mut coded = CodedInputStream::new(input.as_mut());
+    assert_eq!(coded.bytes_until_limit(), None);
+    assert_eq!(coded.bytes_until_total_bytes_limit(), None);
+
+    let outer = coded.as_mut().push_limit(4);
+    assert_eq!(coded.bytes_until_limit(), Some(4));
+    assert_eq!(coded.as_mut().read_varint32(), Ok(1));
+    let inner = coded.as_mut().push_limit(2);
+    assert_eq!(coded.bytes_until_limit(), Some(2));
+    let mut out = vec![];
+    coded.as_mut().read_to_end(&mut out).unwrap();
+    assert_eq!(out, b"\x02\x03");
+    coded.as_mut().pop_limit(inner);
+    assert_eq!(coded.bytes_until_limit(), Some(1));
+    coded.as_mut().pop_limit(outer);
+    assert_eq!(coded.bytes_until_limit(), None);
+
+    let mut out = vec![];
+    coded.as_mut().read_to_end(&mut out).unwrap();
+    assert_eq!(out, b"\x04\x05");
+}
+
+#[test]
+fn test_coded_input_stream_total_bytes_limit() {
+    let buffer = b"\x01\x02\x03\x04\x05";
+    let mut input = SliceInputStream::new(buffer);
+    let mut coded = CodedInputStream::new(input.as_mut());
+    coded.as_mut().set_total_bytes_limit(3);
+    assert_eq!(coded.bytes_until_total_bytes_limit(), Some(3));
+    let mut out = vec![];
+    let err = coded.as_mut().read_to_end(&mut out).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(out, b"\x01\x02\x03");
+    assert_eq!(coded.bytes_until_total_bytes_limit(), Some(0));
+}
+
+#[test]
+fn test_coded_input_stream_total_bytes_limit_at_end() {
+    let buffer = b"\x01\x02\x03";
+    let mut input = SliceInputStream::new(buffer);
+    let mut coded = CodedInputStream::new(input.as_mut());
+    coded.as_mut().set_total_bytes_limit(3);
+    let mut out = vec![];
+    assert_eq!(coded.as_mut().read_to_end(&mut out).unwrap(), 3);
+    assert_eq!(out, buffer);
+    assert_eq!(coded.bytes_until_total_bytes_limit(), Some(0));
+    assert_eq!(coded.as_mut().read(&mut [0; 1]).unwrap(), 0);
+    drop(coded);
+
+    // Data beyond the limit is detected even if it arrives in a later chunk.
+    let mut a = SliceInputStream::new(b"\x01\x02\x03");
+    let mut b = SliceInputStream::new(b"\x04");
+    let mut input = ConcatenatingInputStream::new([
+        a.as_mut() as Pin<&mut dyn ZeroCopyInputStream>,
+        b.as_mut(),
+    ]);
+    let mut coded = CodedInputStream::new(input.as_mut());
+    coded.as_mut().set_total_bytes_limit(3);
+    let mut out = vec![];
+    let err = coded.as_mut().read_to_end(&mut out).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(out, buffer);
+}
+
+#[test]
+fn test_coded_input_stream_read_string() {
+    let buffer = b"abc\x02de\x05f";
+    let mut input = SliceInputStream::new(buffer);
+    let mut coded = CodedInputStream::new(input.as_mut());
+    assert_eq!(coded.as_mut().read_string(3), Ok(b"abc".to_vec()));
+    assert_eq!(coded.as_mut().read_bytes(), Ok(b"de".to_vec()));
+    assert!(coded.as_mut().read_bytes().is_err());
+}
+
+#[test]
+fn test_coded_input_stream_read_raw() {
+    let buffer = b"abcdef";
+    let mut input = SliceInputStream::new(buffer);
+    let mut coded = CodedInputStream::new(input.as_mut());
+    assert_eq!(coded.as_mut().read_raw(2), Ok(b"ab".to_vec()));
+    assert_eq!(coded.as_mut().read_raw(0), Ok(vec![]));
+    assert!(coded.as_mut().read_raw(5).is_err());
+}
+
+#[test]
+fn test_coded_input_stream_read_little_endian() {
+    let buffer = b"\xef\xbe\xad\xde\xef\xcd\xab\x89\x67\x45\x23\x01\x00";
+    let mut input = SliceInputStream::new(buffer);
+    let mut coded = CodedInputStream::new(input.as_mut());
+    assert_eq!(coded.as_mut().read_little_endian32(), Ok(0xdeadbeef));
+    assert_eq!(
+        coded.as_mut().read_little_endian64(),
+        Ok(0x0123456789abcdef)
+    );
+    assert!(coded.as_mut().read_little_endian32().is_err());
+}
+
+#[test]
+fn test_decode_zigzag() {
+    for (encoded, decoded) in [(0, 0), (1, -1), (2, 1), (3, -2), (u32::MAX, i32::MIN)] {
+        assert_eq!(decode_zigzag32(encoded), decoded);
+        assert_eq!(decode_zigzag64(u64::from(encoded)), i64::from(decoded));
+    }
+    assert_eq!(decode_zigzag32(u32::MAX - 1), i32::MAX);
+    assert_eq!(decode_zigzag64(u64::MAX), i64::MIN);
+    assert_eq!(decode_zigzag64(u64::MAX - 1), i64::MAX);
+}
+
+#[test]
+fn test_printer() {
+    let mut buffer = vec![];
+    {
+        let mut output = VecOutputStream::new(&mut buffer);
+        let mut printer = Printer::new(output.as_mut(), '$');
+        printer
+            .as_mut()
+            .print_with_vars("class $name$ {\n", &[("name", "Foo")]);
+        printer.as_mut().indent();
+        printer.as_mut().print("costs $$5;\n\n");
+        printer.as_mut().outdent();
+        printer.as_mut().print("}\n");
+        assert!(!printer.failed());
+    }
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "class Foo {\n  costs $5;\n\n}\n"
+    );
+}
+
+#[test]
+#[should_panic(expected = "undefined variable in printer text")]
+fn test_printer_undefined_variable() {
+    let mut buffer = vec![];
+    let mut output = VecOutputStream::new(&mut buffer);
+    let mut printer = Printer::new(output.as_mut(), '$');
+    printer.as_mut().print("$missing$");
+}
+
+#[test]
+fn test_tokenizer() {
+    let mut input = SliceInputStream::new(b"foo = 12 // comment\n  \"bar\";\n'unterminated");
+    let mut error_collector = SimpleErrorCollector::new();
+    let mut tokenizer = Tokenizer::new(input.as_mut(), "test.proto", error_collector.as_mut());
+    assert_eq!(tokenizer.token_type(), TokenType::Start);
+    let mut tokens = vec![];
+    while tokenizer.as_mut().next() {
+        tokens.push((
+            tokenizer.token_type(),
+            String::from_utf8(tokenizer.text().to_vec()).unwrap(),
+            tokenizer.line(),
+            tokenizer.column(),
+            tokenizer.end_column(),
+        ));
+    }
+    assert_eq!(tokenizer.token_type(), TokenType::End);
+    drop(tokenizer);
+    assert_eq!(
+        tokens,
+        &[
+            (TokenType::Identifier, "foo".into(), 0, 0, 3),
+            (TokenType::Symbol, "=".into(), 0, 4, 5),
+            (TokenType::Integer, "12".into(), 0, 6, 8),
+            (TokenType::String, "\"bar\"".into(), 1, 2, 7),
+            (TokenType::Symbol, ";".into(), 1, 7, 8),
+            (TokenType::String, "'unterminated".into(), 2, 0, 13),
+        ]
+    );
+    let errors: Vec<_> = error_collector.as_mut().collect();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].filename, "test.proto");
+    assert_eq!(errors[0].message, "Unexpected end of string.");
+}
@@ -13,16 +13,35 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::env;
 use std::error::Error;
-use std::path::Path;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
 
 use pretty_assertions::assert_eq;
 
+use protobuf_native::compat::{self, Incompatibility, IncompatibilityKind};
+use protobuf_native::compiler::plugin::{CodeGeneratorRequest, CodeGeneratorResponse};
 use protobuf_native::compiler::{
-    DiskSourceTree, FileLoadError, Location, Severity, SimpleErrorCollector, SourceTree,
-    SourceTreeDescriptorDatabase, VirtualSourceTree,
+    self, CommandLineInterface, CustomSourceTree, DiskSourceTree, FileLoadError, FnErrorCollector,
+    Importer, Location, Severity, SimpleErrorCollector, SourceTree, SourceTreeDescriptorDatabase,
+    VirtualSourceTree,
+};
+use protobuf_native::io::{CodedInputStream, SliceInputStream, Tokenizer};
+use protobuf_native::json::{self, ParseOptions, PrintOptions};
+use protobuf_native::log::{self, LogLevel};
+use protobuf_native::text_format::{self, Parser, Printer};
+use protobuf_native::util::{messages_equal, Differencer};
+use protobuf_native::{
+    DescriptorDatabase, DescriptorPool, EncodedDescriptorDatabase, FieldDescriptor, FieldLabel,
+    FieldType, FileDescriptorSet, MergedDescriptorDatabase, Message, MessageLite,
+    OperationFailedError, SimpleDescriptorDatabase,
 };
-use protobuf_native::{DescriptorDatabase, MessageLite, OperationFailedError};
 
 mod io;
 mod util;
@@ -36,6 +55,262 @@ fn test_open_nonexistent() {
     assert_eq!(util::unwrap_err(res).to_string(), "File not found.");
 }
 
+#[test]
+fn test_map_well_known_types() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = DiskSourceTree::new();
+    source_tree.as_mut().map_well_known_types();
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let timestamp = db
+        .as_mut()
+        .find_file_by_name(Path::new("google/protobuf/timestamp.proto"))?;
+    assert_eq!(timestamp.message_type(0).name(), b"Timestamp");
+
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().map_well_known_types();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto3";
+
+import "google/protobuf/any.proto";
+import "google/protobuf/wrappers.proto";
+
+message Test {
+    google.protobuf.Any any = 1;
+    google.protobuf.StringValue string = 2;
+}
+"#
+        .to_vec(),
+    );
+    let mut error_collector = SimpleErrorCollector::new();
+    let mut importer = Importer::new(source_tree.as_mut(), error_collector.as_mut());
+    let test = importer.as_mut().import(Path::new("test.proto"))?;
+    assert_eq!(test.message_type(0).name(), b"Test");
+    drop(importer);
+
+    let mut source_tree = CustomSourceTree::new(|filename| {
+        let filename = filename.to_str().unwrap();
+        match compiler::well_known_type_source(filename) {
+            Some(source) => Ok(Box::new(source) as Box<dyn std::io::Read>),
+            None => Err(std::io::ErrorKind::NotFound.into()),
+        }
+    });
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let empty = db
+        .as_mut()
+        .find_file_by_name(Path::new("google/protobuf/empty.proto"))?;
+    assert_eq!(empty.message_type(0).name(), b"Empty");
+    assert!(db
+        .as_mut()
+        .find_file_by_name(Path::new("test.proto"))
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_disk_source_tree_mapping() {
+    let include = protobuf_src::include().join("google").join("protobuf");
+    let mut source_tree = DiskSourceTree::new();
+    source_tree.as_mut().map_path(Path::new("wkt"), &include);
+
+    let disk_path = include.join("timestamp.proto");
+    assert_eq!(
+        source_tree
+            .as_mut()
+            .virtual_file_to_disk_file(Path::new("wkt/timestamp.proto")),
+        Some(disk_path.clone())
+    );
+    assert_eq!(
+        source_tree
+            .as_mut()
+            .disk_file_to_virtual_file(&disk_path)
+            .as_deref(),
+        Some(Path::new("wkt/timestamp.proto"))
+    );
+
+    assert_eq!(
+        source_tree
+            .as_mut()
+            .virtual_file_to_disk_file(Path::new("wkt/noexist.proto")),
+        None
+    );
+    assert_eq!(
+        source_tree
+            .as_mut()
+            .disk_file_to_virtual_file(Path::new("/noexist/timestamp.proto")),
+        None
+    );
+}
+
+#[test]
+fn test_compile() -> Result<(), Box<dyn Error>> {
+    let include = protobuf_src::include();
+    let fds = compiler::compile(
+        &[&include],
+        &[
+            include.join("google/protobuf/api.proto"),
+            "google/protobuf/duration.proto".into(),
+        ],
+    )
+    .map_err(|errors| format!("{:?}", errors))?;
+    let names: Vec<_> = fds.iter().map(|file| file.name().to_vec()).collect();
+    assert_eq!(
+        names,
+        [
+            b"google/protobuf/source_context.proto".to_vec(),
+            b"google/protobuf/any.proto".to_vec(),
+            b"google/protobuf/type.proto".to_vec(),
+            b"google/protobuf/api.proto".to_vec(),
+            b"google/protobuf/duration.proto".to_vec(),
+        ]
+    );
+
+    // The well-known types are available even without an include directory
+    // that contains them.
+    let fds = compiler::compile::<&Path, _>(&[], &["google/protobuf/empty.proto"])
+        .map_err(|errors| format!("{:?}", errors))?;
+    assert_eq!(fds.file(0).name(), b"google/protobuf/empty.proto");
+
+    let errors = util::unwrap_err(compiler::compile(&[&include], &["noexist.proto"]));
+    assert_eq!(
+        errors,
+        [FileLoadError {
+            filename: "noexist.proto".into(),
+            message: "File not found.".into(),
+            severity: Severity::Error,
+            location: None,
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_parse_file() -> Result<(), Box<dyn Error>> {
+    let file = compiler::parse_file(
+        "test.proto",
+        br#"
+syntax = "proto3";
+
+package test;
+
+import "google/protobuf/timestamp.proto";
+
+message M {
+    google.protobuf.Timestamp ts = 1;
+}
+"#,
+    )
+    .map_err(|errors| format!("{:?}", errors))?;
+    assert_eq!(file.name(), b"test.proto");
+    assert_eq!(file.package(), b"test");
+    assert_eq!(file.dependency(0), b"google/protobuf/timestamp.proto");
+    assert_eq!(file.message_type(0).name(), b"M");
+
+    let errors = util::unwrap_err(compiler::parse_file(
+        "bad.proto",
+        b"syntax = \"proto3\"; message M {",
+    ));
+    assert_eq!(
+        errors,
+        [FileLoadError {
+            filename: "bad.proto".into(),
+            message: "Reached end of input in message definition (missing '}').".into(),
+            severity: Severity::Error,
+            location: Some(Location {
+                line: 1,
+                column: 31,
+            }),
+        }]
+    );
+
+    let errors = util::unwrap_err(compiler::parse_file(
+        "import.proto",
+        b"syntax = \"proto3\"; import \"missing.proto\";",
+    ));
+    assert_eq!(
+        errors,
+        [FileLoadError {
+            filename: "missing.proto".into(),
+            message: "File not found.".into(),
+            severity: Severity::Error,
+            location: None,
+        }]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_command_line_interface() -> Result<(), Box<dyn Error>> {
+    let out_dir = env::temp_dir().join(format!("protobuf-native-cli-{}", process::id()));
+    fs::create_dir_all(&out_dir)?;
+    let include = protobuf_src::include();
+
+    let mut cli = CommandLineInterface::new();
+    let code = cli.as_mut().run(&[
+        format!("-I{}", include.display()),
+        format!("--cpp_out={}", out_dir.display()),
+        format!("--descriptor_set_out={}", out_dir.join("fds.bin").display()),
+        "google/protobuf/duration.proto".into(),
+    ]);
+    assert_eq!(code, 0);
+    assert!(out_dir.join("google/protobuf/duration.pb.h").exists());
+    let fds = FileDescriptorSet::parse_from_bytes(&fs::read(out_dir.join("fds.bin"))?)?;
+    assert_eq!(fds.file(0).name(), b"google/protobuf/duration.proto");
+
+    assert_eq!(cli.as_mut().run(&["--bogus"]), 1);
+    fs::remove_dir_all(&out_dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_plugin_messages() -> Result<(), Box<dyn Error>> {
+    let fds = compiler::compile(&[protobuf_src::include()], &["google/protobuf/api.proto"])
+        .map_err(|errors| format!("{:?}", errors))?;
+    let mut request = CodeGeneratorRequest::new();
+    request
+        .as_mut()
+        .add_file_to_generate("google/protobuf/api.proto");
+    request.as_mut().set_parameter("opt=1");
+    for file in &*fds {
+        request.as_mut().add_proto_file().copy_from(file);
+    }
+    assert_eq!(
+        request.type_name(),
+        "google.protobuf.compiler.CodeGeneratorRequest"
+    );
+
+    let mut parsed = CodeGeneratorRequest::new();
+    parsed.as_mut().parse_from_bytes(&request.serialize()?)?;
+    assert_eq!(parsed, request);
+    assert_eq!(parsed.file_to_generate_size(), 1);
+    assert_eq!(parsed.file_to_generate(0), b"google/protobuf/api.proto");
+    assert_eq!(parsed.parameter(), b"opt=1");
+    assert_eq!(parsed.proto_file_size(), fds.file_size());
+    assert_eq!(parsed.proto_file(3).name(), b"google/protobuf/api.proto");
+
+    let mut response = CodeGeneratorResponse::new();
+    response
+        .as_mut()
+        .set_supported_features(CodeGeneratorResponse::FEATURE_PROTO3_OPTIONAL);
+    let mut file = response.as_mut().add_file();
+    file.as_mut().set_name("api.txt");
+    file.as_mut().set_insertion_point("top");
+    file.as_mut().set_content("generated\n");
+    let mut parsed = CodeGeneratorResponse::new();
+    parsed.as_mut().parse_from_bytes(&response.serialize()?)?;
+    assert_eq!(parsed, response);
+    assert_eq!(parsed.supported_features(), 1);
+    assert_eq!(parsed.error(), b"");
+    assert_eq!(parsed.file_size(), 1);
+    assert_eq!(parsed.file(0).name(), b"api.txt");
+    assert_eq!(parsed.file(0).insertion_point(), b"top");
+    assert_eq!(parsed.file(0).content(), b"generated\n");
+
+    response.as_mut().set_error("unsupported");
+    assert_eq!(response.error(), b"unsupported");
+    Ok(())
+}
+
 /// Test that opening a path with disallowed path characters fails with a
 /// descriptive error message.
 ///
@@ -97,6 +372,44 @@ fn test_file_load_error_display() {
     }
 }
 
+#[test]
+fn test_simple_error_collector_severity() {
+    fn load(
+        source_tree: Pin<&mut VirtualSourceTree>,
+        error_collector: Pin<&mut SimpleErrorCollector>,
+    ) {
+        let mut db = SourceTreeDescriptorDatabase::new(source_tree);
+        db.as_mut().record_errors_to(error_collector);
+        assert!(db
+            .as_mut()
+            .find_file_by_name(Path::new("test.proto"))
+            .is_err());
+    }
+
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree
+        .as_mut()
+        .add_file(Path::new("test.proto"), b"message M {".to_vec());
+    let mut error_collector = SimpleErrorCollector::new();
+    assert!(!error_collector.has_errors());
+    load(source_tree.as_mut(), error_collector.as_mut());
+    assert!(error_collector.has_errors());
+    let errors: Vec<_> = error_collector.as_mut().errors_only().collect();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message,
+        "Reached end of input in message definition (missing '}')."
+    );
+    assert!(!error_collector.has_errors());
+    assert_eq!(error_collector.as_mut().count(), 0);
+
+    load(source_tree.as_mut(), error_collector.as_mut());
+    let warnings: Vec<_> = error_collector.as_mut().warnings_only().collect();
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].message.starts_with("No syntax specified."));
+    assert!(!error_collector.has_errors());
+}
+
 // Test that loading a file with parser errors produces descriptive error
 // messages with the appropriate locations.
 #[test]
@@ -116,7 +429,7 @@ message M {
     let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
     db.as_mut().record_errors_to(error_collector.as_mut());
     let res = db.as_mut().find_file_by_name(Path::new("test.proto"));
-    assert_eq!(util::unwrap_err(res), OperationFailedError);
+    assert_eq!(util::unwrap_err(res), OperationFailedError::new());
     drop(db);
     let errors: Vec<_> = error_collector.as_mut().collect();
     assert_eq!(
@@ -144,6 +457,80 @@ message M {
     )
 }
 
+// Test that `compiler::Parser` parses tokens into a `FileDescriptorProto` and
+// reports errors through the tokenizer's error collector.
+#[test]
+fn test_parser() -> Result<(), Box<dyn Error>> {
+    let mut input = SliceInputStream::new(
+        br#"syntax = "proto3";
+message M {
+    string s = 1;
+}
+"#,
+    );
+    let mut error_collector = SimpleErrorCollector::new();
+    let mut tokenizer = Tokenizer::new(input.as_mut(), "test.proto", error_collector.as_mut());
+    let file = compiler::Parser::new().as_mut().parse(tokenizer.as_mut())?;
+    drop(tokenizer);
+    assert_eq!(error_collector.as_mut().count(), 0);
+    assert_eq!(file.syntax(), b"proto3");
+    assert_eq!(file.message_type(0).name(), b"M");
+    assert!(file.source_code_info().is_some());
+
+    let mut input = SliceInputStream::new(b"message M { optional string s = 1; }");
+    let mut tokenizer = Tokenizer::new(input.as_mut(), "test.proto", error_collector.as_mut());
+    let mut parser = compiler::Parser::new();
+    parser.as_mut().set_retain_source_code_info(false);
+    let file = parser.as_mut().parse(tokenizer.as_mut())?;
+    drop(tokenizer);
+    assert!(file.source_code_info().is_none());
+    let errors: Vec<_> = error_collector.as_mut().collect();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].severity, Severity::Warning);
+
+    let mut input = SliceInputStream::new(b"message M {\n    f = 1;\n");
+    let mut tokenizer = Tokenizer::new(input.as_mut(), "test.proto", error_collector.as_mut());
+    let res = compiler::Parser::new().as_mut().parse(tokenizer.as_mut());
+    assert_eq!(util::unwrap_err(res), OperationFailedError::new());
+    drop(tokenizer);
+    let errors: Vec<_> = error_collector.as_mut().collect();
+    assert!(errors.contains(&FileLoadError {
+        filename: "test.proto".into(),
+        message: r#"Expected "required", "optional", or "repeated"."#.into(),
+        severity: Severity::Error,
+        location: Some(Location { line: 2, column: 5 }),
+    }));
+    Ok(())
+}
+
+// Test that a `FileLoadError` with attached source produces a
+// `miette::Diagnostic` whose label points at the error location.
+#[cfg(feature = "miette")]
+#[test]
+fn test_file_load_error_diagnostic() {
+    use miette::Diagnostic;
+
+    let source = "syntax = \"proto2\";\n\nmessage M {\n\tf = 1;\n}\n";
+    let error = FileLoadError {
+        filename: "test.proto".into(),
+        message: "Expected field name.".into(),
+        severity: Severity::Error,
+        location: Some(Location {
+            line: 4,
+            column: 11,
+        }),
+    };
+    let diagnostic = error.clone().with_source(source);
+    assert_eq!(diagnostic.to_string(), error.to_string());
+    assert_eq!(diagnostic.severity(), Some(miette::Severity::Error));
+    let labels: Vec<_> = diagnostic.labels().unwrap().collect();
+    assert_eq!(labels.len(), 1);
+    assert_eq!(labels[0].label(), Some("Expected field name."));
+    assert_eq!(labels[0].offset(), source.rfind('=').unwrap());
+    assert_eq!(labels[0].len(), 1);
+    assert_eq!(diagnostic.into_error(), error);
+}
+
 // Test that loading a file that triggers parser warnings propagates those
 // warnings with the appropriate locations.
 #[test]
@@ -181,6 +568,87 @@ message bad_to_the_bone {}
     )
 }
 
+#[test]
+fn test_warnings_as_errors() {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto2";
+
+message bad_to_the_bone {}
+"#
+        .to_vec(),
+    );
+    source_tree.as_mut().add_file(
+        Path::new("clean.proto"),
+        b"syntax = \"proto2\"; message Clean {}".to_vec(),
+    );
+    let mut error_collector = SimpleErrorCollector::new();
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    db.as_mut().record_errors_to(error_collector.as_mut());
+    db.as_mut().set_warnings_as_errors(true);
+    assert!(db
+        .as_mut()
+        .find_file_by_name(Path::new("test.proto"))
+        .is_err());
+    assert!(db
+        .as_mut()
+        .find_file_by_name(Path::new("clean.proto"))
+        .is_ok());
+    db.as_mut().set_warnings_as_errors(false);
+    assert!(db
+        .as_mut()
+        .find_file_by_name(Path::new("test.proto"))
+        .is_ok());
+    drop(db);
+    let errors: Vec<_> = error_collector.as_mut().collect();
+    assert_eq!(errors.len(), 2);
+    assert!(errors.iter().all(|e| e.severity == Severity::Warning));
+}
+
+#[test]
+fn test_fn_error_collector() {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto2";
+
+message bad_to_the_bone {
+"#
+        .to_vec(),
+    );
+    let mut errors = vec![];
+    let mut error_collector = FnErrorCollector::new(|error| errors.push(error.clone()));
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    db.as_mut().record_errors_to(error_collector.as_mut());
+    let res = db.as_mut().find_file_by_name(Path::new("test.proto"));
+    assert!(res.is_err());
+    drop(db);
+    drop(error_collector);
+    assert_eq!(
+        errors
+            .iter()
+            .map(|e| (e.severity, e.location.clone()))
+            .collect::<Vec<_>>(),
+        &[
+            (
+                Severity::Warning,
+                Some(Location {
+                    line: 4,
+                    column: 25
+                })
+            ),
+            (Severity::Error, Some(Location { line: 5, column: 1 })),
+        ],
+    );
+    assert_eq!(
+        errors[1].message,
+        "Reached end of input in message definition (missing '}')."
+    );
+}
+
 #[test]
 fn test_file_descriptor_set() -> Result<(), Box<dyn Error>> {
     let mut source_tree = VirtualSourceTree::new();
@@ -214,9 +682,1787 @@ message Test {
         .build_file_descriptor_set(&[Path::new("root.proto")])?;
     assert_eq!(fds.file_size(), 2);
     assert_eq!(fds.file(0).message_type_size(), 1);
-    assert_eq!(fds.file(0).message_type(0).name(), b"Test");
+    assert_eq!(fds.file(0).message_type(0).name(), b"ImportMe");
+    assert_eq!(fds.file(1).message_type_size(), 1);
+    assert_eq!(fds.file(1).message_type(0).name(), b"Test");
     let mut out = vec![];
     fds.serialize_to_writer(&mut out)?;
     assert!(out.len() > 0);
+
+    let mut buf = Vec::with_capacity(4096);
+    buf.extend_from_slice(b"garbage");
+    let ptr = buf.as_ptr();
+    fds.serialize_to_vec(&mut buf)?;
+    assert_eq!(buf, out);
+    fds.file(0).serialize_to_vec(&mut buf)?;
+    assert_eq!(buf, fds.file(0).serialize()?);
+    assert_eq!(buf.as_ptr(), ptr);
+
+    let names: Vec<_> = fds.iter().map(|f| f.name()).collect();
+    assert_eq!(names, [&b"imported.proto"[..], b"root.proto"]);
+    assert_eq!(fds.iter().len(), 2);
+    assert_eq!(fds.iter().next_back().unwrap().name(), b"root.proto");
+    for (i, file) in (&*fds).into_iter().enumerate() {
+        assert_eq!(file.name(), fds[i].name());
+    }
+
+    assert_eq!(fds.type_name(), "google.protobuf.FileDescriptorSet");
+    assert_eq!(
+        fds.file(0).type_name(),
+        "google.protobuf.FileDescriptorProto"
+    );
+    assert!(fds.space_used() > fds.file(0).space_used());
+    assert!(fds.file(0).space_used() > fds.file(0).message_type(0).space_used());
+
+    let mut copy = fds.clone();
+    assert_eq!(copy, fds);
+    copy.as_mut().file_mut(1).set_name("renamed.proto");
+    assert_eq!(copy.file(1).name(), b"renamed.proto");
+    assert_eq!(fds.file(1).name(), b"root.proto");
+    let mut file = fds.file(0).clone();
+    file.as_mut().set_package("pkg");
+    assert_eq!(file.name(), b"imported.proto");
+    assert_eq!(file.package(), b"pkg");
+    assert_eq!(fds.file(0).package(), b"");
+
+    let parsed = FileDescriptorSet::parse_from_bytes(&out)?;
+    assert_eq!(parsed.file_size(), 2);
+    assert!(parsed == fds);
+    assert!(parsed != copy);
+    assert!(&*parsed as &dyn Message == &*fds as &dyn Message);
+    assert!(fds.file(0) as &dyn Message != &*fds as &dyn Message);
+    assert_eq!(parsed.file(1).message_type(0).name(), b"Test");
+    assert!(FileDescriptorSet::parse_from_bytes(b"\xff").is_err());
+
+    let mut fds = FileDescriptorSet::new();
+    assert_eq!(fds.file_size(), 0);
+    fds.as_mut().add_file().set_name("empty.proto");
+    assert_eq!(fds.file(0).name(), b"empty.proto");
+    Ok(())
+}
+
+#[test]
+fn test_file_descriptor_proto_setters() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree
+        .as_mut()
+        .add_file(Path::new("empty.proto"), b"syntax = \"proto3\";".to_vec());
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+
+    let mut dep = db.as_mut().find_file_by_name(Path::new("empty.proto"))?;
+    dep.as_mut().set_name("dep.proto");
+    dep.as_mut().set_package("test");
+    dep.as_mut().add_message_type().set_name("Dep");
+
+    let mut root = db.as_mut().find_file_by_name(Path::new("empty.proto"))?;
+    root.as_mut().set_name("root.proto");
+    root.as_mut().set_package("test");
+    root.as_mut().add_dependency("dep.proto");
+    root.as_mut().add_message_type().set_name("Root");
+    assert_eq!(root.name(), b"root.proto");
+    assert_eq!(root.package(), b"test");
+    assert_eq!(root.dependency_size(), 1);
+    assert_eq!(root.dependency(0), b"dep.proto");
+    assert_eq!(root.message_type_size(), 1);
+    assert_eq!(root.message_type(0).name(), b"Root");
+
+    let mut pool = DescriptorPool::new();
+    pool.as_mut().build_file(&dep)?;
+    let root = pool.as_mut().build_file(&root)?;
+    assert_eq!(root.message_type(0).full_name(), b"test.Root");
+    Ok(())
+}
+
+#[test]
+fn test_source_code_info() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"syntax = "proto3";
+
+// Detached.
+
+// Leading.
+message A { // Trailing.
+}
+"#
+        .to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let fds = db
+        .as_mut()
+        .build_file_descriptor_set(&[Path::new("test.proto")])?;
+    let info = fds.file(0).source_code_info().unwrap();
+    let location = (0..info.location_size())
+        .map(|i| info.location(i))
+        .find(|location| location.path() == [4, 0])
+        .unwrap();
+    assert_eq!(location.span(), [5, 0, 6, 1]);
+    assert_eq!(location.leading_comments(), b" Leading.\n");
+    assert_eq!(location.trailing_comments(), b" Trailing.\n");
+    assert_eq!(location.leading_detached_comments_size(), 1);
+    assert_eq!(location.leading_detached_comments(0), b" Detached.\n");
+
+    db.as_mut().set_retain_source_code_info(false);
+    let fds = db
+        .as_mut()
+        .build_file_descriptor_set(&[Path::new("test.proto")])?;
+    assert!(fds.file(0).source_code_info().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_delimited_messages() -> Result<(), Box<dyn Error>> {
+    let mut protos = vec![];
+    for name in ["a.proto", "b.proto", ""] {
+        let mut fds = FileDescriptorSet::new();
+        if !name.is_empty() {
+            fds.as_mut().add_file().set_name(name);
+        }
+        protos.push(fds);
+    }
+
+    let mut out = vec![];
+    for fds in &protos {
+        fds.serialize_delimited_to_writer(&mut out)?;
+    }
+    assert_eq!(out[0] as usize, protos[0].byte_size());
+
+    let mut reader = &out[..];
+    let mut fds = FileDescriptorSet::new();
+    for expected in &protos {
+        assert!(fds.as_mut().parse_delimited_from_reader(&mut reader)?);
+        assert_eq!(fds, *expected);
+    }
+    assert!(!fds.as_mut().parse_delimited_from_reader(&mut reader)?);
+
+    let mut reader = &out[..out.len() - 2];
+    fds.as_mut().parse_delimited_from_reader(&mut reader)?;
+    let err = fds
+        .as_mut()
+        .parse_delimited_from_reader(&mut reader)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+    let mut input = SliceInputStream::new(&out);
+    let mut coded = CodedInputStream::new(input.as_mut());
+    for expected in &protos {
+        fds.as_mut()
+            .parse_delimited_from_coded_stream(coded.as_mut())?;
+        assert_eq!(fds, *expected);
+    }
+    assert!(fds
+        .as_mut()
+        .parse_delimited_from_coded_stream(coded.as_mut())
+        .is_err());
+
+    // A huge length prefix must not allocate a buffer of that size up front.
+    let mut reader = &[0xff, 0xff, 0xff, 0xff, 0x07, 0x0a, 0x00][..];
+    let err = fds
+        .as_mut()
+        .parse_delimited_from_reader(&mut reader)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+    // After a malformed message, the next message can still be read.
+    for malformed in [[0x02, 0x0f, 0x00], [0x02, 0x00, 0x00]] {
+        let mut out = malformed.to_vec();
+        protos[0].serialize_delimited_to_writer(&mut out)?;
+        let mut input = SliceInputStream::new(&out);
+        let mut coded = CodedInputStream::new(input.as_mut());
+        assert!(fds
+            .as_mut()
+            .parse_delimited_from_coded_stream(coded.as_mut())
+            .is_err());
+        fds.as_mut()
+            .parse_delimited_from_coded_stream(coded.as_mut())?;
+        assert_eq!(fds, protos[0]);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_file_descriptor_set_order() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    for (name, imports) in [
+        ("root.proto", &["b.proto", "a.proto"][..]),
+        ("other_root.proto", &["c.proto"]),
+        ("a.proto", &["c.proto"]),
+        ("b.proto", &["c.proto", "a.proto"]),
+        ("c.proto", &[]),
+    ] {
+        let mut contents = String::from("syntax = \"proto3\";\n");
+        for import in imports {
+            contents += &format!("import \"{}\";\n", import);
+        }
+        source_tree
+            .as_mut()
+            .add_file(Path::new(name), contents.into_bytes());
+    }
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    for _ in 0..3 {
+        let fds = db
+            .as_mut()
+            .build_file_descriptor_set(&[Path::new("root.proto"), Path::new("other_root.proto")])?;
+        let names: Vec<_> = (0..fds.file_size()).map(|i| fds.file(i).name()).collect();
+        assert_eq!(
+            names,
+            [
+                &b"c.proto"[..],
+                b"a.proto",
+                b"b.proto",
+                b"root.proto",
+                b"other_root.proto"
+            ]
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_file_descriptor_set_import_cycle() {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("root.proto"),
+        b"syntax = \"proto3\"; import \"a.proto\";".to_vec(),
+    );
+    source_tree.as_mut().add_file(
+        Path::new("a.proto"),
+        b"syntax = \"proto3\"; import \"b.proto\";".to_vec(),
+    );
+    source_tree.as_mut().add_file(
+        Path::new("b.proto"),
+        b"syntax = \"proto3\"; import \"a.proto\";".to_vec(),
+    );
+    let mut error_collector = SimpleErrorCollector::new();
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    db.as_mut().record_errors_to(error_collector.as_mut());
+    let res = db
+        .as_mut()
+        .build_file_descriptor_set(&[Path::new("root.proto")]);
+    assert!(res.is_err());
+    drop(db);
+    let errors: Vec<_> = error_collector.as_mut().collect();
+    assert_eq!(
+        errors,
+        &[FileLoadError {
+            filename: "b.proto".into(),
+            message: "File recursively imports itself: a.proto -> b.proto -> a.proto".into(),
+            severity: Severity::Error,
+            location: None,
+        }],
+    );
+}
+
+#[test]
+fn test_field_descriptor_group() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto2";
+
+package test;
+
+message M {
+    optional group G = 1 {
+        optional int32 a = 2;
+    }
+    optional M m = 3;
+    optional sint64 s = 4;
+}
+"#
+        .to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+    let descriptor = proto.get_descriptor();
+    assert_eq!(
+        descriptor.full_name(),
+        b"google.protobuf.FileDescriptorProto"
+    );
+    assert_eq!(
+        descriptor.file().name(),
+        b"google/protobuf/descriptor.proto"
+    );
+    let mut pool = DescriptorPool::new();
+    let file = pool.as_mut().build_file(&proto)?;
+    assert_eq!(file.name(), b"test.proto");
+    assert_eq!(file.message_type_count(), 1);
+    let message = file.message_type(0);
+    assert_eq!(message.name(), b"M");
+    assert_eq!(message.full_name(), b"test.M");
+    assert_eq!(message.field_count(), 3);
+    assert_eq!(message.file().name(), b"test.proto");
+    assert_eq!(message.nested_type_count(), 1);
+    let group = message.nested_type(0);
+    assert_eq!(group.full_name(), b"test.M.G");
+    assert_eq!(group.field(0).name(), b"a");
+    assert_eq!(group.nested_type_count(), 0);
+    let fields: Vec<_> = (0..message.field_count())
+        .map(|i| {
+            let field = message.field(i);
+            (
+                field.name(),
+                field.number(),
+                field.type_(),
+                field.is_group(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        fields,
+        &[
+            (&b"g"[..], 1, FieldType::Group, true),
+            (&b"m"[..], 3, FieldType::Message, false),
+            (&b"s"[..], 4, FieldType::Sint64, false),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_file_descriptor_to_proto() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto3";
+
+package test;
+
+message M {
+    M m = 1;
+}
+"#
+        .to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+    assert_eq!(proto.message_type(0).field(0).type_name(), b"M");
+    let mut pool = DescriptorPool::new();
+    let file = pool.as_mut().build_file(&proto)?;
+
+    let canonical = file.to_proto();
+    assert_eq!(canonical.name(), b"test.proto");
+    assert_eq!(canonical.package(), b"test");
+    assert_eq!(canonical.syntax(), b"proto3");
+    assert!(canonical.source_code_info().is_none());
+    let field = canonical.message_type(0).field(0);
+    assert_eq!(field.type_(), Some(FieldType::Message));
+    assert_eq!(field.type_name(), b".test.M");
+
+    let mut pool = DescriptorPool::new();
+    let rebuilt = pool.as_mut().build_file(&canonical)?;
+    assert_eq!(rebuilt.to_proto(), canonical);
+    Ok(())
+}
+
+#[test]
+fn test_descriptor_to_proto() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto3";
+
+package test;
+
+message Outer {
+    message Inner {
+        Outer outer = 1;
+    }
+    Inner inner = 1;
+}
+"#
+        .to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+    let mut pool = DescriptorPool::new();
+    let file = pool.as_mut().build_file(&proto)?;
+
+    let outer = file.message_type(0).to_proto();
+    assert_eq!(outer.name(), b"Outer");
+    assert_eq!(outer.field(0).type_name(), b".test.Outer.Inner");
+    assert_eq!(outer.nested_type_size(), 1);
+    assert_eq!(*outer, *file.to_proto().message_type(0));
+
+    let inner = file.message_type(0).nested_type(0).to_proto();
+    assert_eq!(inner.name(), b"Inner");
+    assert_eq!(inner.field(0).type_name(), b".test.Outer");
+    Ok(())
+}
+
+#[test]
+fn test_descriptor_pool_extensions() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto2";
+
+package test;
+
+message M {
+    extensions 100 to 200;
+
+    enum E {
+        E0 = 0;
+    }
+}
+
+extend M {
+    optional int32 e1 = 100;
+    optional string e2 = 150;
+}
+"#
+        .to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+    let mut pool = DescriptorPool::new();
+    pool.as_mut().build_file(&proto)?;
+    assert!(pool.find_message_type_by_name("test.Missing").is_none());
+    assert_eq!(
+        pool.find_file_by_name("test.proto").unwrap().name(),
+        b"test.proto"
+    );
+    assert!(pool.find_file_by_name("missing.proto").is_none());
+    for symbol in ["test.M", "test.M.E0", "test.e2"] {
+        assert_eq!(
+            pool.find_file_containing_symbol(symbol).unwrap().name(),
+            b"test.proto"
+        );
+    }
+    assert!(pool.find_file_containing_symbol("test.Missing").is_none());
+    let enum_type = pool.find_enum_type_by_name("test.M.E").unwrap();
+    assert_eq!(enum_type.name(), b"E");
+    assert_eq!(enum_type.file().name(), b"test.proto");
+    assert!(pool.find_enum_type_by_name("test.M").is_none());
+    let message = pool.find_message_type_by_name("test.M").unwrap();
+    assert_eq!(message.full_name(), b"test.M");
+
+    let e1 = pool.find_extension_by_number(message, 100).unwrap();
+    assert_eq!(e1.full_name(), b"test.e1");
+    assert_eq!(e1.type_(), FieldType::Int32);
+    assert!(e1.is_extension());
+    assert!(pool.find_extension_by_number(message, 101).is_none());
+
+    let mut extensions: Vec<_> = pool
+        .find_all_extensions(message)
+        .into_iter()
+        .map(|field| (field.name(), field.number()))
+        .collect();
+    extensions.sort();
+    assert_eq!(extensions, &[(&b"e1"[..], 100), (&b"e2"[..], 150)]);
+    Ok(())
+}
+
+#[test]
+fn test_generated_messages() -> Result<(), Box<dyn Error>> {
+    let descriptor = DescriptorPool::generated_pool()
+        .find_message_type_by_name("google.protobuf.Duration")
+        .unwrap();
+    let mut message = descriptor.new_generated_message().unwrap();
+    assert_eq!(message.type_name(), "google.protobuf.Duration");
+    text_format::parse_from_str("seconds: 1", message.as_mut())?;
+    assert_eq!(message.serialize()?, [0x08, 0x01]);
+
+    // Only the message types compiled into libprotobuf can be instantiated.
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree
+        .as_mut()
+        .add_file(Path::new("test.proto"), b"message A {}".to_vec());
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+    let mut pool = DescriptorPool::new();
+    pool.as_mut().build_file(&proto)?;
+    let descriptor = pool.find_message_type_by_name("A").unwrap();
+    assert!(descriptor.new_generated_message().is_none());
+    Ok(())
+}
+
+#[test]
+fn test_serialize_deterministic() -> Result<(), Box<dyn Error>> {
+    // The `fields` of a `google.protobuf.Struct` are a map, so `serialize` may
+    // write them in any order.
+    let descriptor = DescriptorPool::generated_pool()
+        .find_message_type_by_name("google.protobuf.Struct")
+        .unwrap();
+    let keys = ["b", "d", "a", "c"];
+    let mut expected = vec![];
+    for key in ["a", "b", "c", "d"] {
+        // `fields { key: "..." value { bool_value: true } }`
+        expected.extend([
+            0x0a,
+            0x07,
+            0x0a,
+            0x01,
+            key.as_bytes()[0],
+            0x12,
+            0x02,
+            0x20,
+            0x01,
+        ]);
+    }
+    for rotation in 0..keys.len() {
+        let mut message = descriptor.new_generated_message().unwrap();
+        for key in keys.iter().cycle().skip(rotation).take(keys.len()) {
+            let input = format!("fields {{ key: {:?} value {{ bool_value: true }} }}", key);
+            text_format::merge_from_str(&input, message.as_mut())?;
+        }
+        assert_eq!(message.serialize_deterministic()?, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_from_bytes() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto3";
+
+message A {}
+message B {}
+"#
+        .to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+    let bytes = proto.serialize()?;
+
+    let mut message = proto.new();
+    message.as_mut().parse_from_bytes(&bytes)?;
+    assert_eq!(message.serialize()?, bytes);
+    assert_eq!(message.serialize_deterministic()?, bytes);
+
+    // Parsing replaces the existing contents, while merging appends to
+    // repeated fields.
+    message.as_mut().parse_from_bytes(&bytes)?;
+    assert_eq!(message.byte_size(), proto.byte_size());
+    message.as_mut().merge_from_bytes(&bytes)?;
+    assert!(message.byte_size() > proto.byte_size());
+
+    // A trailing end-group tag is not a legitimate message end.
+    let mut garbage = bytes.clone();
+    garbage.push(0x0c);
+    assert!(message.as_mut().parse_from_bytes(&garbage).is_err());
+    assert!(message.as_mut().parse_from_bytes(b"\xff").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_parse_from_zero_copy_stream() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto3";
+
+message A {}
+"#
+        .to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+    let bytes = proto.serialize()?;
+
+    let mut message = proto.new();
+    message
+        .as_mut()
+        .parse_from_zero_copy_stream(SliceInputStream::new(&bytes).as_mut())?;
+    assert_eq!(message.serialize()?, bytes);
+    message
+        .as_mut()
+        .merge_from_zero_copy_stream(SliceInputStream::new(&bytes).as_mut())?;
+    assert!(message.byte_size() > proto.byte_size());
+
+    message.as_mut().parse_from_reader(&mut &bytes[..])?;
+    assert_eq!(message.serialize()?, bytes);
+    Ok(())
+}
+
+#[test]
+fn test_recursion_limit() -> Result<(), Box<dyn Error>> {
+    // A `FileDescriptorProto` whose first message type has three levels of
+    // nested types.
+    let mut bytes = vec![];
+    for tag in [0x1a, 0x1a, 0x1a, 0x22] {
+        let len = u8::try_from(bytes.len())?;
+        bytes.splice(0..0, [tag, len]);
+    }
+
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree
+        .as_mut()
+        .add_file(Path::new("test.proto"), b"syntax = \"proto3\";".to_vec());
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let mut proto = db
+        .as_mut()
+        .find_file_by_name(Path::new("test.proto"))?
+        .new();
+    for (limit, ok) in [(3, false), (4, true)] {
+        let mut input = SliceInputStream::new(&bytes);
+        let mut input = CodedInputStream::new(input.as_mut());
+        input.as_mut().set_recursion_limit(limit);
+        let res = proto.as_mut().merge_from_coded_stream(input.as_mut());
+        assert_eq!(res.is_ok(), ok);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_json() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = DiskSourceTree::new();
+    source_tree
+        .as_mut()
+        .map_path(Path::new(""), &protobuf_src::include());
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let descriptor_proto = db
+        .as_mut()
+        .find_file_by_name(Path::new("google/protobuf/descriptor.proto"))?;
+    let mut pool = DescriptorPool::new();
+    pool.as_mut().build_file(&descriptor_proto)?;
+
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto3";
+
+message A {}
+"#
+        .to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let mut proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+
+    let json = r#"{"name":"test.proto","messageType":[{"name":"A"}],"syntax":"proto3"}"#;
+    json::json_to_message(json, proto.as_mut(), ParseOptions::default())?;
+    assert_eq!(proto.message_type(0).name(), b"A");
+    assert_eq!(
+        json::message_to_json(&*proto, pool.as_mut(), PrintOptions::default())?,
+        json
+    );
+    let options = PrintOptions::default().with_preserve_proto_field_names(true);
+    assert_eq!(
+        json::message_to_json(&*proto, pool.as_mut(), options)?,
+        r#"{"name":"test.proto","message_type":[{"name":"A"}],"syntax":"proto3"}"#
+    );
+    let options = PrintOptions::default().with_whitespace(true);
+    let pretty = json::message_to_json(&*proto, pool.as_mut(), options)?;
+    assert!(pretty.contains("\n"));
+    assert_eq!(pretty.split_whitespace().collect::<String>(), json);
+
+    let unknown = r#"{"name":"test.proto","bogus":1}"#;
+    assert!(json::json_to_message(unknown, proto.as_mut(), ParseOptions::default()).is_err());
+    let options = ParseOptions {
+        ignore_unknown_fields: true,
+        ..Default::default()
+    };
+    json::json_to_message(unknown, proto.as_mut(), options)?;
+    assert!(json::json_to_message("{", proto.as_mut(), options).is_err());
+
+    // Types must be present in the pool to be printed.
+    let mut empty_pool = DescriptorPool::new();
+    assert!(json::message_to_json(&*proto, empty_pool.as_mut(), PrintOptions::default()).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_field_descriptor_proto() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto2";
+
+package test.pkg;
+
+message A {
+    required int64 a = 1;
+    repeated B b = 2;
+    optional string c = 7;
+
+    reserved 3, 5 to 6;
+    reserved "d";
+
+    message Nested {}
+
+    enum NestedEnum {
+        N0 = 0;
+    }
+
+    oneof o {
+        int32 x = 8;
+    }
+}
+
+message B {}
+
+enum E {
+    E0 = 0;
+    E5 = 5;
+}
+
+service S {
+    rpc M(A) returns (.test.pkg.B);
+}
+"#
+        .to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+    assert_eq!(proto.name(), b"test.proto");
+    assert_eq!(proto.package(), b"test.pkg");
+    assert_eq!(proto.syntax(), b"proto2");
+    assert_eq!(proto.enum_type_size(), 1);
+    assert_eq!(proto.enum_type(0).name(), b"E");
+    assert_eq!(proto.enum_type(0).value_size(), 2);
+    assert_eq!(proto.enum_type(0).value(1).name(), b"E5");
+    assert_eq!(proto.enum_type(0).value(1).number(), 5);
+    assert_eq!(proto.service_size(), 1);
+    assert_eq!(proto.service(0).name(), b"S");
+    assert_eq!(proto.service(0).method_size(), 1);
+    let method = proto.service(0).method(0);
+    assert_eq!(method.name(), b"M");
+    assert_eq!(method.input_type(), b"A");
+    assert_eq!(method.output_type(), b".test.pkg.B");
+
+    let message = proto.message_type(0);
+    assert_eq!(message.field_size(), 4);
+    assert_eq!(message.nested_type_size(), 1);
+    assert_eq!(message.nested_type(0).name(), b"Nested");
+    assert_eq!(message.enum_type_size(), 1);
+    assert_eq!(message.enum_type(0).name(), b"NestedEnum");
+    assert_eq!(message.oneof_decl_size(), 1);
+    assert_eq!(message.oneof_decl(0).name(), b"o");
+    assert_eq!(message.reserved_range_size(), 2);
+    assert_eq!(message.reserved_range(0), 3..4);
+    assert_eq!(message.reserved_range(1), 5..7);
+    assert_eq!(message.reserved_name_size(), 1);
+    assert_eq!(message.reserved_name(0), b"d");
+    let fields = (0..message.field_size())
+        .map(|i| {
+            let field = message.field(i);
+            (
+                field.name(),
+                field.number(),
+                field.type_(),
+                field.label(),
+                field.type_name(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        [
+            (
+                &b"a"[..],
+                1,
+                Some(FieldType::Int64),
+                FieldLabel::Required,
+                &b""[..]
+            ),
+            (&b"b"[..], 2, None, FieldLabel::Repeated, &b"B"[..]),
+            (
+                &b"c"[..],
+                7,
+                Some(FieldType::String),
+                FieldLabel::Optional,
+                &b""[..]
+            ),
+            (
+                &b"x"[..],
+                8,
+                Some(FieldType::Int32),
+                FieldLabel::Optional,
+                &b""[..]
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_message_reflection() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("test.proto"),
+        br#"
+syntax = "proto3";
+
+message A {}
+"#
+        .to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+
+    let descriptor = proto.get_descriptor();
+    let field = |name: &[u8]| {
+        (0..descriptor.field_count())
+            .map(|i| descriptor.field(i))
+            .find(|field| field.name() == name)
+            .unwrap()
+    };
+
+    assert!(proto.has_field(field(b"name")));
+    assert_eq!(proto.get_field_string(field(b"name")), b"test.proto");
+    assert_eq!(proto.get_field_string(field(b"syntax")), b"proto3");
+    assert!(!proto.has_field(field(b"package")));
+    assert_eq!(proto.get_field_string(field(b"package")), b"");
+
+    let message_type = proto.message_type(0);
+    let message_type_name = message_type.get_descriptor().field(0);
+    assert_eq!(message_type.get_field_string(message_type_name), b"A");
+
+    for result in [
+        std::panic::catch_unwind(|| proto.has_field(field(b"dependency"))),
+        std::panic::catch_unwind(|| proto.get_field_bool(field(b"name"))),
+        std::panic::catch_unwind(|| proto.has_field(message_type_name)),
+    ] {
+        assert!(result.is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_message_reflection_getters() -> Result<(), Box<dyn Error>> {
+    fn new_message(type_name: &str, input: &str) -> Pin<Box<dyn Message>> {
+        let descriptor = DescriptorPool::generated_pool()
+            .find_message_type_by_name(type_name)
+            .unwrap();
+        let mut message = descriptor.new_generated_message().unwrap();
+        text_format::parse_from_str(input, message.as_mut()).unwrap();
+        message
+    }
+
+    fn field<'a>(message: &'a dyn Message, name: &str) -> &'a FieldDescriptor {
+        let descriptor = message.get_descriptor();
+        (0..descriptor.field_count())
+            .map(|i| descriptor.field(i))
+            .find(|field| field.name() == name.as_bytes())
+            .unwrap()
+    }
+
+    fn panic_message<T: fmt::Debug>(f: impl FnOnce() -> T) -> String {
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_err();
+        *payload.downcast::<String>().unwrap()
+    }
+
+    let int32 = new_message("google.protobuf.Int32Value", "value: -7");
+    let int64 = new_message("google.protobuf.Int64Value", "value: -1099511627776");
+    let uint32 = new_message("google.protobuf.UInt32Value", "value: 4294967295");
+    let uint64 = new_message("google.protobuf.UInt64Value", "value: 18446744073709551615");
+    let float = new_message("google.protobuf.FloatValue", "value: 1.5");
+    let double = new_message("google.protobuf.DoubleValue", "value: -2.25");
+    let bool = new_message("google.protobuf.BoolValue", "value: true");
+    let field_proto = new_message(
+        "google.protobuf.FieldDescriptorProto",
+        "number: 1 label: LABEL_REPEATED",
+    );
+
+    assert_eq!(int32.get_field_i32(field(&*int32, "value")), -7);
+    assert_eq!(int64.get_field_i64(field(&*int64, "value")), -(1 << 40));
+    assert_eq!(uint32.get_field_u32(field(&*uint32, "value")), u32::MAX);
+    assert_eq!(uint64.get_field_u64(field(&*uint64, "value")), u64::MAX);
+    assert_eq!(float.get_field_f32(field(&*float, "value")), 1.5);
+    assert_eq!(double.get_field_f64(field(&*double, "value")), -2.25);
+    assert!(bool.get_field_bool(field(&*bool, "value")));
+    // LABEL_REPEATED
+    assert_eq!(field_proto.get_field_enum(field(&*field_proto, "label")), 3);
+    // Unset fields report their default value.
+    // TYPE_DOUBLE
+    assert_eq!(field_proto.get_field_enum(field(&*field_proto, "type")), 1);
+    let empty = new_message("google.protobuf.Int64Value", "");
+    assert_eq!(empty.get_field_i64(field(&*empty, "value")), 0);
+
+    // Each getter rejects fields of the wrong type.
+    fn value(message: &dyn Message) -> &FieldDescriptor {
+        field(message, "value")
+    }
+    for (err, method, message, type_) in [
+        (
+            panic_message(|| int64.get_field_i32(value(&*int64))),
+            "get_field_i32",
+            "Int64Value",
+            "Int64",
+        ),
+        (
+            panic_message(|| uint64.get_field_i64(value(&*uint64))),
+            "get_field_i64",
+            "UInt64Value",
+            "Uint64",
+        ),
+        (
+            panic_message(|| int32.get_field_u32(value(&*int32))),
+            "get_field_u32",
+            "Int32Value",
+            "Int32",
+        ),
+        (
+            panic_message(|| int64.get_field_u64(value(&*int64))),
+            "get_field_u64",
+            "Int64Value",
+            "Int64",
+        ),
+        (
+            panic_message(|| double.get_field_f32(value(&*double))),
+            "get_field_f32",
+            "DoubleValue",
+            "Double",
+        ),
+        (
+            panic_message(|| float.get_field_f64(value(&*float))),
+            "get_field_f64",
+            "FloatValue",
+            "Float",
+        ),
+        (
+            panic_message(|| uint32.get_field_bool(value(&*uint32))),
+            "get_field_bool",
+            "UInt32Value",
+            "Uint32",
+        ),
+        (
+            panic_message(|| int32.get_field_enum(value(&*int32))),
+            "get_field_enum",
+            "Int32Value",
+            "Int32",
+        ),
+    ] {
+        assert_eq!(
+            err,
+            format!(
+                "Message::{}: field google.protobuf.{}.value has type {}",
+                method, message, type_
+            )
+        );
+    }
+
+    // Repeated fields and fields of other message types are rejected, too.
+    let location = new_message("google.protobuf.SourceCodeInfo.Location", "path: 1");
+    assert_eq!(
+        panic_message(|| location.get_field_i32(field(&*location, "path"))),
+        "Message::get_field_i32: field google.protobuf.SourceCodeInfo.Location.path is repeated"
+    );
+    assert_eq!(
+        panic_message(|| int32.get_field_i32(field(&*field_proto, "number"))),
+        "Message::get_field_i32: field google.protobuf.FieldDescriptorProto.number does not \
+         match message type google.protobuf.Int32Value"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_text_format_print() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree
+        .as_mut()
+        .add_file(Path::new("test.proto"), b"syntax = \"proto3\";".to_vec());
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let mut proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+    json::json_to_message(
+        r#"{"name":"caf\u00e9.proto","messageType":[{"name":"A"}]}"#,
+        proto.as_mut(),
+        ParseOptions::default(),
+    )?;
+
+    assert_eq!(
+        text_format::print_to_string(&*proto)?,
+        "name: \"caf\\303\\251.proto\"\nmessage_type {\n  name: \"A\"\n}\n"
+    );
+
+    assert_eq!(
+        format!("{:?}", proto),
+        r#"name: "caf\303\251.proto" message_type { name: "A" }"#
+    );
+    assert_eq!(
+        format!("{:#?}", &*proto as &dyn Message),
+        "name: \"caf\\303\\251.proto\"\nmessage_type {\n  name: \"A\"\n}\n"
+    );
+
+    let mut printer = Printer::new();
+    printer.as_mut().set_single_line_mode(true);
+    printer.as_mut().set_use_utf8_string_escaping(true);
+    assert_eq!(
+        printer.print_to_string(&*proto)?,
+        "name: \"caf\u{e9}.proto\" message_type { name: \"A\" } "
+    );
+    Ok(())
+}
+
+#[test]
+fn test_text_format_parse() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree
+        .as_mut()
+        .add_file(Path::new("test.proto"), b"syntax = \"proto3\";".to_vec());
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let mut proto = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+
+    text_format::parse_from_str(
+        r#"name: "a.proto" message_type { name: "A" }"#,
+        proto.as_mut(),
+    )?;
+    text_format::merge_from_str(r#"message_type { name: "B" }"#, proto.as_mut())?;
+    let mut printer = Printer::new();
+    printer.as_mut().set_single_line_mode(true);
+    assert_eq!(
+        printer.print_to_string(&*proto)?,
+        r#"name: "a.proto" message_type { name: "A" } message_type { name: "B" } "#
+    );
+
+    text_format::parse_from_str(r#"name: "b.proto""#, proto.as_mut())?;
+    assert_eq!(printer.print_to_string(&*proto)?, r#"name: "b.proto" "#);
+
+    let input = r#"name: "c.proto" bogus: 1"#;
+    let err = text_format::parse_from_str(input, proto.as_mut()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "operation failed: Error parsing text-format google.protobuf.FileDescriptorProto: \
+         1:22: Message type \"google.protobuf.FileDescriptorProto\" has no field named \"bogus\"."
+    );
+    assert!(text_format::parse_from_str("name: ", proto.as_mut()).is_err());
+    let mut parser = Parser::new();
+    parser.as_mut().allow_unknown_fields(true);
+    parser.as_mut().parse_from_str(input, proto.as_mut())?;
+    assert_eq!(printer.print_to_string(&*proto)?, r#"name: "c.proto" "#);
+
+    // `FileDescriptorProto` has no required fields, but its nested
+    // `UninterpretedOption.NamePart` does.
+    let input = r#"options { uninterpreted_option { name { name_part: "x" } } }"#;
+    assert!(text_format::parse_from_str(input, proto.as_mut()).is_err());
+    let mut parser = Parser::new();
+    parser.as_mut().allow_partial(true);
+    parser.as_mut().parse_from_str(input, proto.as_mut())?;
+    assert!(!proto.is_initialized());
+    assert_eq!(
+        proto.find_initialization_errors(),
+        ["options.uninterpreted_option[0].name[0].is_extension"]
+    );
+    let err = proto.serialize().unwrap_err();
+    assert_eq!(
+        err.message(),
+        Some("options.uninterpreted_option[0].name[0].is_extension")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_differencer() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree
+        .as_mut()
+        .add_file(Path::new("test.proto"), b"syntax = \"proto3\";".to_vec());
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let mut a = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+    let mut b = db.as_mut().find_file_by_name(Path::new("test.proto"))?;
+    text_format::parse_from_str(
+        r#"name: "a.proto" dependency: "x.proto" dependency: "y.proto""#,
+        a.as_mut(),
+    )?;
+    text_format::parse_from_str(
+        r#"name: "b.proto" dependency: "y.proto" dependency: "x.proto""#,
+        b.as_mut(),
+    )?;
+    assert!(!messages_equal(&*a, &*b));
+
+    let mut differencer = Differencer::new();
+    assert_eq!(
+        differencer.as_mut().diff(&*a, &*b),
+        Some(
+            "modified: name: \"a.proto\" -> \"b.proto\"\n\
+             modified: dependency[0]: \"x.proto\" -> \"y.proto\"\n\
+             modified: dependency[1]: \"y.proto\" -> \"x.proto\"\n"
+                .into()
+        )
+    );
+
+    b.as_mut().set_name("a.proto");
+    assert!(!differencer.as_mut().compare(&*a, &*b));
+    differencer.as_mut().set_repeated_as_set(true);
+    assert!(differencer.as_mut().compare(&*a, &*b));
+    assert_eq!(differencer.as_mut().diff(&*a, &*b), None);
+
+    let fds = FileDescriptorSet::new();
+    assert!(!messages_equal(&*a, &*fds));
+    assert_eq!(
+        differencer.as_mut().diff(&*a, &*fds),
+        Some(
+            "message types differ: google.protobuf.FileDescriptorProto vs \
+             google.protobuf.FileDescriptorSet\n"
+                .into()
+        )
+    );
+
+    a.as_mut().set_package("pkg");
+    differencer.as_mut().set_partial(true);
+    assert!(!differencer.as_mut().compare(&*a, &*b));
+    assert!(differencer.as_mut().compare(&*b, &*a));
+
+    // `==` considers NaN equal to NaN, unlike the default differencer.
+    text_format::parse_from_str(
+        "options { uninterpreted_option { double_value: nan } }",
+        a.as_mut(),
+    )?;
+    assert_eq!(*a, *a);
+    assert!(messages_equal(&*a, &*a));
+    assert!(!differencer.as_mut().compare(&*a, &*a));
+    Ok(())
+}
+
+/// Returns a source tree containing `root.proto`, which imports
+/// `imported.proto`.
+fn import_source_tree() -> Pin<Box<VirtualSourceTree>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("imported.proto"),
+        br#"
+syntax = "proto3";
+
+package test;
+
+message ImportMe {}
+"#
+        .to_vec(),
+    );
+    source_tree.as_mut().add_file(
+        Path::new("root.proto"),
+        br#"
+syntax = "proto3";
+
+package test;
+
+import "imported.proto";
+
+message Test {
+    ImportMe im = 1;
+}
+"#
+        .to_vec(),
+    );
+    source_tree
+}
+
+#[test]
+fn test_descriptor_pool_with_database() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = import_source_tree();
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let pool = DescriptorPool::with_database(db.as_mut());
+
+    // Symbols are not available until their file has been loaded.
+    assert!(pool.find_message_type_by_name("test.Test").is_none());
+    assert!(pool.find_file_by_name("missing.proto").is_none());
+
+    let file = pool.find_file_by_name("root.proto").unwrap();
+    assert_eq!(file.message_type(0).full_name(), b"test.Test");
+    let message = pool.find_message_type_by_name("test.ImportMe").unwrap();
+    assert_eq!(message.file().name(), b"imported.proto");
+    Ok(())
+}
+
+#[test]
+fn test_importer() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("dep.proto"),
+        br#"
+syntax = "proto3";
+
+package test;
+
+message Dep {}
+"#
+        .to_vec(),
+    );
+    source_tree.as_mut().add_file(
+        Path::new("root.proto"),
+        br#"
+syntax = "proto3";
+
+package test;
+
+import "dep.proto";
+
+message Root {
+    Dep dep = 1;
+}
+"#
+        .to_vec(),
+    );
+    source_tree
+        .as_mut()
+        .add_file(Path::new("bad.proto"), b"message {".to_vec());
+    let mut error_collector = SimpleErrorCollector::new();
+    let mut importer = Importer::new(source_tree.as_mut(), error_collector.as_mut());
+
+    let root = importer.as_mut().import(Path::new("root.proto"))?;
+    assert_eq!(root.message_type(0).full_name(), b"test.Root");
+    assert_eq!(root.message_type(0).field(0).type_(), FieldType::Message);
+    assert_eq!(root.package(), b"test");
+    assert_eq!(root.dependency_count(), 1);
+    assert_eq!(root.dependency(0).name(), b"dep.proto");
+    assert_eq!(root.dependency(0).dependency_count(), 0);
+    let root: *const _ = root;
+    let dep: *const _ = importer
+        .pool()
+        .find_message_type_by_name("test.Dep")
+        .unwrap();
+
+    // Importing a file that has already been built, either directly or as a
+    // dependency, returns the existing descriptor.
+    let dep_file = importer.as_mut().import(Path::new("dep.proto"))?;
+    assert!(std::ptr::eq(dep_file.message_type(0), dep));
+    assert!(std::ptr::eq(unsafe { (*root).dependency(0) }, dep_file));
+    let root_again = importer.as_mut().import(Path::new("root.proto"))?;
+    assert!(std::ptr::eq(root_again, root));
+
+    assert!(importer.as_mut().import(Path::new("bad.proto")).is_err());
+    assert!(importer
+        .as_mut()
+        .import(Path::new("missing.proto"))
+        .is_err());
+    drop(importer);
+    let errors = error_collector.as_mut().collect::<Vec<_>>();
+    assert!(errors.iter().any(|e| e.filename == "bad.proto"));
+    assert!(errors.iter().any(|e| e.filename == "missing.proto"));
+    Ok(())
+}
+
+#[test]
+fn test_virtual_source_tree() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("b.proto"),
+        b"syntax = \"proto3\"; message B {}".to_vec(),
+    );
+    source_tree.as_mut().add_file(
+        Path::new("a.proto"),
+        b"syntax = \"proto3\"; message A {}".to_vec(),
+    );
+    assert_eq!(
+        source_tree.file_names(),
+        [PathBuf::from("a.proto"), PathBuf::from("b.proto")]
+    );
+    assert!(source_tree.contains(Path::new("a.proto")));
+    assert!(!source_tree.contains(Path::new("c.proto")));
+
+    // Adding a file with an existing name replaces its contents.
+    source_tree.as_mut().add_file(
+        Path::new("a.proto"),
+        b"syntax = \"proto3\"; message C {}".to_vec(),
+    );
+    assert_eq!(source_tree.file_names().len(), 2);
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let file = db.as_mut().find_file_by_name(Path::new("a.proto"))?;
+    assert_eq!(file.message_type(0).name(), b"C");
+    drop(db);
+
+    assert!(source_tree.as_mut().remove_file(Path::new("a.proto")));
+    assert!(!source_tree.as_mut().remove_file(Path::new("a.proto")));
+    assert!(!source_tree.contains(Path::new("a.proto")));
+    assert_eq!(source_tree.file_names(), [PathBuf::from("b.proto")]);
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    assert!(db.as_mut().find_file_by_name(Path::new("a.proto")).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_custom_source_tree() -> Result<(), Box<dyn Error>> {
+    let mut opened = vec![];
+    let mut source_tree = CustomSourceTree::new(|filename| {
+        opened.push(filename.to_owned());
+        let contents: &'static [u8] = match filename.to_str() {
+            Some("dep.proto") => b"syntax = \"proto3\"; message Dep {}",
+            Some("root.proto") => {
+                b"syntax = \"proto3\"; import \"dep.proto\"; message Root { Dep dep = 1; }"
+            }
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "not in the bundle",
+                ))
+            }
+        };
+        let reader: Box<dyn std::io::Read> = Box::new(contents);
+        Ok(reader)
+    });
+    let mut error_collector = SimpleErrorCollector::new();
+    let mut importer = Importer::new(source_tree.as_mut(), error_collector.as_mut());
+    let root = importer.as_mut().import(Path::new("root.proto"))?;
+    assert_eq!(root.message_type(0).full_name(), b"Root");
+    assert!(importer
+        .as_mut()
+        .import(Path::new("missing.proto"))
+        .is_err());
+    drop(importer);
+
+    let errors = error_collector.as_mut().collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        vec![FileLoadError {
+            filename: "missing.proto".into(),
+            message: "not in the bundle".into(),
+            severity: Severity::Error,
+            location: None,
+        }]
+    );
+    drop(source_tree);
+    assert_eq!(
+        opened,
+        vec![
+            Path::new("root.proto"),
+            Path::new("dep.proto"),
+            Path::new("missing.proto")
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_build_file_errors() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = import_source_tree();
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let imported = db.as_mut().find_file_by_name(Path::new("imported.proto"))?;
+    let root = db.as_mut().find_file_by_name(Path::new("root.proto"))?;
+
+    // The root file cannot be built until its dependency is in the pool.
+    let mut pool = DescriptorPool::new();
+    let err = util::unwrap_err(pool.as_mut().build_file(&root));
+    assert_eq!(
+        err.message(),
+        Some(
+            "Invalid proto descriptor for file \"root.proto\":\n  \
+             imported.proto: Import \"imported.proto\" has not been loaded.\n  \
+             test.Test.im: \"ImportMe\" is not defined."
+        )
+    );
+    let mut error_collector = SimpleErrorCollector::new();
+    let res = pool
+        .as_mut()
+        .build_file_collecting_errors(&root, error_collector.as_mut());
+    assert_eq!(util::unwrap_err(res).message(), err.message());
+    let errors: Vec<_> = error_collector.as_mut().collect();
+    assert!(errors.contains(&FileLoadError {
+        filename: "root.proto".into(),
+        message: "Import \"imported.proto\" has not been loaded.".into(),
+        severity: Severity::Error,
+        location: None,
+    }));
+
+    pool.as_mut().build_file(&imported)?;
+    let file = pool
+        .as_mut()
+        .build_file_collecting_errors(&root, error_collector.as_mut())?;
+    assert_eq!(file.name(), b"root.proto");
+    assert_eq!(error_collector.as_mut().count(), 0);
+    Ok(())
+}
+
+#[test]
+fn test_log_handler() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("log_handler.proto"),
+        br#"
+syntax = "proto3";
+
+import "missing.proto";
+"#
+        .to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let file = db
+        .as_mut()
+        .find_file_by_name(Path::new("log_handler.proto"))?;
+
+    // The handler is process-wide, so ignore messages from concurrent tests.
+    let logs = Arc::new(Mutex::new(vec![]));
+    log::set_log_handler({
+        let logs = Arc::clone(&logs);
+        move |level, _filename, _line, message| {
+            if message.contains("log_handler.proto") || message.contains("missing.proto") {
+                logs.lock().unwrap().push((level, message.to_owned()));
+            }
+        }
+    });
+    let mut pool = DescriptorPool::new();
+    assert!(pool.as_mut().build_file(&file).is_err());
+    log::silence_logs();
+    assert!(pool.as_mut().build_file(&file).is_err());
+
+    let logs = logs.lock().unwrap();
+    assert_eq!(
+        *logs,
+        [
+            (
+                LogLevel::Error,
+                "Invalid proto descriptor for file \"log_handler.proto\":".into()
+            ),
+            (
+                LogLevel::Error,
+                "  missing.proto: Import \"missing.proto\" has not been loaded.".into()
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_simple_descriptor_database() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = import_source_tree();
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let fds = db
+        .as_mut()
+        .build_file_descriptor_set(&[Path::new("root.proto")])?;
+    let imported = db.as_mut().find_file_by_name(Path::new("imported.proto"))?;
+
+    let mut simple_db = SimpleDescriptorDatabase::new();
+    simple_db.as_mut().add(fds.file(1))?;
+    simple_db.as_mut().add_and_own(imported)?;
+    assert!(simple_db.as_mut().add(fds.file(0)).is_err());
+    assert_eq!(
+        simple_db.as_mut().find_all_file_names()?,
+        &[Path::new("imported.proto"), Path::new("root.proto")]
+    );
+    let root = simple_db
+        .as_mut()
+        .find_file_by_name(Path::new("root.proto"))?;
+    assert_eq!(root.message_type(0).name(), b"Test");
+    assert!(simple_db
+        .as_mut()
+        .find_file_by_name(Path::new("missing.proto"))
+        .is_err());
+
+    let file = simple_db
+        .as_mut()
+        .find_file_containing_symbol("test.ImportMe")?;
+    assert_eq!(file.message_type(0).name(), b"ImportMe");
+
+    let pool = DescriptorPool::with_database(simple_db.as_mut());
+    let message = pool.find_message_type_by_name("test.Test").unwrap();
+    assert_eq!(message.field(0).name(), b"im");
+    Ok(())
+}
+
+#[test]
+fn test_encoded_descriptor_database() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = import_source_tree();
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let root = db
+        .as_mut()
+        .find_file_by_name(Path::new("root.proto"))?
+        .serialize()?;
+    let imported = db
+        .as_mut()
+        .find_file_by_name(Path::new("imported.proto"))?
+        .serialize()?;
+
+    let mut encoded_db = EncodedDescriptorDatabase::new();
+    encoded_db.as_mut().add(&root)?;
+    encoded_db.as_mut().add_borrowed(&imported)?;
+    assert!(encoded_db.as_mut().add(&root).is_err());
+    assert!(encoded_db.as_mut().add(b"\xff").is_err());
+
+    let file = encoded_db
+        .as_mut()
+        .find_file_containing_symbol("test.Test")?;
+    assert_eq!(file.message_type(0).name(), b"Test");
+    assert!(encoded_db
+        .as_mut()
+        .find_file_containing_symbol("test.Missing")
+        .is_err());
+
+    // The pool can find symbols whose files have not yet been loaded.
+    let pool = DescriptorPool::with_database(encoded_db.as_mut());
+    let message = pool.find_message_type_by_name("test.Test").unwrap();
+    assert_eq!(message.file().name(), b"root.proto");
+    assert!(pool.find_message_type_by_name("test.Missing").is_none());
+    Ok(())
+}
+
+#[test]
+fn test_merged_descriptor_database() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("a.proto"),
+        b"syntax = \"proto3\"; package test; message Base {}".to_vec(),
+    );
+    source_tree.as_mut().add_file(
+        Path::new("b.proto"),
+        b"syntax = \"proto3\"; package test; message Shadowed {}".to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let mut base = SimpleDescriptorDatabase::new();
+    base.as_mut()
+        .add_and_own(db.as_mut().find_file_by_name(Path::new("a.proto"))?)?;
+    base.as_mut()
+        .add_and_own(db.as_mut().find_file_by_name(Path::new("b.proto"))?)?;
+
+    // The override replaces `a.proto` with a file that defines a different
+    // message.
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("a.proto"),
+        b"syntax = \"proto3\"; package test; message Override {}".to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let mut overrides = SimpleDescriptorDatabase::new();
+    overrides
+        .as_mut()
+        .add_and_own(db.as_mut().find_file_by_name(Path::new("a.proto"))?)?;
+
+    let mut merged = MergedDescriptorDatabase::new([
+        overrides.as_mut() as Pin<&mut (dyn DescriptorDatabase + Send)>,
+        base.as_mut(),
+    ]);
+    let a = merged.as_mut().find_file_by_name(Path::new("a.proto"))?;
+    assert_eq!(a.message_type(0).name(), b"Override");
+    let b = merged.as_mut().find_file_by_name(Path::new("b.proto"))?;
+    assert_eq!(b.message_type(0).name(), b"Shadowed");
+    assert!(merged
+        .as_mut()
+        .find_file_by_name(Path::new("c.proto"))
+        .is_err());
+
+    let file = merged
+        .as_mut()
+        .find_file_containing_symbol("test.Override")?;
+    assert_eq!(file.message_type(0).name(), b"Override");
+    let file = merged
+        .as_mut()
+        .find_file_containing_symbol("test.Shadowed")?;
+    assert_eq!(file.message_type(0).name(), b"Shadowed");
+    // `test.Base` is defined in the overridden version of `a.proto`.
+    assert!(merged
+        .as_mut()
+        .find_file_containing_symbol("test.Base")
+        .is_err());
+    Ok(())
+}
+
+#[test]
+fn test_descriptor_pool_from_file_descriptor_set() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("a.proto"),
+        b"syntax = \"proto3\"; package test; message A {}".to_vec(),
+    );
+    source_tree.as_mut().add_file(
+        Path::new("b.proto"),
+        b"syntax = \"proto3\"; package test; import \"a.proto\"; message B { A a = 1; }".to_vec(),
+    );
+    source_tree.as_mut().add_file(
+        Path::new("c.proto"),
+        b"syntax = \"proto3\"; package test; import \"b.proto\"; message C { B b = 1; }".to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let mut fds = db
+        .as_mut()
+        .build_file_descriptor_set(&[Path::new("c.proto")])?;
+    let names: Vec<_> = (0..fds.file_size()).map(|i| fds.file(i).name()).collect();
+    assert_eq!(names, &[&b"a.proto"[..], b"b.proto", b"c.proto"]);
+
+    // The files in the set need not be in dependency order.
+    fds.as_mut().clear_file();
+    for name in ["c.proto", "b.proto", "a.proto"] {
+        let file = db.as_mut().find_file_by_name(Path::new(name))?;
+        fds.as_mut().add_file().copy_from(&file);
+    }
+    let pool = DescriptorPool::build_from_file_descriptor_set(&fds)?;
+    let c = pool.find_message_type_by_name("test.C").unwrap();
+    assert_eq!(c.file().name(), b"c.proto");
+    assert!(pool.find_message_type_by_name("test.A").is_some());
+
+    // A set with a missing dependency cannot be built.
+    let b = db.as_mut().find_file_by_name(Path::new("b.proto"))?;
+    fds.as_mut().clear_file();
+    fds.as_mut().add_file().copy_from(&b);
+    assert!(DescriptorPool::build_from_file_descriptor_set(&fds).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_descriptor_pool_threads() -> Result<(), Box<dyn Error>> {
+    let mut source_tree = VirtualSourceTree::new();
+    source_tree.as_mut().add_file(
+        Path::new("a.proto"),
+        b"syntax = \"proto3\"; package test; message A {}".to_vec(),
+    );
+    let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+    let fds = db
+        .as_mut()
+        .build_file_descriptor_set(&[Path::new("a.proto")])?;
+
+    // Both a file descriptor set and the pool built from it can be moved
+    // between threads.
+    let pool = thread::spawn(move || DescriptorPool::build_from_file_descriptor_set(&fds))
+        .join()
+        .unwrap()?;
+
+    // Pools can be shared between threads, even when they load files lazily.
+    let lazy_pool = DescriptorPool::with_database(db.as_mut());
+    thread::scope(|s| {
+        for pool in [&pool, &lazy_pool] {
+            for _ in 0..4 {
+                s.spawn(move || {
+                    let file = pool.find_file_by_name("a.proto").unwrap();
+                    assert_eq!(file.message_type(0).full_name(), b"test.A");
+                });
+            }
+        }
+    });
+    Ok(())
+}
+
+#[test]
+fn test_check_compatibility() -> Result<(), Box<dyn Error>> {
+    fn build(source: &str) -> Result<Pin<Box<FileDescriptorSet>>, OperationFailedError> {
+        let mut source_tree = VirtualSourceTree::new();
+        source_tree
+            .as_mut()
+            .add_file(Path::new("test.proto"), source.as_bytes().to_vec());
+        let mut db = SourceTreeDescriptorDatabase::new(source_tree.as_mut());
+        db.as_mut()
+            .build_file_descriptor_set(&[Path::new("test.proto")])
+    }
+
+    let old = build(
+        r#"
+syntax = "proto3";
+package pkg;
+message M {
+    int32 a = 1;
+    string b = 2;
+    repeated int64 c = 3;
+    bytes d = 4;
+    int32 e = 5;
+    message Nested { bool f = 1; }
+}
+message Gone {}
+"#,
+    )?;
+    let new = build(
+        r#"
+syntax = "proto3";
+package pkg;
+message M {
+    int32 renamed = 1;
+    bytes b = 2;
+    int64 c = 3;
+    bytes d = 6;
+    message Nested { bool f = 1; }
+}
+"#,
+    )?;
+
+    assert_eq!(compat::check_compatibility(&old, &old), vec![]);
+    let incompatibilities = compat::check_compatibility(&old, &new);
+    assert_eq!(
+        incompatibilities,
+        vec![
+            Incompatibility {
+                message: "pkg.M".into(),
+                field: Some("b".into()),
+                kind: IncompatibilityKind::FieldTypeChanged {
+                    old: "string".into(),
+                    new: "bytes".into(),
+                },
+            },
+            Incompatibility {
+                message: "pkg.M".into(),
+                field: Some("c".into()),
+                kind: IncompatibilityKind::FieldLabelChanged {
+                    old: FieldLabel::Repeated,
+                    new: FieldLabel::Optional,
+                },
+            },
+            Incompatibility {
+                message: "pkg.M".into(),
+                field: Some("d".into()),
+                kind: IncompatibilityKind::FieldNumberChanged { old: 4, new: 6 },
+            },
+            Incompatibility {
+                message: "pkg.M".into(),
+                field: Some("e".into()),
+                kind: IncompatibilityKind::FieldRemoved,
+            },
+            Incompatibility {
+                message: "pkg.Gone".into(),
+                field: None,
+                kind: IncompatibilityKind::MessageRemoved,
+            },
+        ]
+    );
+    let messages: Vec<_> = incompatibilities.iter().map(|i| i.to_string()).collect();
+    assert_eq!(
+        messages,
+        [
+            "field pkg.M.b changed type from string to bytes",
+            "field pkg.M.c changed label from repeated to optional",
+            "field pkg.M.d changed number from 4 to 6",
+            "field pkg.M.e was removed",
+            "message pkg.Gone was removed",
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_file_descriptor_set_merge_dedup() -> Result<(), Box<dyn Error>> {
+    let include = protobuf_src::include();
+    let mut fds = compiler::compile(&[&include], &["google/protobuf/api.proto"])
+        .map_err(|_| "compile failed")?;
+    let other = compiler::compile(
+        &[&include],
+        &["google/protobuf/type.proto", "google/protobuf/struct.proto"],
+    )
+    .map_err(|_| "compile failed")?;
+    let names = |fds: &FileDescriptorSet| -> Vec<String> {
+        fds.iter()
+            .map(|f| String::from_utf8_lossy(f.name()).into_owned())
+            .collect()
+    };
+    assert_eq!(
+        names(&fds),
+        [
+            "google/protobuf/source_context.proto",
+            "google/protobuf/any.proto",
+            "google/protobuf/type.proto",
+            "google/protobuf/api.proto",
+        ]
+    );
+    assert!(names(&other).contains(&"google/protobuf/type.proto".to_string()));
+
+    fds.as_mut().merge_dedup(&other)?;
+    assert_eq!(
+        names(&fds),
+        [
+            "google/protobuf/source_context.proto",
+            "google/protobuf/any.proto",
+            "google/protobuf/type.proto",
+            "google/protobuf/api.proto",
+            "google/protobuf/struct.proto",
+        ]
+    );
+    DescriptorPool::build_from_file_descriptor_set(&fds)?;
+
+    let mut conflicting = other.clone();
+    conflicting.as_mut().file_mut(0).set_package("other");
+    let err = util::unwrap_err(fds.as_mut().merge_dedup(&conflicting));
+    assert_eq!(
+        err.message(),
+        Some(
+            "file google/protobuf/any.proto is defined differently in both descriptor \
+             sets"
+        )
+    );
+    assert_eq!(fds.file_size(), 5);
     Ok(())
 }
@@ -17,22 +17,226 @@ use std::env;
 use std::error::Error;
 use std::fs;
 use std::path::PathBuf;
+use std::process::Command;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    println!("cargo:rerun-if-env-changed=PROTOBUF_SRC_USE_SYSTEM");
+    println!("cargo:rerun-if-env-changed=PROTOC");
+    println!("cargo:rerun-if-env-changed=PKG_CONFIG");
+    println!("cargo:rerun-if-env-changed=PROTOBUF_SRC_CONFIGURE_ARGS");
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=protobuf");
+
+    let vendored_version = protobuf_version()?;
+    let installation = if env::var("PROTOBUF_SRC_USE_SYSTEM").as_deref() == Ok("1") {
+        match probe_system(&vendored_version) {
+            Ok(installation) => installation,
+            Err(e) => {
+                println!(
+                    "cargo:warning=unable to use system protobuf ({}); building vendored copy",
+                    e
+                );
+                build_vendored(vendored_version)?
+            }
+        }
+    } else {
+        build_vendored(vendored_version)?
+    };
+
+    println!(
+        "cargo:rustc-env=INCLUDE_DIR={}",
+        installation.include_dir.display()
+    );
+    println!("cargo:rustc-env=LIB_DIR={}", installation.lib_dir.display());
+    println!(
+        "cargo:rustc-env=PROTOC_PATH={}",
+        installation.protoc.display()
+    );
+    println!("cargo:rustc-env=PROTOBUF_VERSION={}", installation.version);
+    println!("cargo:root={}", installation.install_dir.display());
+    println!(
+        "cargo:CXXBRIDGE_DIR0={}",
+        installation.include_dir.display()
+    );
+    println!("cargo:INCLUDE_DIR={}", installation.include_dir.display());
+    println!("cargo:LIB_DIR={}", installation.lib_dir.display());
+    println!("cargo:LINK_KIND={}", installation.link_kind);
+    println!("cargo:ZLIB={}", if installation.zlib { 1 } else { 0 });
+    println!("cargo:VERSION={}", installation.version);
+    Ok(())
+}
+
+/// The location of a protobuf installation.
+struct Installation {
+    install_dir: PathBuf,
+    include_dir: PathBuf,
+    lib_dir: PathBuf,
+    protoc: PathBuf,
+    version: String,
+    link_kind: &'static str,
+    /// Whether libprotobuf was built with zlib support.
+    zlib: bool,
+}
+
+fn build_vendored(version: String) -> Result<Installation, Box<dyn Error>> {
     let out_dir = PathBuf::from(env::var("OUT_DIR")?);
     let install_dir = out_dir.join("install");
     fs::create_dir_all(&install_dir)?;
 
-    autotools::Config::new("protobuf")
+    let mut config = autotools::Config::new("protobuf");
+    config
         .disable("maintainer-mode", None)
-        .out_dir(&install_dir)
-        .build();
+        .with("zlib", None)
+        // Build position-independent code even for the static libraries, so
+        // that they can be linked into shared objects, like cdylibs.
+        .with("pic", None)
+        .out_dir(&install_dir);
+    let target = env::var("TARGET")?;
+    if target != env::var("HOST")? {
+        // Otherwise autotools infers the host system from the name of the C
+        // compiler, which fails for compilers that are not prefixed with the
+        // target triple, like clang.
+        config.config_option("host", Some(&*autoconf_triple(&target)));
+    }
+    if cfg!(feature = "shared") {
+        config.enable_shared().disable_static();
+    }
+    if cfg!(not(feature = "protoc")) {
+        // libprotoc is still built, as it is needed by code generators, but
+        // the protoc binary is skipped.
+        config.make_args(vec!["bin_PROGRAMS=".into()]);
+    }
+    if cfg!(feature = "optimize") {
+        // Appended after the flags derived from the Cargo profile, so this
+        // overrides the optimization level but preserves debug info.
+        config.cflag("-O2").cxxflag("-O2");
+    }
+    if let Ok(args) = env::var("PROTOBUF_SRC_CONFIGURE_ARGS") {
+        for arg in args.split_whitespace() {
+            let arg = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("configure argument {:?} does not start with --", arg))?;
+            match arg.split_once('=') {
+                Some((opt, optarg)) => config.config_option(opt, Some(optarg)),
+                None => config.config_option(arg, None),
+            };
+        }
+    }
+    config.build();
 
     // Move the build directory out of the installation directory.
     let _ = fs::remove_dir_all(out_dir.join("build"));
     fs::rename(install_dir.join("build"), out_dir.join("build"))?;
 
-    println!("cargo:rustc-env=INSTALL_DIR={}", install_dir.display());
-    println!("cargo:CXXBRIDGE_DIR0={}/include", install_dir.display());
-    Ok(())
+    // zlib support is enabled by default, but may be disabled via
+    // `PROTOBUF_SRC_CONFIGURE_ARGS=--without-zlib`.
+    let config = fs::read_to_string(out_dir.join("build").join("config.h"))?;
+    let zlib = config.lines().any(|line| line == "#define HAVE_ZLIB 1");
+
+    // Depending on the platform, autotools may install libraries into either
+    // `lib` or `lib64`.
+    let lib_dir = ["lib", "lib64"]
+        .iter()
+        .map(|dir| install_dir.join(dir))
+        .find(|dir| {
+            ["libprotobuf.a", "libprotobuf.so", "libprotobuf.dylib"]
+                .iter()
+                .any(|lib| dir.join(lib).exists())
+        })
+        .ok_or("unable to find installed libprotobuf")?;
+
+    Ok(Installation {
+        include_dir: install_dir.join("include"),
+        protoc: install_dir.join("bin").join("protoc"),
+        install_dir,
+        lib_dir,
+        version,
+        link_kind: link_kind(),
+        zlib,
+    })
+}
+
+/// Locates a system installation of protobuf via pkg-config.
+///
+/// The system installation is only used if its major and minor version match
+/// the vendored copy, as the libraries are otherwise unlikely to be
+/// compatible with dependents.
+fn probe_system(vendored_version: &str) -> Result<Installation, Box<dyn Error>> {
+    let version = pkg_config(&["--modversion", "protobuf"])?;
+    if major_minor(&version) != major_minor(vendored_version) {
+        return Err(format!(
+            "found version {}, but version {} is required",
+            version, vendored_version
+        )
+        .into());
+    }
+    let install_dir = PathBuf::from(pkg_config(&["--variable=prefix", "protobuf"])?);
+    let exec_prefix = PathBuf::from(pkg_config(&["--variable=exec_prefix", "protobuf"])?);
+    let protoc = match env::var_os("PROTOC") {
+        Some(protoc) => PathBuf::from(protoc),
+        None => exec_prefix.join("bin").join("protoc"),
+    };
+    // The private libraries of a libprotobuf built with zlib support include
+    // zlib.
+    let zlib = pkg_config(&["--static", "--libs", "protobuf"])?
+        .split_whitespace()
+        .any(|flag| flag == "-lz");
+    Ok(Installation {
+        include_dir: PathBuf::from(pkg_config(&["--variable=includedir", "protobuf"])?),
+        lib_dir: PathBuf::from(pkg_config(&["--variable=libdir", "protobuf"])?),
+        install_dir,
+        protoc,
+        version,
+        link_kind: "dylib",
+        zlib,
+    })
+}
+
+/// Runs pkg-config with the specified arguments and returns its trimmed
+/// output.
+fn pkg_config(args: &[&str]) -> Result<String, Box<dyn Error>> {
+    let pkg_config = env::var_os("PKG_CONFIG").unwrap_or_else(|| "pkg-config".into());
+    let output = Command::new(pkg_config).args(args).output()?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().into());
+    }
+    Ok(String::from_utf8(output.stdout)?.trim().into())
+}
+
+fn major_minor(version: &str) -> Option<(&str, &str)> {
+    let mut parts = version.split('.');
+    Some((parts.next()?, parts.next()?))
+}
+
+/// Converts a Rust target triple into a system triple that autoconf
+/// recognizes.
+fn autoconf_triple(target: &str) -> String {
+    let mut parts: Vec<_> = target.split('-').collect();
+    if let Some(arch) = parts[0].strip_suffix("gc") {
+        // riscv64gc => riscv64
+        parts[0] = arch;
+    }
+    if parts[1..] == ["pc", "windows", "gnu"] {
+        return format!("{}-w64-mingw32", parts[0]);
+    }
+    parts.join("-")
+}
+
+fn link_kind() -> &'static str {
+    if cfg!(feature = "shared") {
+        "dylib"
+    } else {
+        "static"
+    }
+}
+
+/// Extracts the version of the vendored protobuf from its autoconf script.
+fn protobuf_version() -> Result<String, Box<dyn Error>> {
+    let configure = fs::read_to_string("protobuf/configure.ac")?;
+    let version = configure
+        .lines()
+        .find_map(|line| line.strip_prefix("AC_INIT([Protocol Buffers],["))
+        .and_then(|rest| rest.split(']').next())
+        .ok_or("unable to determine protobuf version from configure.ac")?;
+    Ok(version.into())
 }
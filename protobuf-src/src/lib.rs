@@ -32,33 +32,172 @@
 //! but as a dependency for other crates that need libprotobuf or protoc
 //! available, like [prost-build].
 //!
-//! protobuf-src is currently bundling protobuf [v3.19.1].
+//! protobuf-src is currently bundling protobuf [v3.19.3]. The bundled version
+//! is also available at runtime via [`protoc_version`].
 //!
 //! To use this crate, declare a `dependency` or `dev-dependency` on
 //! `protobuf-src`. Then, in the build script for your crate, the environment
 //! variable `DEP_PROTOBUF_SRC_ROOT` will point to the directory in which the
-//! bundled copy of protobuf has been installed. You can build and link another
-//! C/C++ library against this copy of libprotobuf or generate Rust bindings and
-//! link Rust code against this copy of libprotobuf.
+//! bundled copy of protobuf has been installed, `DEP_PROTOBUF_SRC_INCLUDE_DIR`
+//! will point to the directory containing its headers, and
+//! `DEP_PROTOBUF_SRC_LIB_DIR` will point to the directory that contains the
+//! libraries. You can build and link another C/C++ library against this
+//! copy of libprotobuf or generate Rust bindings and link Rust code against
+//! this copy of libprotobuf. The libraries to link are listed by
+//! [`link_libraries`], and the version of protobuf, as returned by
+//! [`protoc_version`], is available as `DEP_PROTOBUF_SRC_VERSION`.
 //!
-//! If you simply need to invoke the vendored protoc binary, [`protoc`] returns
-//! the path to pass to [`std::process::Command`].
+//! # Shared libraries
+//!
+//! By default, libprotobuf is built as a static library. Enabling the `shared`
+//! feature builds shared libraries instead. The kind of library to pass to
+//! `cargo:rustc-link-lib` (`static` or `dylib`) is available to build scripts
+//! as `DEP_PROTOBUF_SRC_LINK_KIND`.
+//!
+//! libprotobuf is built with zlib support, which is required by the gzip
+//! streams in `google/protobuf/io/gzip_stream.h`, unless
+//! `PROTOBUF_SRC_CONFIGURE_ARGS` contains `--without-zlib`. Build scripts can
+//! check `DEP_PROTOBUF_SRC_ZLIB`, which is `1` if zlib support is enabled and
+//! `0` otherwise. Dependents that link libprotobuf statically must also link
+//! zlib when it is enabled.
+//!
+//! Cargo arranges for the shared libraries to be found when running binaries
+//! and tests via `cargo run` and `cargo test`. When running binaries by other
+//! means, the directory returned by [`lib_dir`] must be on the dynamic
+//! linker's search path, e.g. via `LD_LIBRARY_PATH` on Linux, or installed
+//! alongside the binary and located via an rpath.
+//!
+//! If you simply need to invoke the vendored protoc binary, [`protoc_command`]
+//! returns a [`std::process::Command`] that invokes protoc with the
+//! well-known types, like `google/protobuf/timestamp.proto`, on its import
+//! path. [`protoc`] returns the path to the binary itself.
+//!
+//! # Cross compilation
+//!
+//! When cross compiling, protobuf is configured for the target platform and
+//! built with the C and C++ compilers that the [cc] crate selects for the
+//! target, which can be overridden via the `CC` and `CXX` environment
+//! variables or their target-specific variants, like
+//! `CXX_aarch64_unknown_linux_musl`. The target platform's zlib must be
+//! available, or zlib support must be disabled by passing `--without-zlib` in
+//! `PROTOBUF_SRC_CONFIGURE_ARGS`.
+//!
+//! Note that the protoc binary is built for the target platform, too, and so
+//! typically cannot be run on the build machine.
+//!
+//! # System protobuf
+//!
+//! Building the vendored copy of protobuf is slow. If the environment variable
+//! `PROTOBUF_SRC_USE_SYSTEM` is set to `1`, this crate instead locates a system
+//! installation of protobuf via `pkg-config` and skips the vendored build. The
+//! system installation must have the same major and minor version as the
+//! vendored copy and provide shared libraries. The vendored copy is built as
+//! usual if no suitable system installation is found.
+//!
+//! With a system installation, [`protoc`] returns the path in the `PROTOC`
+//! environment variable, if set, and otherwise the protoc binary installed
+//! alongside libprotobuf.
+//!
+//! # Features
+//!
+//! The protoc binary is built by the `protoc` feature, which is enabled by
+//! default. Crates that only need the libraries can disable default features to
+//! reduce build times. libprotoc is built regardless.
+//!
+//! By default, libprotobuf is compiled with the optimization level and debug
+//! info settings of the Cargo profile. The `optimize` feature compiles
+//! libprotobuf with optimizations in all profiles, which is useful to speed up
+//! tests that run in the dev profile. Debug info still follows the Cargo
+//! profile.
+//!
+//! # Configure arguments
+//!
+//! Additional arguments for protobuf's `configure` script can be passed via
+//! the `PROTOBUF_SRC_CONFIGURE_ARGS` environment variable, separated by
+//! whitespace, e.g. `PROTOBUF_SRC_CONFIGURE_ARGS="--without-zlib
+//! --disable-dependency-tracking"`. Each argument must start with `--`.
 //!
 //! [Materialize]: https://materialize.com
 //! [Protocol Buffers]: https://developers.google.com/protocol-buffers
-//! [v3.19.1]: https://github.com/protocolbuffers/protobuf/releases/tag/v3.19.1
+//! [v3.19.3]: https://github.com/protocolbuffers/protobuf/releases/tag/v3.19.3
 //! [prost-build]: https://docs.rs/prost-build/latest/prost_build/
+//! [cc]: https://docs.rs/cc
 
 use std::path::PathBuf;
+use std::process::Command;
 
-/// Returns the path to the vendored protoc binary.
+/// Returns the path to the protoc binary.
+///
+/// # Panics
+///
+/// Panics if the `protoc` feature is disabled, as the binary is not built.
 pub fn protoc() -> PathBuf {
-    PathBuf::from(env!("INSTALL_DIR"))
-        .join("bin")
-        .join("protoc")
+    if cfg!(not(feature = "protoc")) {
+        panic!("protobuf-src was built without the protoc binary; enable the `protoc` feature");
+    }
+    PathBuf::from(env!("PROTOC_PATH"))
 }
 
-/// Returns the path to the vendored include directory.
+/// Returns a [`Command`] that invokes the protoc binary.
+///
+/// The command is preconfigured with a `--proto_path` argument that points at
+/// [`include`], so that .proto files can import the well-known types. Further
+/// arguments, like additional `--proto_path`s, output directives, and the
+/// input files, can be added with [`Command::arg`] and [`Command::args`].
+///
+/// # Panics
+///
+/// Panics if the `protoc` feature is disabled, as the binary is not built.
+pub fn protoc_command() -> Command {
+    let mut command = Command::new(protoc());
+    command.arg("--proto_path").arg(include());
+    command
+}
+
+/// Returns the path to the include directory.
 pub fn include() -> PathBuf {
-    PathBuf::from(env!("INSTALL_DIR")).join("include")
+    PathBuf::from(env!("INCLUDE_DIR"))
+}
+
+/// Returns the path to the directory containing the vendored libraries.
+///
+/// This is usually the `lib` directory of the installation, but may be `lib64`
+/// on some platforms.
+pub fn lib_dir() -> PathBuf {
+    PathBuf::from(env!("LIB_DIR"))
+}
+
+/// Returns the names of the vendored libraries, in the order in which they must
+/// be passed to the linker.
+///
+/// libprotoc is only required by code that uses the protocol compiler's
+/// libraries, like code generators. If libprotobuf was built with zlib
+/// support, it additionally requires the system's zlib, which is not included
+/// in this list.
+pub fn link_libraries() -> &'static [&'static str] {
+    &["protoc", "protobuf"]
+}
+
+/// Returns the version of the vendored protobuf, e.g. `"3.19.3"`.
+///
+/// This is the version of both protoc and libprotobuf. Only a single version
+/// of protobuf is bundled; to use a different version, depend on the release
+/// of protobuf-src whose build metadata names that version.
+///
+/// When a [system installation](crate#system-protobuf) of protobuf is used,
+/// this is instead the version of that installation, which may differ from the
+/// bundled version in its patch version.
+///
+/// The version is determined when protobuf-src is built. To compare it against
+/// a minimum version, split it into its numeric components:
+///
+/// ```
+/// let version: Vec<u32> = protobuf_src::protoc_version()
+///     .split('.')
+///     .map(|part| part.parse().unwrap())
+///     .collect();
+/// assert!(version >= vec![3, 19]);
+/// ```
+pub fn protoc_version() -> &'static str {
+    env!("PROTOBUF_VERSION")
 }
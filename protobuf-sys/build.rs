@@ -18,7 +18,7 @@ use std::path::PathBuf;
 
 fn main() {
     let include_paths = [
-        PathBuf::from(env::var("DEP_PROTOBUF_SRC_ROOT").unwrap()).join("include"),
+        PathBuf::from(env::var("DEP_PROTOBUF_SRC_INCLUDE_DIR").unwrap()),
         PathBuf::from("src"),
     ];
     autocxx_build::Builder::new("src/lib.rs", &include_paths)
@@ -27,8 +27,14 @@ fn main() {
         .compile("protobuf-sys");
     println!("cargo:rerun-if-changed=src/lib.rs");
     println!(
-        "cargo:rustc-link-search=native={}/lib",
-        env::var("DEP_PROTOBUF_SRC_ROOT").unwrap()
+        "cargo:rustc-link-search=native={}",
+        env::var("DEP_PROTOBUF_SRC_LIB_DIR").unwrap()
     );
-    println!("cargo:rustc-link-lib=static=protobuf");
+    let link_kind = env::var("DEP_PROTOBUF_SRC_LINK_KIND").unwrap();
+    println!("cargo:rustc-link-lib={}=protobuf", link_kind);
+    // A static libprotobuf built with zlib support requires linking zlib,
+    // while a shared libprotobuf links zlib itself.
+    if env::var("DEP_PROTOBUF_SRC_ZLIB").unwrap() == "1" && link_kind == "static" {
+        println!("cargo:rustc-link-lib=z");
+    }
 }
@@ -51,12 +51,19 @@
 //! [Protocol Buffers]: https://github.com/google/protobuf
 
 autocxx::include_cpp! {
+    #include "google/protobuf/descriptor.h"
     #include "google/protobuf/descriptor_database.h"
     #include "google/protobuf/compiler/importer.h"
     #include "google/protobuf/io/coded_stream.h"
+    #include "google/protobuf/message.h"
+    #include "google/protobuf/message_lite.h"
     #include "google/protobuf/util/json_util.h"
     #include "google/protobuf/util/time_util.h"
 
+    generate!("google::protobuf::DescriptorPool")
+    generate!("google::protobuf::Descriptor")
+    generate!("google::protobuf::FileDescriptor")
+    generate!("google::protobuf::FieldDescriptor")
     generate!("google::protobuf::DescriptorDatabase")
     generate!("google::protobuf::compiler::SourceTree")
     generate!("google::protobuf::compiler::Importer")
@@ -65,6 +72,8 @@ autocxx::include_cpp! {
     generate!("google::protobuf::io::ZeroCopyInputStream")
     generate!("google::protobuf::io::CodedOutputStream")
     generate!("google::protobuf::io::ZeroCopyOutputStream")
+    generate!("google::protobuf::MessageLite")
+    generate!("google::protobuf::Message")
     generate_pod!("google::protobuf::util::JsonParseOptions")
     generate_pod!("google::protobuf::util::JsonPrintOptions")
     generate_pod!("google::protobuf::util::TimeUtil")
@@ -14,6 +14,7 @@
 // limitations under the License.
 
 use protobuf_sys::google::protobuf::util::TimeUtil;
+use protobuf_sys::google::protobuf::{DescriptorPool, MessageLite};
 
 #[test]
 fn test_linkage() {
@@ -21,3 +22,35 @@ fn test_linkage() {
     let s = TimeUtil::ToString1(&TimeUtil::SecondsToDuration(42));
     assert_eq!(s.to_str().unwrap(), "42s");
 }
+
+#[test]
+fn test_message_lite_linkage() {
+    let duration = TimeUtil::SecondsToDuration(42);
+    // `Duration` derives from `MessageLite` via single inheritance, so the
+    // `MessageLite` subobject lives at the start of the `Duration`.
+    let message = unsafe { &*(&*duration as *const _ as *const MessageLite) };
+    assert_eq!(message.ByteSizeLong(), 2);
+    assert_eq!(message.SerializeAsString().as_bytes(), b"\x08\x2a");
+}
+
+#[test]
+fn test_descriptor_pool_linkage() {
+    let pool = unsafe { DescriptorPool::generated_pool().as_ref() }.unwrap();
+    let descriptor = unsafe {
+        pool.FindMessageTypeByName("google.protobuf.Duration")
+            .as_ref()
+    }
+    .unwrap();
+    assert_eq!(
+        descriptor.full_name().to_str().unwrap(),
+        "google.protobuf.Duration"
+    );
+    assert_eq!(descriptor.field_count().0, 2);
+    let field = unsafe { descriptor.FindFieldByName("nanos").as_ref() }.unwrap();
+    assert_eq!(field.number().0, 2);
+    let file = unsafe { descriptor.file().as_ref() }.unwrap();
+    assert_eq!(
+        file.name().to_str().unwrap(),
+        "google/protobuf/duration.proto"
+    );
+}